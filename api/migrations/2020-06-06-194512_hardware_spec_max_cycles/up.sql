@@ -0,0 +1,4 @@
+-- Default matches gdlk::MAX_CYCLE_COUNT, so existing specs keep their behavior
+ALTER TABLE hardware_specs
+    ADD COLUMN max_cycles INTEGER NOT NULL DEFAULT 1000000
+    CHECK(max_cycles >= 1);
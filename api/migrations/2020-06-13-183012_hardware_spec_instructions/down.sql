@@ -0,0 +1,3 @@
+ALTER TABLE hardware_specs
+    DROP COLUMN allowed_instructions,
+    DROP COLUMN disallowed_instructions;
@@ -0,0 +1,4 @@
+-- NULL means every instruction is allowed
+ALTER TABLE hardware_specs
+    ADD COLUMN allowed_instructions TEXT[],
+    ADD COLUMN disallowed_instructions TEXT[] NOT NULL DEFAULT '{}';
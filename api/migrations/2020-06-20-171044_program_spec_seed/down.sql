@@ -0,0 +1 @@
+ALTER TABLE program_specs DROP COLUMN seed;
@@ -0,0 +1,5 @@
+-- Seeds the RNG used by RAND, so that runs are reproducible. The seed is a
+-- u32 in gdlk::ProgramSpec, so it can't be negative.
+ALTER TABLE program_specs
+    ADD COLUMN seed INTEGER NOT NULL DEFAULT 0
+    CHECK(seed >= 0);
@@ -0,0 +1 @@
+ALTER TABLE hardware_specs DROP COLUMN memory_size;
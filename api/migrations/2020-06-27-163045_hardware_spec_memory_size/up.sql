@@ -0,0 +1,4 @@
+-- The upper bound matches gdlk::MAX_MEMORY_SIZE
+ALTER TABLE hardware_specs
+    ADD COLUMN memory_size INTEGER NOT NULL DEFAULT 0
+    CHECK(memory_size >= 0 AND memory_size <= 256);
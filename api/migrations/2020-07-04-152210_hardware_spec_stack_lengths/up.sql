@@ -0,0 +1,4 @@
+-- Empty means every stack uses max_stack_length
+ALTER TABLE hardware_specs
+    ADD COLUMN stack_lengths INTEGER[] NOT NULL DEFAULT '{}'
+    CHECK(cardinality(stack_lengths) <= num_stacks);
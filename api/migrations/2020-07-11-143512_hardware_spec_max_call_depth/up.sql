@@ -0,0 +1,4 @@
+-- Zero means CALL can't be used
+ALTER TABLE hardware_specs
+    ADD COLUMN max_call_depth INTEGER NOT NULL DEFAULT 0
+    CHECK(max_call_depth >= 0 AND max_call_depth <= 256);
@@ -0,0 +1,3 @@
+ALTER TABLE hardware_specs
+    DROP COLUMN num_queues,
+    DROP COLUMN max_queue_length;
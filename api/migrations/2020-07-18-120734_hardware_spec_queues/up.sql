@@ -0,0 +1,6 @@
+-- The upper bounds match the ones for stacks
+ALTER TABLE hardware_specs
+    ADD COLUMN num_queues INTEGER NOT NULL DEFAULT 0
+    CHECK(num_queues >= 0 AND num_queues <= 16),
+    ADD COLUMN max_queue_length INTEGER NOT NULL DEFAULT 0
+    CHECK(max_queue_length >= 0 AND max_queue_length <= 256);
@@ -0,0 +1,4 @@
+-- NULL means there's no limit on program length
+ALTER TABLE hardware_specs
+    ADD COLUMN max_instructions INTEGER
+    CHECK(max_instructions >= 1);
@@ -3,7 +3,7 @@ use diesel::{
     prelude::*, query_builder::InsertStatement, Identifiable, Queryable,
 };
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 /// A derivative of [HardwareSpec](gdlk::HardwareSpec), containing all fields.
 /// that are present on the DB table. This should only ever be constructed from
@@ -18,7 +18,7 @@ pub struct HardwareSpec {
     /// User-friendly name for this hardware.
     pub name: String,
 
-    // These three need to be i32s because postgres has no unsigned type.
+    // These need to be i32s because postgres has no unsigned type.
     // The insertion code and DB should both enforce that they are >= 0.
     /// Number of registers available
     pub num_registers: i32,
@@ -26,6 +26,25 @@ pub struct HardwareSpec {
     pub num_stacks: i32,
     /// Maximum size of each stack
     pub max_stack_length: i32,
+    /// Maximum number of cycles a program can run for
+    pub max_cycles: i32,
+    /// If not null, the only instructions that programs can use
+    pub allowed_instructions: Option<Vec<String>>,
+    /// Instructions that programs can't use
+    pub disallowed_instructions: Vec<String>,
+    /// Number of cells of random-access memory
+    pub memory_size: i32,
+    /// Maximum size of each individual stack, indexed by stack ID. Stacks
+    /// that aren't listed here use `max_stack_length`.
+    pub stack_lengths: Vec<i32>,
+    /// Maximum depth of nested subroutine calls
+    pub max_call_depth: i32,
+    /// Number of queues available
+    pub num_queues: i32,
+    /// Maximum size of each queue
+    pub max_queue_length: i32,
+    /// If not null, the maximum number of instructions in a compiled program
+    pub max_instructions: Option<i32>,
 }
 
 /// A derivative of [HardwareSpec](gdlk::HardwareSpec), meant for DB inserts.
@@ -34,6 +53,7 @@ pub struct HardwareSpec {
 /// field-level documentation.
 #[derive(Debug, PartialEq, Insertable, Validate)]
 #[table_name = "hardware_specs"]
+#[validate(schema(function = "validate_new_stack_lengths"))]
 pub struct NewHardwareSpec<'a> {
     #[validate(length(min = 1))]
     pub name: &'a str,
@@ -46,6 +66,24 @@ pub struct NewHardwareSpec<'a> {
     pub num_stacks: i32,
     #[validate(range(min = 0, max = 256))]
     pub max_stack_length: i32,
+    #[validate(range(min = 1, max = 1000000))]
+    pub max_cycles: i32,
+    #[validate(custom = "validate_instruction_names")]
+    pub allowed_instructions: Option<Vec<String>>,
+    #[validate(custom = "validate_instruction_names")]
+    pub disallowed_instructions: Vec<String>,
+    #[validate(range(min = 0, max = 256))]
+    pub memory_size: i32,
+    #[validate(custom = "validate_stack_length_values")]
+    pub stack_lengths: Vec<i32>,
+    #[validate(range(min = 0, max = 256))]
+    pub max_call_depth: i32,
+    #[validate(range(min = 0, max = 16))]
+    pub num_queues: i32,
+    #[validate(range(min = 0, max = 256))]
+    pub max_queue_length: i32,
+    #[validate(range(min = 1))]
+    pub max_instructions: Option<i32>,
 }
 
 impl NewHardwareSpec<'_> {
@@ -67,6 +105,15 @@ impl Default for NewHardwareSpec<'_> {
             num_registers: 1,
             num_stacks: 0,
             max_stack_length: 0,
+            max_cycles: gdlk::MAX_CYCLE_COUNT as i32,
+            allowed_instructions: None,
+            disallowed_instructions: Vec::new(),
+            memory_size: 0,
+            stack_lengths: Vec::new(),
+            max_call_depth: 0,
+            num_queues: 0,
+            max_queue_length: 0,
+            max_instructions: None,
         }
     }
 }
@@ -86,6 +133,7 @@ impl Factory for NewHardwareSpec<'_> {
 /// A struct used to modify a row in the hardware_specs table.
 #[derive(Clone, Debug, PartialEq, Identifiable, AsChangeset, Validate)]
 #[table_name = "hardware_specs"]
+#[validate(schema(function = "validate_modified_stack_lengths"))]
 pub struct ModifiedHardwareSpec<'a> {
     pub id: Uuid,
 
@@ -98,4 +146,108 @@ pub struct ModifiedHardwareSpec<'a> {
     pub num_stacks: Option<i32>,
     #[validate(range(min = 0, max = 256))]
     pub max_stack_length: Option<i32>,
+    #[validate(range(min = 1, max = 1000000))]
+    pub max_cycles: Option<i32>,
+    #[validate(custom = "validate_instruction_names")]
+    pub allowed_instructions: Option<Vec<String>>,
+    #[validate(custom = "validate_instruction_names")]
+    pub disallowed_instructions: Option<Vec<String>>,
+    #[validate(range(min = 0, max = 256))]
+    pub memory_size: Option<i32>,
+    #[validate(custom = "validate_stack_length_values")]
+    pub stack_lengths: Option<Vec<i32>>,
+    #[validate(range(min = 0, max = 256))]
+    pub max_call_depth: Option<i32>,
+    #[validate(range(min = 0, max = 16))]
+    pub num_queues: Option<i32>,
+    #[validate(range(min = 0, max = 256))]
+    pub max_queue_length: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_instructions: Option<i32>,
+}
+
+/// Makes sure every name in the list refers to a real
+/// [InstructionKind](gdlk::InstructionKind).
+fn validate_instruction_names(names: &[String]) -> Result<(), ValidationError> {
+    match names
+        .iter()
+        .find(|name| gdlk::InstructionKind::from_name(name).is_none())
+    {
+        None => Ok(()),
+        Some(name) => {
+            let mut error = ValidationError::new("instruction");
+            error.add_param("value".into(), name);
+            Err(error)
+        }
+    }
+}
+
+/// Converts instruction names to the uppercase form that the core crate
+/// expects, e.g. `mul` becomes `MUL`. The names must have already passed
+/// [validate_instruction_names].
+pub fn normalize_instruction_names(names: &mut [String]) {
+    for name in names {
+        *name = gdlk::InstructionKind::from_name(name)
+            .unwrap()
+            .name()
+            .into();
+    }
+}
+
+/// Makes sure every per-stack capacity is in the same range as
+/// `max_stack_length`.
+fn validate_stack_length_values(
+    stack_lengths: &[i32],
+) -> Result<(), ValidationError> {
+    match stack_lengths
+        .iter()
+        .find(|length| !(0..=256).contains(*length))
+    {
+        None => Ok(()),
+        Some(length) => {
+            let mut error = ValidationError::new("range");
+            error.add_param("min".into(), &0.0);
+            error.add_param("max".into(), &256.0);
+            error.add_param("value".into(), length);
+            Err(error)
+        }
+    }
+}
+
+/// Makes sure there aren't capacities listed for more stacks than the hardware
+/// has. The DB enforces this too, but checking here gives a better error.
+fn validate_stack_lengths_count(
+    stack_lengths: &[i32],
+    num_stacks: i32,
+) -> Result<(), ValidationError> {
+    if stack_lengths.len() as i32 > num_stacks {
+        let mut error = ValidationError::new("stack_lengths");
+        error.add_param("max".into(), &num_stacks);
+        error.add_param("value".into(), &stack_lengths.len());
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_new_stack_lengths(
+    hardware_spec: &NewHardwareSpec<'_>,
+) -> Result<(), ValidationError> {
+    validate_stack_lengths_count(
+        &hardware_spec.stack_lengths,
+        hardware_spec.num_stacks,
+    )
+}
+
+/// If only one of the two fields is being modified, the DB check catches any
+/// mismatch with the existing value of the other one.
+fn validate_modified_stack_lengths(
+    hardware_spec: &ModifiedHardwareSpec<'_>,
+) -> Result<(), ValidationError> {
+    match (&hardware_spec.stack_lengths, hardware_spec.num_stacks) {
+        (Some(stack_lengths), Some(num_stacks)) => {
+            validate_stack_lengths_count(stack_lengths, num_stacks)
+        }
+        _ => Ok(()),
+    }
 }
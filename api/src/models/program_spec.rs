@@ -35,6 +35,10 @@ pub struct ProgramSpec {
     /// The correct value to be left in the output when the program exits. The
     /// first element will be the first one pushed, and so on.
     pub expected_output: Vec<i32>,
+    /// Seed for the random number generator used by `RAND`. Stored as a
+    /// signed int because that's all Postgres and GraphQL give us. The DB and
+    /// validation keep it non-negative, so it always fits in the core `u32`.
+    pub seed: i32,
 }
 
 impl ProgramSpec {
@@ -70,6 +74,9 @@ pub struct NewProgramSpec<'a> {
     pub input: Vec<i32>,
     #[validate(length(max = 256))]
     pub expected_output: Vec<i32>,
+    // The core seed is a u32, so only the non-negative half of i32 is usable
+    #[validate(range(min = 0))]
+    pub seed: i32,
 }
 
 impl NewProgramSpec<'_> {
@@ -110,4 +117,6 @@ pub struct ModifiedProgramSpec<'a> {
     pub input: Option<Vec<i32>>,
     #[validate(length(max = 256))]
     pub expected_output: Option<Vec<i32>>,
+    #[validate(range(min = 0))]
+    pub seed: Option<i32>,
 }
@@ -6,6 +6,15 @@ table! {
         num_registers -> Int4,
         num_stacks -> Int4,
         max_stack_length -> Int4,
+        max_cycles -> Int4,
+        allowed_instructions -> Nullable<Array<Text>>,
+        disallowed_instructions -> Array<Text>,
+        memory_size -> Int4,
+        stack_lengths -> Array<Int4>,
+        max_call_depth -> Int4,
+        num_queues -> Int4,
+        max_queue_length -> Int4,
+        max_instructions -> Nullable<Int4>,
     }
 }
 
@@ -18,6 +27,7 @@ table! {
         hardware_spec_id -> Uuid,
         input -> Array<Int4>,
         expected_output -> Array<Int4>,
+        seed -> Int4,
     }
 }
 
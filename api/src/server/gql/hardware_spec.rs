@@ -85,6 +85,69 @@ impl HardwareSpecNodeFields for HardwareSpecNode {
         self.hardware_spec.max_stack_length
     }
 
+    fn field_max_cycles(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.hardware_spec.max_cycles
+    }
+
+    fn field_allowed_instructions(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> Option<Vec<String>> {
+        self.hardware_spec.allowed_instructions.clone()
+    }
+
+    fn field_disallowed_instructions(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> Vec<String> {
+        self.hardware_spec.disallowed_instructions.clone()
+    }
+
+    fn field_memory_size(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.hardware_spec.memory_size
+    }
+
+    fn field_stack_lengths(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> Vec<i32> {
+        self.hardware_spec.stack_lengths.clone()
+    }
+
+    fn field_max_call_depth(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.hardware_spec.max_call_depth
+    }
+
+    fn field_num_queues(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.hardware_spec.num_queues
+    }
+
+    fn field_max_queue_length(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.hardware_spec.max_queue_length
+    }
+
+    fn field_max_instructions(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> Option<i32> {
+        self.hardware_spec.max_instructions
+    }
+
     fn field_program_spec(
         &self,
         executor: &juniper::Executor<'_, RequestContext>,
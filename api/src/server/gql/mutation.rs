@@ -47,6 +47,15 @@ impl MutationFields for Mutation {
             num_registers: input.num_registers,
             num_stacks: input.num_stacks,
             max_stack_length: input.max_stack_length,
+            max_cycles: input.max_cycles,
+            allowed_instructions: input.allowed_instructions,
+            disallowed_instructions: input.disallowed_instructions,
+            memory_size: input.memory_size,
+            stack_lengths: input.stack_lengths,
+            max_call_depth: input.max_call_depth,
+            num_queues: input.num_queues,
+            max_queue_length: input.max_queue_length,
+            max_instructions: input.max_instructions,
         };
         let hardware_spec = view.execute()?;
 
@@ -68,6 +77,15 @@ impl MutationFields for Mutation {
             num_registers: input.num_registers,
             num_stacks: input.num_stacks,
             max_stack_length: input.max_stack_length,
+            max_cycles: input.max_cycles,
+            allowed_instructions: input.allowed_instructions,
+            disallowed_instructions: input.disallowed_instructions,
+            memory_size: input.memory_size,
+            stack_lengths: input.stack_lengths,
+            max_call_depth: input.max_call_depth,
+            num_queues: input.num_queues,
+            max_queue_length: input.max_queue_length,
+            max_instructions: input.max_instructions,
         };
         let hardware_spec = view.execute()?;
 
@@ -89,6 +107,7 @@ impl MutationFields for Mutation {
             description: &input.description,
             input: &input.input,
             expected_output: &input.expected_output,
+            seed: input.seed,
         };
         let program_spec = view.execute()?;
 
@@ -110,6 +129,7 @@ impl MutationFields for Mutation {
             description: input.description.as_deref(),
             input: input.input.as_deref(),
             expected_output: input.expected_output.as_deref(),
+            seed: input.seed,
         };
         let program_spec = view.execute()?;
 
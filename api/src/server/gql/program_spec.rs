@@ -96,6 +96,13 @@ impl ProgramSpecNodeFields for ProgramSpecNode {
             .collect()
     }
 
+    fn field_seed(
+        &self,
+        _executor: &juniper::Executor<'_, RequestContext>,
+    ) -> i32 {
+        self.program_spec.seed
+    }
+
     fn field_hardware_spec(
         &self,
         executor: &juniper::Executor<'_, RequestContext>,
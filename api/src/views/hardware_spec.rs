@@ -16,6 +16,24 @@ pub struct CreateHardwareSpecView<'a> {
     pub num_registers: i32,
     pub num_stacks: i32,
     pub max_stack_length: i32,
+    /// If not given, the default cycle limit will be used
+    pub max_cycles: Option<i32>,
+    /// If not given, all instructions will be allowed
+    pub allowed_instructions: Option<Vec<String>>,
+    /// If not given, no instructions will be disallowed
+    pub disallowed_instructions: Option<Vec<String>>,
+    /// If not given, there will be no memory
+    pub memory_size: Option<i32>,
+    /// If not given, every stack will use `max_stack_length`
+    pub stack_lengths: Option<Vec<i32>>,
+    /// If not given, subroutines can't be called
+    pub max_call_depth: Option<i32>,
+    /// If not given, there will be no queues
+    pub num_queues: Option<i32>,
+    /// If not given, queues can't hold any values
+    pub max_queue_length: Option<i32>,
+    /// If not given, programs can be any length
+    pub max_instructions: Option<i32>,
 }
 
 impl<'a> View for CreateHardwareSpecView<'a> {
@@ -23,13 +41,33 @@ impl<'a> View for CreateHardwareSpecView<'a> {
 
     fn execute(&self) -> ResponseResult<Self::Output> {
         // User a helper type to do the insert
-        let new_hardware_spec = models::NewHardwareSpec {
+        let mut new_hardware_spec = models::NewHardwareSpec {
             name: self.name,
             num_registers: self.num_registers,
             num_stacks: self.num_stacks,
             max_stack_length: self.max_stack_length,
+            max_cycles: self.max_cycles.unwrap_or(gdlk::MAX_CYCLE_COUNT as i32),
+            allowed_instructions: self.allowed_instructions.clone(),
+            disallowed_instructions: self
+                .disallowed_instructions
+                .clone()
+                .unwrap_or_default(),
+            memory_size: self.memory_size.unwrap_or(0),
+            stack_lengths: self.stack_lengths.clone().unwrap_or_default(),
+            max_call_depth: self.max_call_depth.unwrap_or(0),
+            num_queues: self.num_queues.unwrap_or(0),
+            max_queue_length: self.max_queue_length.unwrap_or(0),
+            max_instructions: self.max_instructions,
         };
         new_hardware_spec.validate()?;
+        // Names are validated case-insensitively, but always stored in
+        // uppercase so that the core crate can read them back
+        if let Some(names) = &mut new_hardware_spec.allowed_instructions {
+            models::normalize_instruction_names(names);
+        }
+        models::normalize_instruction_names(
+            &mut new_hardware_spec.disallowed_instructions,
+        );
 
         // Insert the new row and return the whole row
         let result: Result<models::HardwareSpec, _> = new_hardware_spec
@@ -55,6 +93,15 @@ pub struct UpdateHardwareSpecView<'a> {
     pub num_registers: Option<i32>,
     pub num_stacks: Option<i32>,
     pub max_stack_length: Option<i32>,
+    pub max_cycles: Option<i32>,
+    pub allowed_instructions: Option<Vec<String>>,
+    pub disallowed_instructions: Option<Vec<String>>,
+    pub memory_size: Option<i32>,
+    pub stack_lengths: Option<Vec<i32>>,
+    pub max_call_depth: Option<i32>,
+    pub num_queues: Option<i32>,
+    pub max_queue_length: Option<i32>,
+    pub max_instructions: Option<i32>,
 }
 
 impl<'a> View for UpdateHardwareSpecView<'a> {
@@ -62,14 +109,31 @@ impl<'a> View for UpdateHardwareSpecView<'a> {
 
     fn execute(&self) -> ResponseResult<Self::Output> {
         // User a helper type to do the insert
-        let modified_hardware_spec = models::ModifiedHardwareSpec {
+        let mut modified_hardware_spec = models::ModifiedHardwareSpec {
             id: self.id,
             name: self.name,
             num_registers: self.num_registers,
             num_stacks: self.num_stacks,
             max_stack_length: self.max_stack_length,
+            max_cycles: self.max_cycles,
+            allowed_instructions: self.allowed_instructions.clone(),
+            disallowed_instructions: self.disallowed_instructions.clone(),
+            memory_size: self.memory_size,
+            stack_lengths: self.stack_lengths.clone(),
+            max_call_depth: self.max_call_depth,
+            num_queues: self.num_queues,
+            max_queue_length: self.max_queue_length,
+            max_instructions: self.max_instructions,
         };
         modified_hardware_spec.validate()?;
+        // See CreateHardwareSpecView
+        if let Some(names) = &mut modified_hardware_spec.allowed_instructions {
+            models::normalize_instruction_names(names);
+        }
+        if let Some(names) = &mut modified_hardware_spec.disallowed_instructions
+        {
+            models::normalize_instruction_names(names);
+        }
 
         // Update the row, returning the new value. If the row doesn't exist,
         // this will return None.
@@ -17,6 +17,7 @@ pub struct CreateProgramSpecView<'a> {
     pub description: &'a str,
     pub input: &'a [i32],
     pub expected_output: &'a [i32],
+    pub seed: Option<i32>,
 }
 
 impl<'a> View for CreateProgramSpecView<'a> {
@@ -30,6 +31,7 @@ impl<'a> View for CreateProgramSpecView<'a> {
             description: self.description,
             input: self.input.into(),
             expected_output: self.expected_output.into(),
+            seed: self.seed.unwrap_or_default(),
         };
         new_program_spec.validate()?;
 
@@ -59,6 +61,7 @@ pub struct UpdateProgramSpecView<'a> {
     pub description: Option<&'a str>,
     pub input: Option<&'a [i32]>,
     pub expected_output: Option<&'a [i32]>,
+    pub seed: Option<i32>,
 }
 
 impl<'a> View for UpdateProgramSpecView<'a> {
@@ -72,6 +75,7 @@ impl<'a> View for UpdateProgramSpecView<'a> {
             description: self.description.as_deref(),
             input: self.input.map(Vec::from),
             expected_output: self.expected_output.map(Vec::from),
+            seed: self.seed,
         };
         modified_program_spec.validate()?;
 
@@ -14,12 +14,30 @@ static QUERY: &str = r#"
         $numRegisters: Int!,
         $numStacks: Int!,
         $maxStackLength: Int!,
+        $memorySize: Int,
+        $stackLengths: [Int!],
+        $maxCallDepth: Int,
+        $numQueues: Int,
+        $maxQueueLength: Int,
+        $maxInstructions: Int,
+        $maxCycles: Int,
+        $allowedInstructions: [String!],
+        $disallowedInstructions: [String!],
     ) {
         createHardwareSpec(input: {
             name: $name,
             numRegisters: $numRegisters,
             numStacks: $numStacks,
             maxStackLength: $maxStackLength,
+            memorySize: $memorySize,
+            stackLengths: $stackLengths,
+            maxCallDepth: $maxCallDepth,
+            numQueues: $numQueues,
+            maxQueueLength: $maxQueueLength,
+            maxInstructions: $maxInstructions,
+            maxCycles: $maxCycles,
+            allowedInstructions: $allowedInstructions,
+            disallowedInstructions: $disallowedInstructions,
         }) {
             hardwareSpecEdge {
                 node {
@@ -28,6 +46,15 @@ static QUERY: &str = r#"
                     numRegisters
                     numStacks
                     maxStackLength
+                    memorySize
+                    stackLengths
+                    maxCallDepth
+                    numQueues
+                    maxQueueLength
+                    maxInstructions
+                    maxCycles
+                    allowedInstructions
+                    disallowedInstructions
                 }
             }
         }
@@ -69,6 +96,15 @@ fn test_create_hardware_spec_success() {
                             "numRegisters": 3,
                             "numStacks": 2,
                             "maxStackLength": 16,
+                            "memorySize": 0,
+                            "stackLengths": [],
+                            "maxCallDepth": 0,
+                            "numQueues": 0,
+                            "maxQueueLength": 0,
+                            "maxInstructions": null,
+                            "maxCycles": 1000000,
+                            "allowedInstructions": null,
+                            "disallowedInstructions": [],
                         }
                     }
                 }
@@ -78,6 +114,190 @@ fn test_create_hardware_spec_success() {
     );
 }
 
+/// Test createHardwareSpec with a custom cycle limit
+#[test]
+fn test_create_hardware_spec_max_cycles() {
+    let mut context_builder = ContextBuilder::new();
+    context_builder.log_in();
+
+    let runner = QueryRunner::new(context_builder);
+    assert_eq!(
+        runner.query(
+            QUERY,
+            hashmap! {
+                "name" => InputValue::scalar("HW 1"),
+                "numRegisters" => InputValue::scalar(1),
+                "numStacks" => InputValue::scalar(0),
+                "maxStackLength" => InputValue::scalar(0),
+                "maxCycles" => InputValue::scalar(500),
+            }
+        ),
+        (
+            json!({
+                "createHardwareSpec": {
+                    "hardwareSpecEdge": {
+                        "node": {
+                            "name": "HW 1",
+                            "slug": "hw-1",
+                            "numRegisters": 1,
+                            "numStacks": 0,
+                            "maxStackLength": 0,
+                            "memorySize": 0,
+                            "stackLengths": [],
+                            "maxCallDepth": 0,
+                            "numQueues": 0,
+                            "maxQueueLength": 0,
+                            "maxInstructions": null,
+                            "maxCycles": 500,
+                            "allowedInstructions": null,
+                            "disallowedInstructions": [],
+                        }
+                    }
+                }
+            }),
+            vec![]
+        )
+    );
+}
+
+/// Test createHardwareSpec with restricted instructions. Names are accepted in
+/// any case, but stored in uppercase.
+#[test]
+fn test_create_hardware_spec_instructions() {
+    let mut context_builder = ContextBuilder::new();
+    context_builder.log_in();
+
+    let runner = QueryRunner::new(context_builder);
+    assert_eq!(
+        runner.query(
+            QUERY,
+            hashmap! {
+                "name" => InputValue::scalar("HW 1"),
+                "numRegisters" => InputValue::scalar(1),
+                "numStacks" => InputValue::scalar(0),
+                "maxStackLength" => InputValue::scalar(0),
+                "allowedInstructions" => InputValue::list(vec![
+                    InputValue::scalar("READ"),
+                    InputValue::scalar("Write"),
+                    InputValue::scalar("add"),
+                ]),
+                "disallowedInstructions" => InputValue::list(vec![
+                    InputValue::scalar("mul"),
+                ]),
+            }
+        ),
+        (
+            json!({
+                "createHardwareSpec": {
+                    "hardwareSpecEdge": {
+                        "node": {
+                            "name": "HW 1",
+                            "slug": "hw-1",
+                            "numRegisters": 1,
+                            "numStacks": 0,
+                            "maxStackLength": 0,
+                            "memorySize": 0,
+                            "stackLengths": [],
+                            "maxCallDepth": 0,
+                            "numQueues": 0,
+                            "maxQueueLength": 0,
+                            "maxInstructions": null,
+                            "maxCycles": 1000000,
+                            "allowedInstructions": ["READ", "WRITE", "ADD"],
+                            "disallowedInstructions": ["MUL"],
+                        }
+                    }
+                }
+            }),
+            vec![]
+        )
+    );
+}
+
+/// Test createHardwareSpec with per-stack capacities
+#[test]
+fn test_create_hardware_spec_stack_lengths() {
+    let mut context_builder = ContextBuilder::new();
+    context_builder.log_in();
+
+    let runner = QueryRunner::new(context_builder);
+    assert_eq!(
+        runner.query(
+            QUERY,
+            hashmap! {
+                "name" => InputValue::scalar("HW 1"),
+                "numRegisters" => InputValue::scalar(1),
+                "numStacks" => InputValue::scalar(2),
+                "maxStackLength" => InputValue::scalar(8),
+                "stackLengths" => InputValue::list(vec![
+                    InputValue::scalar(4),
+                ]),
+            }
+        ),
+        (
+            json!({
+                "createHardwareSpec": {
+                    "hardwareSpecEdge": {
+                        "node": {
+                            "name": "HW 1",
+                            "slug": "hw-1",
+                            "numRegisters": 1,
+                            "numStacks": 2,
+                            "maxStackLength": 8,
+                            "memorySize": 0,
+                            "stackLengths": [4],
+                            "maxCallDepth": 0,
+                            "numQueues": 0,
+                            "maxQueueLength": 0,
+                            "maxInstructions": null,
+                            "maxCycles": 1000000,
+                            "allowedInstructions": null,
+                            "disallowedInstructions": [],
+                        }
+                    }
+                }
+            }),
+            vec![]
+        )
+    );
+}
+
+/// [ERROR] Test createHardwareSpec with capacities for more stacks than the
+/// hardware has
+#[test]
+fn test_create_hardware_spec_too_many_stack_lengths() {
+    let mut context_builder = ContextBuilder::new();
+    context_builder.log_in();
+
+    let runner = QueryRunner::new(context_builder);
+    assert_eq!(
+        runner.query(
+            QUERY,
+            hashmap! {
+                "name" => InputValue::scalar("HW 1"),
+                "numRegisters" => InputValue::scalar(1),
+                "numStacks" => InputValue::scalar(1),
+                "maxStackLength" => InputValue::scalar(8),
+                "stackLengths" => InputValue::list(vec![
+                    InputValue::scalar(4),
+                    InputValue::scalar(4),
+                ]),
+            }
+        ),
+        (
+            serde_json::Value::Null,
+            vec![json!({
+                "locations": [{"line": 17, "column": 9}],
+                "message": "Input validation error(s)",
+                "path": ["createHardwareSpec"],
+                "extensions": {
+                    "__all__": [{"max": "1", "value": "2"}],
+                }
+            })]
+        )
+    );
+}
+
 /// [ERROR] Test createHardwareSpec when you try to use a pre-existing name
 #[test]
 fn test_create_hardware_spec_duplicate() {
@@ -106,7 +326,7 @@ fn test_create_hardware_spec_duplicate() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 8, "column": 9}],
+                "locations": [{"line": 17, "column": 9}],
                 "message": "This resource already exists",
                 "path": ["createHardwareSpec"],
             })]
@@ -129,12 +349,25 @@ fn test_create_hardware_spec_invalid_values() {
                 "numRegisters" => InputValue::scalar(0),
                 "numStacks" => InputValue::scalar(-1),
                 "maxStackLength" => InputValue::scalar(-1),
+                "memorySize" => InputValue::scalar(257),
+                "stackLengths" => InputValue::list(vec![
+                    InputValue::scalar(-1),
+                ]),
+                "maxCallDepth" => InputValue::scalar(257),
+                "numQueues" => InputValue::scalar(-1),
+                "maxQueueLength" => InputValue::scalar(-1),
+                "maxInstructions" => InputValue::scalar(0),
+                "maxCycles" => InputValue::scalar(0),
+                "disallowedInstructions" => InputValue::list(vec![
+                    InputValue::scalar("ADD"),
+                    InputValue::scalar("FOO"),
+                ]),
             }
         ),
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 8, "column": 9}],
+                "locations": [{"line": 17, "column": 9}],
                 "message": "Input validation error(s)",
                 "path": ["createHardwareSpec"],
                 "extensions": {
@@ -142,6 +375,14 @@ fn test_create_hardware_spec_invalid_values() {
                     "num_registers": [{"min": "1.0", "max": "16.0", "value": "0"}],
                     "num_stacks": [{"min": "0.0", "max": "16.0", "value": "-1"}],
                     "max_stack_length": [{"min": "0.0", "max": "256.0", "value": "-1"}],
+                    "memory_size": [{"min": "0.0", "max": "256.0", "value": "257"}],
+                    "stack_lengths": [{"min": "0.0", "max": "256.0", "value": "-1"}],
+                    "max_call_depth": [{"min": "0.0", "max": "256.0", "value": "257"}],
+                    "num_queues": [{"min": "0.0", "max": "16.0", "value": "-1"}],
+                    "max_queue_length": [{"min": "0.0", "max": "256.0", "value": "-1"}],
+                    "max_instructions": [{"min": "1.0", "value": "0"}],
+                    "max_cycles": [{"min": "1.0", "max": "1000000.0", "value": "0"}],
+                    "disallowed_instructions": [{"value": "\"FOO\""}],
                 }
             })]
         )
@@ -167,7 +408,7 @@ fn test_create_hardware_spec_not_logged_in() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 8, "column": 9}],
+                "locations": [{"line": 17, "column": 9}],
                 "message": "Not logged in",
                 "path": ["createHardwareSpec"],
             })]
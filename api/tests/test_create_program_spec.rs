@@ -30,6 +30,7 @@ static QUERY: &str = r#"
                     description
                     input
                     expectedOutput
+                    seed
                 }
             }
         }
@@ -75,6 +76,7 @@ fn test_create_program_spec_success() {
                             "description": "description!",
                             "input": [1, 2, 3],
                             "expectedOutput": [1, 2, 3],
+                            "seed": 0,
                         }
                     }
                 }
@@ -33,6 +33,13 @@ fn test_field_hardware_spec() {
                 numRegisters
                 numStacks
                 maxStackLength
+                memorySize
+                stackLengths
+                maxCallDepth
+                numQueues
+                maxQueueLength
+                maxInstructions
+                maxCycles
             }
         }
     "#;
@@ -55,6 +62,13 @@ fn test_field_hardware_spec() {
                     "numRegisters": 1,
                     "numStacks": 0,
                     "maxStackLength": 0,
+                    "memorySize": 0,
+                    "stackLengths": [],
+                    "maxCallDepth": 0,
+                    "numQueues": 0,
+                    "maxQueueLength": 0,
+                    "maxInstructions": null,
+                    "maxCycles": 1000000,
                 }
             }),
             vec![]
@@ -25,6 +25,7 @@ fn test_program_spec() {
         hardware_spec_id,
         input: vec![1, 2, 3],
         expected_output: vec![1, 2, 3],
+        seed: 42,
     }
     .create(conn)
     .id;
@@ -36,6 +37,7 @@ fn test_program_spec() {
                     slug
                     input
                     expectedOutput
+                    seed
                     hardwareSpec {
                         slug
                     }
@@ -61,6 +63,7 @@ fn test_program_spec() {
                         "slug": "prog1",
                         "input": vec![1, 2, 3],
                         "expectedOutput": vec![1, 2, 3],
+                        "seed": 42,
                         "hardwareSpec": {
                             "slug": "hw1"
                         }
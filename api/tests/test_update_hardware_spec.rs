@@ -15,6 +15,13 @@ static QUERY: &str = r#"
         $numRegisters: Int,
         $numStacks: Int,
         $maxStackLength: Int,
+        $memorySize: Int,
+        $stackLengths: [Int!],
+        $maxCallDepth: Int,
+        $numQueues: Int,
+        $maxQueueLength: Int,
+        $maxInstructions: Int,
+        $maxCycles: Int,
     ) {
         updateHardwareSpec(input: {
             id: $id,
@@ -22,6 +29,13 @@ static QUERY: &str = r#"
             numRegisters: $numRegisters,
             numStacks: $numStacks,
             maxStackLength: $maxStackLength,
+            memorySize: $memorySize,
+            stackLengths: $stackLengths,
+            maxCallDepth: $maxCallDepth,
+            numQueues: $numQueues,
+            maxQueueLength: $maxQueueLength,
+            maxInstructions: $maxInstructions,
+            maxCycles: $maxCycles,
         }) {
             hardwareSpecEdge {
                 node {
@@ -30,6 +44,13 @@ static QUERY: &str = r#"
                     numRegisters
                     numStacks
                     maxStackLength
+                    memorySize
+                    stackLengths
+                    maxCallDepth
+                    numQueues
+                    maxQueueLength
+                    maxInstructions
+                    maxCycles
                 }
             }
         }
@@ -69,6 +90,13 @@ fn test_update_hardware_spec_partial_modification() {
                             "numRegisters": 3,
                             "numStacks": 0,
                             "maxStackLength": 0,
+                            "memorySize": 0,
+                            "stackLengths": [],
+                            "maxCallDepth": 0,
+                            "numQueues": 0,
+                            "maxQueueLength": 0,
+                            "maxInstructions": null,
+                            "maxCycles": 1000000,
                         }
                     }
                 }
@@ -101,6 +129,12 @@ fn test_update_hardware_spec_full_modification() {
                 "numRegisters" => InputValue::scalar(10),
                 "numStacks" => InputValue::scalar(2),
                 "maxStackLength" => InputValue::scalar(16),
+                "memorySize" => InputValue::scalar(8),
+                "maxCycles" => InputValue::scalar(500),
+                "maxInstructions" => InputValue::scalar(100),
+                "numQueues" => InputValue::scalar(2),
+                "maxQueueLength" => InputValue::scalar(8),
+                "maxCallDepth" => InputValue::scalar(4),
             }
         ),
         (
@@ -113,6 +147,13 @@ fn test_update_hardware_spec_full_modification() {
                             "numRegisters": 10,
                             "numStacks": 2,
                             "maxStackLength": 16,
+                            "memorySize": 8,
+                            "stackLengths": [],
+                            "maxCallDepth": 4,
+                            "numQueues": 2,
+                            "maxQueueLength": 8,
+                            "maxInstructions": 100,
+                            "maxCycles": 500,
                         }
                     }
                 }
@@ -172,7 +213,7 @@ fn test_update_hardware_spec_empty_modification() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 16, "column": 9}],
                 "message": "No fields were given to update",
                 "path": ["updateHardwareSpec"],
             })]
@@ -211,7 +252,7 @@ fn test_update_hardware_spec_duplicate() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 16, "column": 9}],
                 "message": "This resource already exists",
                 "path": ["updateHardwareSpec"],
             })]
@@ -242,12 +283,18 @@ fn test_update_hardware_spec_invalid_values() {
                 "numRegisters" => InputValue::scalar(-1),
                 "numStacks" => InputValue::scalar(-1),
                 "maxStackLength" => InputValue::scalar(-1),
+                "memorySize" => InputValue::scalar(257),
+                "maxCallDepth" => InputValue::scalar(257),
+                "numQueues" => InputValue::scalar(-1),
+                "maxQueueLength" => InputValue::scalar(-1),
+                "maxInstructions" => InputValue::scalar(0),
+                "maxCycles" => InputValue::scalar(0),
             }
         ),
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 16, "column": 9}],
                 "message": "Input validation error(s)",
                 "path": ["updateHardwareSpec"],
                 "extensions": {
@@ -255,6 +302,12 @@ fn test_update_hardware_spec_invalid_values() {
                     "num_registers": [{"min": "1.0", "max": "16.0", "value": "-1"}],
                     "num_stacks": [{"min": "0.0", "max": "16.0", "value": "-1"}],
                     "max_stack_length": [{"min": "0.0", "max": "256.0", "value": "-1"}],
+                    "memory_size": [{"min": "0.0", "max": "256.0", "value": "257"}],
+                    "max_call_depth": [{"min": "0.0", "max": "256.0", "value": "257"}],
+                    "num_queues": [{"min": "0.0", "max": "16.0", "value": "-1"}],
+                    "max_queue_length": [{"min": "0.0", "max": "256.0", "value": "-1"}],
+                    "max_instructions": [{"min": "1.0", "value": "0"}],
+                    "max_cycles": [{"min": "1.0", "max": "1000000.0", "value": "0"}],
                 }
             })]
         )
@@ -289,7 +342,7 @@ fn test_update_hardware_spec_not_logged_in() {
             serde_json::Value::Null,
             vec![json!({
                 "message": "Not logged in",
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 16, "column": 9}],
                 "path": ["updateHardwareSpec"],
             })]
         )
@@ -15,6 +15,7 @@ static QUERY: &str = r#"
         $description: String,
         $input: [Int!],
         $expectedOutput: [Int!],
+        $seed: Int,
     ) {
         updateProgramSpec(input: {
             id: $id,
@@ -22,6 +23,7 @@ static QUERY: &str = r#"
             description: $description,
             input: $input,
             expectedOutput: $expectedOutput,
+            seed: $seed,
         }) {
             programSpecEdge {
                 node {
@@ -30,6 +32,7 @@ static QUERY: &str = r#"
                     description
                     input
                     expectedOutput
+                    seed
                 }
             }
         }
@@ -76,6 +79,7 @@ fn test_update_program_spec_partial_modification() {
                             "description": "description!",
                             "input": [],
                             "expectedOutput": [],
+                            "seed": 0,
                         }
                     }
                 }
@@ -118,6 +122,7 @@ fn test_update_program_spec_full_modification() {
                 "description" => InputValue::scalar("new description!"),
                 "input" => values_list.clone(),
                 "expectedOutput" => values_list,
+                "seed" => InputValue::scalar(1234),
             }
         ),
         (
@@ -130,6 +135,7 @@ fn test_update_program_spec_full_modification() {
                             "description": "new description!",
                             "input": [1, 2, 3],
                             "expectedOutput": [1, 2, 3],
+                            "seed": 1234,
                         }
                     }
                 }
@@ -202,7 +208,7 @@ fn test_update_program_spec_empty_modification() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 10, "column": 9}],
                 "message": "No fields were given to update",
                 "path": ["updateProgramSpec"],
             })]
@@ -248,7 +254,7 @@ fn test_update_program_spec_duplicate() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 10, "column": 9}],
                 "message": "This resource already exists",
                 "path": ["updateProgramSpec"],
             })]
@@ -281,16 +287,18 @@ fn test_update_program_spec_invalid_values() {
             hashmap! {
                 "id" => InputValue::scalar(program_spec.id.to_string()),
                 "name" => InputValue::scalar(""),
+                "seed" => InputValue::scalar(-1),
             }
         ),
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 10, "column": 9}],
                 "message": "Input validation error(s)",
                 "path": ["updateProgramSpec"],
                 "extensions": {
                     "name": [{"min": "1", "value": "\"\""}],
+                    "seed": [{"min": "0.0", "value": "-1"}],
                 }
             })]
         )
@@ -327,7 +335,7 @@ fn test_update_program_spec_not_logged_in() {
         (
             serde_json::Value::Null,
             vec![json!({
-                "locations": [{"line": 9, "column": 9}],
+                "locations": [{"line": 10, "column": 9}],
                 "message": "Not logged in",
                 "path": ["updateProgramSpec"],
             })]
@@ -1,9 +1,16 @@
 #![deny(clippy::all)]
 
-use failure::Fallible;
-use gdlk::{Compiler, HardwareSpec, ProgramSpec};
+use failure::{format_err, Fallible};
+use gdlk::{
+    ast::compiled::Program, cfg::ControlFlowGraph, format_source, Compiler,
+    HardwareSpec, ProgramSpec, SourceLoader, Span,
+};
 use serde::de::DeserializeOwned;
-use std::{fs, path::PathBuf, process};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 use structopt::StructOpt;
 
 /// The sub-command to execute.
@@ -36,6 +43,30 @@ enum Command {
         #[structopt(parse(from_os_str), long = "source", short = "s")]
         source_path: PathBuf,
     },
+
+    /// Format source code into the canonical style.
+    #[structopt(name = "fmt")]
+    Fmt {
+        /// Path to the source code file, which will be overwritten with the
+        /// formatted code
+        #[structopt(parse(from_os_str), long = "source", short = "s")]
+        source_path: PathBuf,
+        /// Don't write anything, just fail if the file isn't already formatted
+        #[structopt(long = "check")]
+        check: bool,
+    },
+
+    /// Print the control-flow graph of a program, in Graphviz DOT format.
+    #[structopt(name = "cfg")]
+    Cfg {
+        /// Path to the hardware spec file, in JSON format. If not provided, a
+        /// default hardware spec will be used.
+        #[structopt(parse(from_os_str), long = "hardware")]
+        hardware_spec_path: Option<PathBuf>,
+        /// Path to the source code file
+        #[structopt(parse(from_os_str))]
+        source_path: PathBuf,
+    },
 }
 
 /// GDLK executable, for compiling and executing GDLK programs
@@ -60,6 +91,34 @@ fn load_spec<T: Default + DeserializeOwned>(
     }
 }
 
+/// Loads files for `.include` directives from disk. Relative paths are
+/// resolved against the directory of the main source file.
+struct FileLoader {
+    root: PathBuf,
+}
+
+impl FileLoader {
+    fn new(source_path: &Path) -> Self {
+        Self {
+            root: source_path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        }
+    }
+}
+
+impl SourceLoader for FileLoader {
+    fn load(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(path)).ok()
+    }
+}
+
+/// Print any lint warnings from a compiled program, with their source.
+fn print_warnings(compiler: &Compiler<Program<Span>>) {
+    let warnings = compiler.warnings();
+    if !warnings.errors().is_empty() {
+        eprintln!("{:#}", warnings);
+    }
+}
+
 fn run(opt: Opt) -> Fallible<()> {
     match opt.cmd {
         // Compile and build the given program
@@ -69,9 +128,14 @@ fn run(opt: Opt) -> Fallible<()> {
         } => {
             let hw_spec: HardwareSpec = load_spec(&hardware_spec_path)?;
             // Read the source code from the file
-            let source = fs::read_to_string(source_path)?;
+            let source = fs::read_to_string(&source_path)?;
             // Compile
-            Compiler::compile(source, hw_spec)?;
+            let compiler = Compiler::compile_with_loader(
+                source,
+                hw_spec,
+                &FileLoader::new(&source_path),
+            )?;
+            print_warnings(&compiler);
         }
 
         // Compile and build the given program
@@ -85,11 +149,16 @@ fn run(opt: Opt) -> Fallible<()> {
             let program_spec: ProgramSpec = load_spec(&program_spec_path)?;
 
             // Read the source code from the file
-            let source = fs::read_to_string(source_path)?;
+            let source = fs::read_to_string(&source_path)?;
 
             // Compile and execute
-            let mut machine =
-                Compiler::compile(source, hw_spec)?.allocate(&program_spec);
+            let compiler = Compiler::compile_with_loader(
+                source,
+                hw_spec,
+                &FileLoader::new(&source_path),
+            )?;
+            print_warnings(&compiler);
+            let mut machine = compiler.allocate(&program_spec)?;
             let success = machine.execute_all().map_err(Clone::clone)?;
 
             println!(
@@ -97,16 +166,50 @@ fn run(opt: Opt) -> Fallible<()> {
 Stacks: {:?}
 Input: {:?}
 Output: {:?}
+Instructions: {}
 Cycles: {}
 Program completed with {}",
                 machine.registers(),
                 machine.stacks(),
                 machine.input(),
                 machine.output(),
+                machine.instruction_count(),
                 machine.cycle_count(),
                 if success { "SUCCESS" } else { "FAILURE" },
             );
         }
+
+        // Format the given program, or check that it's already formatted
+        Command::Fmt { source_path, check } => {
+            let source = fs::read_to_string(&source_path)?;
+            let formatted = format_source(&source);
+            if formatted != source {
+                if check {
+                    return Err(format_err!(
+                        "{} is not formatted",
+                        source_path.display()
+                    ));
+                }
+                fs::write(&source_path, formatted)?;
+            }
+        }
+
+        // Compile the given program, and print its control-flow graph
+        Command::Cfg {
+            hardware_spec_path,
+            source_path,
+        } => {
+            let hw_spec: HardwareSpec = load_spec(&hardware_spec_path)?;
+            let source = fs::read_to_string(&source_path)?;
+            let compiler = Compiler::compile_with_loader(
+                source,
+                hw_spec,
+                &FileLoader::new(&source_path),
+            )?;
+            print_warnings(&compiler);
+            let cfg = ControlFlowGraph::new(compiler.program());
+            print!("{}", cfg.to_dot(compiler.program(), compiler.sources()));
+        }
     }
     Ok(())
 }
@@ -5,15 +5,19 @@
 
 use crate::{
     consts::{
-        INPUT_LENGTH_REGISTER_REF, NULL_REGISTER_REF,
-        STACK_LENGTH_REGISTER_REF_TAG, STACK_REF_TAG, USER_REGISTER_REF_TAG,
+        CYCLE_COUNT_REGISTER_REF, INPUT_LENGTH_REGISTER_REF, NULL_REGISTER_REF,
+        PROGRAM_COUNTER_REGISTER_REF, QUEUE_LENGTH_REGISTER_REF_TAG,
+        QUEUE_REF_TAG, STACK_LENGTH_REGISTER_REF_TAG, STACK_REF_TAG,
+        USER_REGISTER_REF_TAG,
     },
     util::Span,
 };
 use std::fmt::{self, Display, Formatter};
 
-/// The type of every value in our language.
-pub type LangValue = i16;
+/// The type of every value in our language. This is wide enough to hold values
+/// of any [ValueWidth](crate::ValueWidth), but the values are always kept in
+/// the range of the width that the hardware uses.
+pub type LangValue = i32;
 
 /// A symbol used to identify a certain user register.
 pub type UserRegisterId = usize;
@@ -21,9 +25,16 @@ pub type UserRegisterId = usize;
 /// A symbol used to identify a certain stack.
 pub type StackId = usize;
 
+/// A symbol used to identify a certain queue.
+pub type QueueId = usize;
+
 /// A label for a certain point in the code.
 pub type Label = String;
 
+/// A name defined by a `.const` or `.alias` directive, which can be used in
+/// place of a value or register.
+pub type Symbol = String;
+
 /// A generic AST node container. This holds the AST node data itself, as well
 /// as some metadata (e.g. source span).
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -62,10 +73,45 @@ impl Display for StackRef {
     }
 }
 
+/// A stack argument to an instruction. This can be a fixed stack, or a stack
+/// whose index is held in a register. `R` is the type of the index register,
+/// which is a [source::RegisterOperand] in the source AST.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StackOperand<R = RegisterRef> {
+    /// A fixed stack, e.g. "S0"
+    Direct(StackRef),
+    /// The stack whose index is held in the given register, e.g. "S[RX0]".
+    /// The index is read at runtime, so it isn't validated until the stack is
+    /// actually used.
+    Indirect(R),
+}
+
+impl<R: Display> Display for StackOperand<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Direct(stack_ref) => write!(f, "{}", stack_ref),
+            Self::Indirect(index_reg) => {
+                write!(f, "{}[{}]", STACK_REF_TAG, index_reg)
+            }
+        }
+    }
+}
+
+/// A reference to a queue, e.g. "Q0". This should NOT be used for other uses
+/// of a queue ID, e.g. in the register "RQ0".
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QueueRef(pub QueueId);
+
+impl Display for QueueRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", QUEUE_REF_TAG, self.0)
+    }
+}
+
 /// A reference to a register. Registers can be readonly (in which case the
 /// value is a reflection of some other part of state), or read-write, which
 /// means the user can read and write freely from/to it.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum RegisterRef {
     /// This register is both readable and writable, but it also produces zero
     /// when read from, and anything written to it is thrown away.
@@ -73,11 +119,24 @@ pub enum RegisterRef {
     /// Read-only register that provides the number of elements remaining
     /// in the input buffer
     InputLength,
+    /// Read-only register that provides the index of the instruction that is
+    /// currently executing
+    ProgramCounter,
+    /// Read-only register that provides the number of cycles that have been
+    /// executed so far, including the current one
+    CycleCount,
     /// Read-only register that provides the current length of (i.e. the number
     /// of elements stored in) the referenced stack
     StackLength(StackId),
+    /// Read-only register that provides the current length of (i.e. the number
+    /// of elements stored in) the referenced queue
+    QueueLength(QueueId),
     /// User-writable register to be used for arbitrary computations
     User(UserRegisterId),
+    /// The user register whose index is held in another register, e.g.
+    /// "[RX0]". The index is read at runtime, so it isn't validated until the
+    /// reference is actually used.
+    Indirect(Box<RegisterRef>),
 }
 
 impl Display for RegisterRef {
@@ -85,19 +144,27 @@ impl Display for RegisterRef {
         match self {
             Self::Null => write!(f, "{}", NULL_REGISTER_REF),
             Self::InputLength => write!(f, "{}", INPUT_LENGTH_REGISTER_REF),
+            Self::ProgramCounter => {
+                write!(f, "{}", PROGRAM_COUNTER_REGISTER_REF)
+            }
+            Self::CycleCount => write!(f, "{}", CYCLE_COUNT_REGISTER_REF),
             Self::StackLength(stack_id) => {
                 write!(f, "{}{}", STACK_LENGTH_REGISTER_REF_TAG, stack_id)
             }
+            Self::QueueLength(queue_id) => {
+                write!(f, "{}{}", QUEUE_LENGTH_REGISTER_REF_TAG, queue_id)
+            }
             Self::User(reg_id) => {
                 write!(f, "{}{}", USER_REGISTER_REF_TAG, reg_id)
             }
+            Self::Indirect(index_reg) => write!(f, "[{}]", index_reg),
         }
     }
 }
 
 /// Something that can produce a [LangValue] idempotently. The value
 /// can be read (repeatedly if necessary), but cannot be written to.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ValueSource<T> {
     /// A static value, fixed at build time
     Const(Node<LangValue, T>),
@@ -114,59 +181,122 @@ pub enum ValueSource<T> {
 /// because we know that each operator will immediately progress to the next
 /// instruction.
 ///
-/// NOTE: All arithmetic operations are wrapping (for overflow/underflow).
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum Operator<T> {
+/// The operands are generic, so that the source AST can hold operands that
+/// haven't been resolved yet (see [source::RegisterOperand] and
+/// [source::ValueOperand]). `R` is the type of register operands, and `V` is
+/// the type of value operands.
+///
+/// NOTE: What happens when arithmetic overflows/underflows is determined by the
+/// hardware's [OverflowMode](crate::OverflowMode). By default, it wraps.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operator<T, R = RegisterRef, V = ValueSource<T>> {
     /// Reads one value from the input buffer to a register. If the input is
     /// empty, triggers a runtime error.
-    Read(Node<RegisterRef, T>),
+    Read(Node<R, T>),
     /// Writes a value to the output buffer.
-    Write(Node<ValueSource<T>, T>),
+    Write(Node<V, T>),
     /// Sets a register to a value.
-    Set(Node<RegisterRef, T>, Node<ValueSource<T>, T>),
+    Set(Node<R, T>, Node<V, T>),
     /// Adds two values. Puts the result in the first argument.
-    Add(Node<RegisterRef, T>, Node<ValueSource<T>, T>),
+    Add(Node<R, T>, Node<V, T>),
     /// Subtracts the second value from the first. Puts the result in the
     /// first argument.
-    Sub(Node<RegisterRef, T>, Node<ValueSource<T>, T>),
+    Sub(Node<R, T>, Node<V, T>),
     /// Multiplies the two values. Puts the result in the first argument.
-    Mul(Node<RegisterRef, T>, Node<ValueSource<T>, T>),
+    Mul(Node<R, T>, Node<V, T>),
     /// Divides the first value by the second. Puts the result in the first
     /// argument. Any remainder from the division is thrown away, i.e. the
     /// result is floored. If the divisor is zero, triggers a runtime error.
-    Div(Node<RegisterRef, T>, Node<ValueSource<T>, T>),
+    Div(Node<R, T>, Node<V, T>),
+    /// Divides the first value by the second, and puts the remainder in the
+    /// first argument. The result has the same sign as the dividend. If the
+    /// divisor is zero, triggers a runtime error.
+    Mod(Node<R, T>, Node<V, T>),
+    /// Negates the value in a register, i.e. multiplies it by -1.
+    Neg(Node<R, T>),
+    /// Bitwise AND of the two values. Puts the result in the first argument.
+    And(Node<R, T>, Node<V, T>),
+    /// Bitwise OR of the two values. Puts the result in the first argument.
+    Or(Node<R, T>, Node<V, T>),
+    /// Bitwise XOR of the two values. Puts the result in the first argument.
+    Xor(Node<R, T>, Node<V, T>),
+    /// Shifts the first value left by the number of bits given by the second
+    /// value. Puts the result in the first argument. The shift amount is
+    /// taken modulo the number of bits in a value.
+    Shl(Node<R, T>, Node<V, T>),
+    /// Shifts the first value right by the number of bits given by the second
+    /// value. The shift is arithmetic, i.e. the sign is preserved. Puts the
+    /// result in the first argument. The shift amount is taken modulo the
+    /// number of bits in a value.
+    Shr(Node<R, T>, Node<V, T>),
     /// Compares the last two arguments, and stores the comparison result in
     /// the first register. Result is -1 if the first value is less than the
     /// second, 0 if they are equal, and 1 if the first value is greater. The
     /// result will **never** be any value other than -1, 0, or 1.
-    Cmp(
-        Node<RegisterRef, T>,
-        Node<ValueSource<T>, T>,
-        Node<ValueSource<T>, T>,
-    ),
+    Cmp(Node<R, T>, Node<V, T>, Node<V, T>),
+    /// Sets a register to a pseudo-random value, anywhere in the range of the
+    /// hardware's value width. The values are drawn from a generator that is
+    /// seeded by the [ProgramSpec](crate::ProgramSpec), so every run of the
+    /// same program spec gets the same sequence of values.
+    Rand(Node<R, T>),
     /// Pushes the value in a register onto the given stack. If the stack is
     /// already at capacity, triggers a runtime error.
-    Push(Node<ValueSource<T>, T>, Node<StackRef, T>),
+    Push(Node<V, T>, Node<StackOperand<R>, T>),
     /// Pops the top value off the given stack into a register. If the stack is
     /// empty, triggers a runtime error.
-    Pop(Node<StackRef, T>, Node<RegisterRef, T>),
+    Pop(Node<StackOperand<R>, T>, Node<R, T>),
+    /// Copies the top value of the given stack into a register, without
+    /// removing it. If the stack is empty, triggers a runtime error.
+    Peek(Node<StackOperand<R>, T>, Node<R, T>),
+    /// Pushes a copy of the top value of the given stack onto the same stack.
+    /// If the stack is empty or already at capacity, triggers a runtime error.
+    Dup(Node<StackOperand<R>, T>),
+    /// Swaps the top two values of the given stack. If the stack has fewer
+    /// than two values, triggers a runtime error.
+    Swap(Node<StackOperand<R>, T>),
+    /// Adds a value to the back of the given queue. If the queue is already
+    /// at capacity, triggers a runtime error.
+    Enq(Node<V, T>, Node<QueueRef, T>),
+    /// Removes the value at the front of the given queue and puts it in a
+    /// register. If the queue is empty, triggers a runtime error.
+    Deq(Node<QueueRef, T>, Node<R, T>),
+    /// Loads the value at the given memory address into a register. If the
+    /// address is outside of memory, triggers a runtime error.
+    Load(Node<R, T>, Node<V, T>),
+    /// Stores a value at the given memory address. The first argument is the
+    /// value, the second is the address. If the address is outside of memory,
+    /// triggers a runtime error.
+    Store(Node<V, T>, Node<V, T>),
 }
 
 /// The different types of jumps. This just holds the jump type and conditional
 /// value, not the jump target. That should be held by the parent, because the
-/// target type can vary (label vs offset).
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum Jump<T> {
+/// target type can vary (label vs offset). `V` is the type of value operands,
+/// same as in [Operator].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Jump<T, V = ValueSource<T>> {
     /// Jumps unconditionally
     Jmp,
     /// Jumps if the value == 0
-    Jez(Node<ValueSource<T>, T>),
+    Jez(Node<V, T>),
     /// Jumps if the value != 0
-    Jnz(Node<ValueSource<T>, T>),
+    Jnz(Node<V, T>),
     /// Jumps if the value > 0
-    Jlz(Node<ValueSource<T>, T>),
+    Jlz(Node<V, T>),
     /// Jumps if the value < 0
-    Jgz(Node<ValueSource<T>, T>),
+    Jgz(Node<V, T>),
+    /// Jumps if the first value == the second value
+    Jeq(Node<V, T>, Node<V, T>),
+    /// Jumps if the first value != the second value
+    Jne(Node<V, T>, Node<V, T>),
+    /// Jumps if the first value < the second value
+    Jlt(Node<V, T>, Node<V, T>),
+    /// Jumps if the first value > the second value
+    Jgt(Node<V, T>, Node<V, T>),
+    /// Jumps if the first value <= the second value
+    Jle(Node<V, T>, Node<V, T>),
+    /// Jumps if the first value >= the second value
+    Jge(Node<V, T>, Node<V, T>),
 }
 
 /// All types unique to the source AST live here.
@@ -177,23 +307,178 @@ pub mod source {
     #[derive(Clone, Debug, PartialEq)]
     pub struct LabelDecl(pub Label);
 
+    /// A directive that defines a name, which can then be used in place of a
+    /// value or register elsewhere in the program. Names are resolved and
+    /// removed during validation, so these never reach the compiled AST.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Directive<T> {
+        /// Defines a named constant, e.g. ".const LIMIT 10"
+        Const(Node<Symbol, T>, Node<LangValue, T>),
+        /// Defines another name for a register, e.g. ".alias counter RX0"
+        Alias(Node<Symbol, T>, Node<RegisterRef, T>),
+    }
+
+    /// A register operand as it appears in the source. Aliases are resolved
+    /// into plain [RegisterRef]s during validation.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum RegisterOperand {
+        /// A register that's referenced directly, e.g. "RX0"
+        Register(RegisterRef),
+        /// A name defined by an `.alias` directive
+        Alias(Symbol),
+        /// The register whose index is held in another register, e.g.
+        /// "[RX0]" or "[counter]"
+        Indirect(Box<RegisterOperand>),
+    }
+
+    impl Display for RegisterOperand {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::Register(reg_ref) => write!(f, "{}", reg_ref),
+                Self::Alias(name) => write!(f, "{}", name),
+                Self::Indirect(index_reg) => write!(f, "[{}]", index_reg),
+            }
+        }
+    }
+
+    /// A value operand as it appears in the source. Symbols and expressions
+    /// are resolved into plain [ValueSource]s during validation.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum ValueOperand<T> {
+        /// A literal value, e.g. "3"
+        Const(Node<LangValue, T>),
+        /// A register, e.g. "RX0"
+        Register(Node<RegisterOperand, T>),
+        /// A name defined by a `.const` or `.alias` directive
+        Symbol(Node<Symbol, T>),
+        /// A constant expression, e.g. `3*4+1`, which is folded into a single
+        /// constant
+        Expr(Node<Expr<T>, T>),
+    }
+
+    /// An integer expression that can be evaluated at compile time. Each
+    /// sub-expression is wrapped in a [Node], so errors during evaluation can
+    /// point at the exact part of the expression that caused them.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum Expr<T> {
+        /// A literal value, e.g. `3`
+        Literal(LangValue),
+        /// A name defined by a `.const` directive
+        Symbol(Symbol),
+        /// The negation of an expression, e.g. `-LIMIT`
+        Neg(Box<Node<Expr<T>, T>>),
+        /// Two expressions combined by an arithmetic operator, e.g. `3*4`
+        Binary(BinaryOp, Box<Node<Expr<T>, T>>, Box<Node<Expr<T>, T>>),
+    }
+
+    /// An arithmetic operator in a constant [Expr].
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum BinaryOp {
+        Add,
+        Sub,
+        Mul,
+        Div,
+        Mod,
+    }
+
+    /// The statements inside an `IF`, `ELSE` or `WHILE` block.
+    pub type Block<T, R = RegisterOperand, V = ValueOperand<T>> =
+        Vec<Node<Statement<T, R, V>, T>>;
+
     /// A statement is one complete parseable element. Generally, each statement
-    /// goes on its own line in the source.
+    /// goes on its own line in the source. `R` and `V` are the types of
+    /// register and value operands, same as in [Operator]. They start out as
+    /// the source operand types, and are resolved during validation (see
+    /// [ResolvedStatement]).
     #[derive(Clone, Debug, PartialEq)]
-    pub enum Statement<T> {
+    pub enum Statement<T, R = RegisterOperand, V = ValueOperand<T>> {
         /// A label declaration
         Label(Node<LabelDecl, T>),
+        /// A `.const` or `.alias` directive
+        Directive(Node<Directive<T>, T>),
+        /// Pulls in the contents of another file, e.g. `.include "lib.gdlk"`.
+        /// These are expanded right after parsing, so they never make it to
+        /// validation.
+        Include(Node<String, T>),
         /// See [Operator]
-        Operator(Node<Operator<T>, T>),
+        Operator(Node<Operator<T, R, V>, T>),
         /// Jump to the given label
-        Jump(Node<Jump<T>, T>, Node<Label, T>),
+        Jump(Node<Jump<T, V>, T>, Node<Label, T>),
+        /// Jump unconditionally to the instruction index given by a value,
+        /// e.g. "JMP RX0"
+        ComputedJump(Node<V, T>),
+        /// Call the subroutine that starts at the given label
+        Call(Node<Label, T>),
+        /// Return from the current subroutine
+        Return,
+        /// Stop execution immediately
+        Halt,
+        /// A conditional block, e.g. "IF RX0 == 0 ... ELSE ... END". The
+        /// condition is stored as the jump that would be taken if it's true.
+        /// Blocks are lowered to labels and jumps during delabeling.
+        If {
+            condition: Node<Jump<T, V>, T>,
+            body: Block<T, R, V>,
+            /// The `ELSE` branch, if any. The span is the `ELSE` keyword.
+            else_body: Option<Node<Block<T, R, V>, T>>,
+        },
+        /// A loop block, e.g. "WHILE RX0 > 0 ... END". The condition is
+        /// stored the same way as in [Statement::If].
+        While {
+            condition: Node<Jump<T, V>, T>,
+            body: Block<T, R, V>,
+            /// The span of the `END` keyword, which jumps back to the top
+            end: T,
+        },
+    }
+
+    impl<T, R, V> Statement<T, R, V> {
+        /// Get the bodies of any blocks nested directly in this statement.
+        /// This is empty for statements that aren't blocks.
+        pub fn bodies(&self) -> Vec<&Block<T, R, V>> {
+            match self {
+                Self::If {
+                    body, else_body, ..
+                } => match else_body {
+                    Some(Node(else_body, _)) => vec![body, else_body],
+                    None => vec![body],
+                },
+                Self::While { body, .. } => vec![body],
+                _ => vec![],
+            }
+        }
+
+        /// Mutable version of [Self::bodies].
+        pub fn bodies_mut(&mut self) -> Vec<&mut Block<T, R, V>> {
+            match self {
+                Self::If {
+                    body, else_body, ..
+                } => match else_body {
+                    Some(Node(else_body, _)) => vec![body, else_body],
+                    None => vec![body],
+                },
+                Self::While { body, .. } => vec![body],
+                _ => vec![],
+            }
+        }
     }
 
     /// A parsed and untransformed program.
     #[derive(Clone, Debug, PartialEq)]
-    pub struct Program<T> {
-        pub body: Vec<Node<Statement<T>, T>>,
+    pub struct Program<T, R = RegisterOperand, V = ValueOperand<T>> {
+        pub body: Block<T, R, V>,
     }
+
+    /// A statement whose operands have all been resolved.
+    pub type ResolvedStatement<T> = Statement<T, RegisterRef, ValueSource<T>>;
+
+    /// A block of statements whose operands have all been resolved.
+    pub type ResolvedBlock<T> = Block<T, RegisterRef, ValueSource<T>>;
+
+    /// A program whose operands have all been resolved, so it only uses
+    /// operand types that are shared with the compiled AST. This is what
+    /// validation produces.
+    pub type ResolvedProgram<T> = Program<T, RegisterRef, ValueSource<T>>;
 }
 
 /// All types unique to the compiled AST live here.
@@ -202,7 +487,7 @@ pub mod compiled {
 
     /// An executable instruction. These are the instructions that machines
     /// actually execute.
-    #[derive(Copy, Clone, Debug, PartialEq)]
+    #[derive(Clone, Debug, PartialEq)]
     pub enum Instruction<T> {
         /// See [Operator]
         Operator(Node<Operator<T>, T>),
@@ -214,6 +499,22 @@ pub mod compiled {
         /// - `Jmp(2)` skips the next instruction
         /// - etc...
         Jump(Node<Jump<T>, T>, isize),
+        /// Jumps unconditionally to an instruction index that's determined at
+        /// runtime. Unlike [Instruction::Jump], the index is absolute, so
+        /// that it can be calculated from the `RPC` register. If the index
+        /// is outside the program, triggers a runtime error.
+        ComputedJump(Node<ValueSource<T>, T>),
+        /// Calls a subroutine. The offset is relative, just like in
+        /// [Instruction::Jump]. The address of the following instruction is
+        /// pushed onto the call stack, so that [Instruction::Return] can
+        /// resume from there.
+        Call(isize),
+        /// Pops the top address off the call stack and resumes execution
+        /// there.
+        Return,
+        /// Terminates the program, exactly as if execution had run off the
+        /// end of the instruction list.
+        Halt,
     }
 
     /// A compiled program, ready to be executed.
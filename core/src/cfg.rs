@@ -0,0 +1,731 @@
+//! Control-flow analysis of compiled programs. A [ControlFlowGraph] splits a
+//! program into basic blocks, i.e. runs of instructions that always execute
+//! together, and connects them with the ways that execution can move between
+//! them. From there, it can find the dominators of each block and the loops in
+//! the program, and render itself in Graphviz DOT format.
+//!
+//! ```
+//! use gdlk::{cfg::ControlFlowGraph, Compiler, HardwareSpec};
+//!
+//! let compiler = Compiler::compile(
+//!     "LOOP:\nREAD RX0\nWRITE RX0\nJGZ RLI LOOP".into(),
+//!     HardwareSpec::default(),
+//! )
+//! .unwrap();
+//! let cfg = ControlFlowGraph::new(compiler.program());
+//! assert_eq!(cfg.blocks().len(), 1);
+//! assert_eq!(cfg.loops()[0].header, 0);
+//! ```
+//!
+//! A few simplifications are made about how programs run:
+//!
+//! - A `CALL` is assumed to always return, so the instruction after it is its
+//!   fallthrough successor, and `RET` has no successors.
+//! - A computed jump whose target isn't a constant could land anywhere, so if a
+//!   program has one, every instruction gets its own block, and the jump gets
+//!   an edge to all of them.
+
+use crate::{
+    ast::{
+        compiled::{Instruction, Program},
+        Jump, Node, ValueSource,
+    },
+    util::{SourceFiles, Span},
+};
+use std::{collections::BTreeSet, fmt::Write, ops::Range};
+
+/// Identifies a basic block within a [ControlFlowGraph]. Blocks are numbered
+/// in program order, so the entry block is always `0`.
+pub type BlockId = usize;
+
+/// The different ways that execution can move from one block to another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution continues to the next instruction, either because the block
+    /// ended at a jump target, or a conditional jump wasn't taken
+    Fallthrough,
+    /// A jump was taken
+    Jump,
+    /// A subroutine was called
+    Call,
+}
+
+/// A directed edge from one block to another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub target: BlockId,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions that always execute together, in order. Execution
+/// can only enter a block at its first instruction, and only leave from its
+/// last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasicBlock {
+    /// Indexes of the instructions in this block
+    pub instructions: Range<usize>,
+    /// Blocks that execution can move to after this one. Empty if the program
+    /// can end (or return) after this block.
+    pub successors: Vec<Edge>,
+    /// Blocks that execution can come from before this one
+    pub predecessors: Vec<BlockId>,
+}
+
+/// A loop in the control-flow graph, made up of every block that can reach one
+/// of the loop's back edges without going through its header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NaturalLoop {
+    /// The only block that execution can enter the loop through. It dominates
+    /// every other block in the loop.
+    pub header: BlockId,
+    /// The blocks that jump back to the header
+    pub latches: Vec<BlockId>,
+    /// Every block in the loop, including the header
+    pub blocks: BTreeSet<BlockId>,
+}
+
+/// The dominator tree of a control-flow graph. Block `a` dominates block `b`
+/// if every path from the entry to `b` goes through `a`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dominators {
+    /// The immediate dominator of each block. The entry block is its own
+    /// immediate dominator, and unreachable blocks have none.
+    idoms: Vec<Option<BlockId>>,
+}
+
+impl Dominators {
+    /// Get the closest block that strictly dominates the given one. Returns
+    /// `None` for the entry block and for unreachable blocks.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        match self.idoms[block] {
+            Some(idom) if idom != block => Some(idom),
+            _ => None,
+        }
+    }
+
+    /// Check if block `a` dominates block `b`. Every reachable block
+    /// dominates itself. Unreachable blocks don't dominate, and aren't
+    /// dominated by, anything.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if self.idoms[a].is_none() || self.idoms[b].is_none() {
+            return false;
+        }
+        let mut current = b;
+        loop {
+            if current == a {
+                return true;
+            }
+            match self.immediate_dominator(current) {
+                Some(idom) => current = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Check if a block can be reached from the entry block.
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idoms[block].is_some()
+    }
+}
+
+/// The control-flow graph of a compiled program. See the module-level
+/// documentation for more info.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+    /// The block that each instruction belongs to
+    instruction_blocks: Vec<BlockId>,
+}
+
+impl ControlFlowGraph {
+    /// Build the control-flow graph for a program.
+    pub fn new(program: &Program<Span>) -> Self {
+        let instructions = &program.instructions;
+        let targets: Vec<Targets> = instructions
+            .iter()
+            .enumerate()
+            .map(|(i, instr)| {
+                Targets::new(i, instr.value(), instructions.len())
+            })
+            .collect();
+
+        // Figure out which instructions start a new block. Every jump target
+        // does, as well as every instruction after a jump.
+        let mut leaders = vec![false; instructions.len()];
+        let any_target = targets.iter().any(|t| t.any);
+        for (i, instr_targets) in targets.iter().enumerate() {
+            leaders[i] |= i == 0 || any_target;
+            for edge in &instr_targets.edges {
+                if edge.kind != EdgeKind::Fallthrough {
+                    leaders[edge.target] = true;
+                }
+            }
+            if instr_targets.ends_block && i + 1 < instructions.len() {
+                leaders[i + 1] = true;
+            }
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut instruction_blocks = Vec::with_capacity(instructions.len());
+        for (i, &is_leader) in leaders.iter().enumerate() {
+            if is_leader {
+                blocks.push(BasicBlock {
+                    instructions: i..i,
+                    successors: Vec::new(),
+                    predecessors: Vec::new(),
+                });
+            }
+            // unwrap is safe because the first instruction is always a leader
+            blocks.last_mut().unwrap().instructions.end = i + 1;
+            instruction_blocks.push(blocks.len() - 1);
+        }
+
+        // Connect each block to its successors, based on its last instruction
+        let num_blocks = blocks.len();
+        for block_id in 0..num_blocks {
+            let last = blocks[block_id].instructions.end - 1;
+            let successors: Vec<Edge> = if targets[last].any {
+                (0..num_blocks)
+                    .map(|target| Edge {
+                        target,
+                        kind: EdgeKind::Jump,
+                    })
+                    .collect()
+            } else {
+                targets[last]
+                    .edges
+                    .iter()
+                    .map(|edge| Edge {
+                        target: instruction_blocks[edge.target],
+                        kind: edge.kind,
+                    })
+                    .collect()
+            };
+            for edge in &successors {
+                let predecessors = &mut blocks[edge.target].predecessors;
+                if !predecessors.contains(&block_id) {
+                    predecessors.push(block_id);
+                }
+            }
+            blocks[block_id].successors = successors;
+        }
+        for block in &mut blocks {
+            block.predecessors.sort();
+        }
+
+        Self {
+            blocks,
+            instruction_blocks,
+        }
+    }
+
+    /// Get all the basic blocks in the graph, in program order.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Get the block that an instruction belongs to.
+    pub fn block_of(&self, instruction: usize) -> BlockId {
+        self.instruction_blocks[instruction]
+    }
+
+    /// Get the blocks in reverse postorder, starting from the entry. This is
+    /// an order where each block comes before its successors, other than along
+    /// back edges. Unreachable blocks are left out.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut order = Vec::with_capacity(self.blocks.len());
+        if self.blocks.is_empty() {
+            return order;
+        }
+
+        // Iterative DFS, so a long program can't overflow the stack. Each
+        // stack entry is a block and the index of its next successor to visit.
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((block, next)) = stack.pop() {
+            match self.blocks[block].successors.get(next) {
+                Some(edge) => {
+                    stack.push((block, next + 1));
+                    if !visited[edge.target] {
+                        visited[edge.target] = true;
+                        stack.push((edge.target, 0));
+                    }
+                }
+                None => order.push(block),
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// Compute the dominator tree, using the algorithm from "A Simple, Fast
+    /// Dominance Algorithm" by Cooper, Harvey and Kennedy.
+    pub fn dominators(&self) -> Dominators {
+        let order = self.reverse_postorder();
+        let mut order_index = vec![usize::MAX; self.blocks.len()];
+        for (i, &block) in order.iter().enumerate() {
+            order_index[block] = i;
+        }
+
+        let mut idoms: Vec<Option<BlockId>> = vec![None; self.blocks.len()];
+        if let Some(&entry) = order.first() {
+            idoms[entry] = Some(entry);
+        }
+
+        // Walk up the tree from both blocks until they meet
+        let intersect =
+            |idoms: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+                while a != b {
+                    while order_index[a] > order_index[b] {
+                        a = idoms[a].unwrap();
+                    }
+                    while order_index[b] > order_index[a] {
+                        b = idoms[b].unwrap();
+                    }
+                }
+                a
+            };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let new_idom = self.blocks[block]
+                    .predecessors
+                    .iter()
+                    .filter(|&&pred| idoms[pred].is_some())
+                    .fold(None, |idom, &pred| match idom {
+                        None => Some(pred),
+                        Some(idom) => Some(intersect(&idoms, pred, idom)),
+                    });
+                if idoms[block] != new_idom {
+                    idoms[block] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { idoms }
+    }
+
+    /// Find all the natural loops in the graph, ordered by header. A back edge
+    /// is an edge to a block that dominates its source, and each one forms a
+    /// loop. Loops that share a header are merged together.
+    pub fn loops(&self) -> Vec<NaturalLoop> {
+        let dominators = self.dominators();
+        let mut loops: Vec<NaturalLoop> = Vec::new();
+
+        for (block_id, block) in self.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                let header = edge.target;
+                if !dominators.dominates(header, block_id) {
+                    continue;
+                }
+
+                let index =
+                    match loops.iter().position(|lp| lp.header == header) {
+                        Some(index) => index,
+                        None => {
+                            loops.push(NaturalLoop {
+                                header,
+                                latches: Vec::new(),
+                                blocks: vec![header].into_iter().collect(),
+                            });
+                            loops.len() - 1
+                        }
+                    };
+                let natural_loop = &mut loops[index];
+                if !natural_loop.latches.contains(&block_id) {
+                    natural_loop.latches.push(block_id);
+                }
+
+                // Everything that reaches the latch without going through
+                // the header is in the loop. Unreachable blocks that jump in
+                // are left out, since the header doesn't dominate them.
+                let mut stack = vec![block_id];
+                while let Some(current) = stack.pop() {
+                    if natural_loop.blocks.insert(current) {
+                        stack.extend(
+                            self.blocks[current]
+                                .predecessors
+                                .iter()
+                                .filter(|&&pred| dominators.is_reachable(pred)),
+                        );
+                    }
+                }
+            }
+        }
+
+        loops.sort_by_key(|lp| lp.header);
+        loops
+    }
+
+    /// Render the graph in Graphviz DOT format. Each block is labelled with
+    /// the source of its instructions. Loop headers are drawn with a double
+    /// border.
+    pub fn to_dot(
+        &self,
+        program: &Program<Span>,
+        sources: &SourceFiles,
+    ) -> String {
+        let loop_headers: Vec<BlockId> =
+            self.loops().iter().map(|lp| lp.header).collect();
+        let mut dot = String::new();
+
+        // Writing to a String can't fail, so the unwraps are safe
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for (block_id, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for i in block.instructions.clone() {
+                let span = program.instructions[i].metadata();
+                let source = span.get_source_slice(sources.source(span.file));
+                // Generated jumps can span multiple lines, just show the first
+                let source = source.lines().next().unwrap_or("").trim();
+                label.push_str(&format!("{}: {}", i, escape_dot(source)));
+                label.push_str("\\l");
+            }
+            let peripheries = if loop_headers.contains(&block_id) {
+                ", peripheries=2"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "    b{} [label=\"{}\"{}];",
+                block_id, label, peripheries
+            )
+            .unwrap();
+        }
+        for (block_id, block) in self.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                let attrs = match edge.kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=dashed]",
+                };
+                writeln!(
+                    dot,
+                    "    b{} -> b{}{};",
+                    block_id, edge.target, attrs
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// Escape text so it can go in a quoted DOT string.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Where execution can go after a single instruction. The targets are
+/// instruction indexes, which are turned into blocks later.
+struct Targets {
+    edges: Vec<Edge>,
+    /// Execution could go to any instruction, because of a computed jump
+    any: bool,
+    /// The instruction can move somewhere other than the next instruction,
+    /// so it has to be the last in its block
+    ends_block: bool,
+}
+
+impl Targets {
+    fn new(i: usize, instr: &Instruction<Span>, len: usize) -> Self {
+        // Jumping to the end of the program (or past it) just ends execution,
+        // so there's no edge for it
+        let edge = |target: isize, kind| {
+            if 0 <= target && (target as usize) < len {
+                Some(Edge {
+                    target: target as usize,
+                    kind,
+                })
+            } else {
+                None
+            }
+        };
+        let offset = |offset: isize| i as isize + offset;
+        let next = edge(offset(1), EdgeKind::Fallthrough);
+
+        let (edges, any): (Vec<Option<Edge>>, bool) = match instr {
+            Instruction::Operator(_) => (vec![next], false),
+            Instruction::Jump(Node(Jump::Jmp, _), jump_offset) => {
+                (vec![edge(offset(*jump_offset), EdgeKind::Jump)], false)
+            }
+            Instruction::Jump(_, jump_offset) => (
+                vec![edge(offset(*jump_offset), EdgeKind::Jump), next],
+                false,
+            ),
+            Instruction::ComputedJump(Node(ValueSource::Const(target), _)) => {
+                (vec![edge(*target.value() as isize, EdgeKind::Jump)], false)
+            }
+            Instruction::ComputedJump(_) => (vec![], true),
+            Instruction::Call(call_offset) => (
+                vec![edge(offset(*call_offset), EdgeKind::Call), next],
+                false,
+            ),
+            Instruction::Return | Instruction::Halt => (vec![], false),
+        };
+
+        Self {
+            edges: edges.into_iter().flatten().collect(),
+            any,
+            ends_block: match instr {
+                Instruction::Operator(_) => false,
+                _ => true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, HardwareSpec};
+
+    fn compile(source: &str) -> Compiler<Program<Span>> {
+        Compiler::compile(
+            source.into(),
+            HardwareSpec {
+                num_registers: 2,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn build(source: &str) -> ControlFlowGraph {
+        ControlFlowGraph::new(compile(source).program())
+    }
+
+    fn edge(target: BlockId, kind: EdgeKind) -> Edge {
+        Edge { target, kind }
+    }
+
+    #[test]
+    fn test_basic_blocks() {
+        let cfg = build(
+            "
+            READ RX0
+            LOOP:
+            JEZ RX0 END
+            SUB RX0 1
+            WRITE RX0
+            JMP LOOP
+            END:
+            HALT
+            WRITE RX0
+            ",
+        );
+        assert_eq!(
+            cfg.blocks(),
+            &[
+                BasicBlock {
+                    instructions: 0..1,
+                    successors: vec![edge(1, EdgeKind::Fallthrough)],
+                    predecessors: vec![],
+                },
+                BasicBlock {
+                    instructions: 1..2,
+                    successors: vec![
+                        edge(3, EdgeKind::Jump),
+                        edge(2, EdgeKind::Fallthrough)
+                    ],
+                    predecessors: vec![0, 2],
+                },
+                BasicBlock {
+                    instructions: 2..5,
+                    successors: vec![edge(1, EdgeKind::Jump)],
+                    predecessors: vec![1],
+                },
+                BasicBlock {
+                    instructions: 5..6,
+                    successors: vec![],
+                    predecessors: vec![1],
+                },
+                BasicBlock {
+                    instructions: 6..7,
+                    successors: vec![],
+                    predecessors: vec![],
+                },
+            ]
+        );
+        assert_eq!(cfg.block_of(3), 2);
+        assert_eq!(cfg.reverse_postorder(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_dominators() {
+        let cfg = build(
+            "
+            READ RX0
+            IF RX0 > 0
+                WRITE 1
+            ELSE
+                WRITE 2
+            END
+            WRITE RX0
+            HALT
+            WRITE 3
+            ",
+        );
+        // 0: READ, JLE  1: WRITE 1, JMP  2: WRITE 2  3: WRITE RX0, HALT
+        // 4: WRITE 3
+        let dominators = cfg.dominators();
+        assert_eq!(dominators.immediate_dominator(0), None);
+        assert_eq!(dominators.immediate_dominator(1), Some(0));
+        assert_eq!(dominators.immediate_dominator(2), Some(0));
+        assert_eq!(dominators.immediate_dominator(3), Some(0));
+        assert_eq!(dominators.immediate_dominator(4), None);
+        assert!(dominators.dominates(0, 3));
+        assert!(dominators.dominates(3, 3));
+        assert!(!dominators.dominates(1, 3));
+        assert!(!dominators.dominates(0, 4));
+        assert!(!dominators.is_reachable(4));
+    }
+
+    #[test]
+    fn test_loops() {
+        let cfg = build(
+            "
+            WHILE RLI > 0
+                READ RX0
+                WHILE RX0 > 0
+                    SUB RX0 1
+                    JEZ RX1 1f
+                END
+                1:
+            END
+            ",
+        );
+        // 0: JLE outer  1: READ  2: JLE inner  3: SUB, JEZ  4: JMP inner
+        // 5: JMP outer
+        assert_eq!(
+            cfg.loops(),
+            vec![
+                NaturalLoop {
+                    header: 0,
+                    latches: vec![5],
+                    blocks: vec![0, 1, 2, 3, 4, 5].into_iter().collect(),
+                },
+                NaturalLoop {
+                    header: 2,
+                    latches: vec![4],
+                    blocks: vec![2, 3, 4].into_iter().collect(),
+                },
+            ]
+        );
+        let dominators = cfg.dominators();
+        assert!(dominators.dominates(2, 5));
+        assert!(!dominators.dominates(3, 5));
+
+        // Dead code that jumps into a loop isn't part of it
+        let cfg = build(
+            "
+            LOOP:
+                READ RX0
+            BODY:
+                WRITE RX0
+                JGT RLI 0 LOOP
+            HALT
+            JMP BODY
+            ",
+        );
+        // 0: READ  1: WRITE, JGT  2: HALT  3: JMP BODY
+        assert_eq!(
+            cfg.loops(),
+            vec![NaturalLoop {
+                header: 0,
+                latches: vec![1],
+                blocks: vec![0, 1].into_iter().collect(),
+            }]
+        );
+        assert!(!cfg.dominators().is_reachable(3));
+    }
+
+    #[test]
+    fn test_calls_and_computed_jumps() {
+        let cfg = build(
+            "
+            CALL SUB
+            HALT
+            SUB:
+            WRITE 1
+            RET
+            ",
+        );
+        assert_eq!(
+            cfg.blocks()[0].successors,
+            vec![edge(2, EdgeKind::Call), edge(1, EdgeKind::Fallthrough)]
+        );
+        assert_eq!(cfg.blocks()[2].successors, vec![]);
+
+        // A constant target is just a jump
+        let cfg = build(
+            "
+            JMP 2
+            WRITE 1
+            WRITE 2
+            ",
+        );
+        assert_eq!(cfg.blocks().len(), 3);
+        assert_eq!(cfg.blocks()[0].successors, vec![edge(2, EdgeKind::Jump)]);
+
+        // A register target could go anywhere
+        let cfg = build(
+            "
+            READ RX0
+            WRITE 1
+            JMP RX0
+            ",
+        );
+        assert_eq!(cfg.blocks().len(), 3);
+        assert_eq!(
+            cfg.blocks()[2].successors,
+            vec![
+                edge(0, EdgeKind::Jump),
+                edge(1, EdgeKind::Jump),
+                edge(2, EdgeKind::Jump),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let compiler = compile(
+            "LOOP:\nREAD RX0\nJEZ RX0 LOOP\nCALL SUB\nHALT\nSUB:\nRET\n",
+        );
+        let cfg = ControlFlowGraph::new(compiler.program());
+        assert_eq!(
+            cfg.to_dot(compiler.program(), compiler.sources()),
+            "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: READ RX0\\l1: JEZ RX0 LOOP\\l\", peripheries=2];
+    b1 [label=\"2: CALL SUB\\l\"];
+    b2 [label=\"3: HALT\\l\"];
+    b3 [label=\"4: RET\\l\"];
+    b0 -> b0 [label=\"jump\"];
+    b0 -> b1;
+    b1 -> b3 [label=\"call\", style=dashed];
+    b1 -> b2;
+}
+"
+        );
+        assert_eq!(escape_dot("a \"b\" \\c"), "a \\\"b\\\" \\\\c");
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let cfg = ControlFlowGraph::new(&Program {
+            instructions: vec![],
+        });
+        assert!(cfg.blocks().is_empty());
+        assert!(cfg.reverse_postorder().is_empty());
+        assert!(cfg.loops().is_empty());
+    }
+}
@@ -1,17 +1,36 @@
-/// The maximum number of cycles that a program can run for before being killed.
-/// Programs that take this number of cycles WILL terminate normally, but going
-/// over this number will cause an error when trying to execute additional
-/// instructions. This isn't meant to be a strategic restriction on users, just
-/// a mechanism to prevent programs from running forever.
+/// The default maximum number of cycles that a program can run for before being
+/// killed. Programs that take this number of cycles WILL terminate normally,
+/// but going over this number will cause an error when trying to execute
+/// additional instructions. This is just a mechanism to prevent programs from
+/// running forever. Hardware specs can set a tighter limit via
+/// [HardwareSpec::max_cycles](crate::HardwareSpec::max_cycles).
 pub const MAX_CYCLE_COUNT: usize = 1_000_000;
 
+/// The maximum number of memory cells that a hardware spec can have. Memory is
+/// allocated up front for every machine, so this keeps a bad spec from
+/// allocating an absurd amount of it.
+pub const MAX_MEMORY_SIZE: usize = 256;
+
+/// The maximum depth of nested `.include`s. Circular includes are normally
+/// caught by comparing paths, but two different paths can refer to the same
+/// file, so this is a backstop to prevent infinite recursion.
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
 /// The prefix that indicates a stack reference.
 pub const STACK_REF_TAG: &str = "S";
+/// The prefix that indicates a queue reference.
+pub const QUEUE_REF_TAG: &str = "Q";
 /// The string that refers to the null register.
 pub const NULL_REGISTER_REF: &str = "RZR";
 /// The string that refers to the input length register.
 pub const INPUT_LENGTH_REGISTER_REF: &str = "RLI";
+/// The string that refers to the program counter register.
+pub const PROGRAM_COUNTER_REGISTER_REF: &str = "RPC";
+/// The string that refers to the cycle count register.
+pub const CYCLE_COUNT_REGISTER_REF: &str = "RCY";
 /// The prefix that indicates a reference to a stack length register.
 pub const STACK_LENGTH_REGISTER_REF_TAG: &str = "RS";
+/// The prefix that indicates a reference to a queue length register.
+pub const QUEUE_LENGTH_REGISTER_REF_TAG: &str = "RQ";
 /// The prefix that indicates a reference to a user register.
 pub const USER_REGISTER_REF_TAG: &str = "RX";
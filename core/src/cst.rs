@@ -0,0 +1,556 @@
+//! A lossless Concrete Syntax Tree for GDLK source. Unlike the
+//! [source AST](crate::ast::source), the CST holds onto every byte of the
+//! source, including whitespace, comments, and blank lines, so that a tree can
+//! always be turned back into the exact text that it came from. That makes it
+//! the right tool for anything that needs to rewrite a user's source, such as
+//! a formatter.
+//!
+//! The CST only captures the layout of the source: its lines, the tokens on
+//! each line, and which lines open and close blocks. It doesn't check that the
+//! tokens form valid statements, so a tree can be built from any input, even if
+//! it has syntax errors. Use [SyntaxTree::lower] to get the AST.
+//!
+//! ```
+//! use gdlk::cst::SyntaxTree;
+//!
+//! let source = "READ RX0 ; get it\n\nWRITE    RX0\n";
+//! let tree = SyntaxTree::parse(source);
+//! assert_eq!(tree.to_string(), source);
+//! ```
+
+use crate::{
+    ast::source::Program,
+    error::{CompileError, SourceErrorWrapper},
+    models::ValueWidth,
+    parse::{eol_or_eof, parse, ParseResult},
+    util::{FileId, ParseContext, RawSpan, SourceFiles, Span},
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag},
+    character::complete::{char, none_of, space0},
+    combinator::{map, not, opt, recognize},
+    multi::{many0, many1},
+    sequence::{pair, preceded, terminated, tuple},
+};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display, Formatter},
+    iter::Peekable,
+};
+
+/// A single token of code, e.g. an instruction name or an argument, along with
+/// the whitespace that comes before it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    /// The whitespace between this token and whatever comes before it on the
+    /// line. For the first token on a line, this is the indentation.
+    pub leading: String,
+    /// The text of the token itself
+    pub text: String,
+    /// The location of the text, not including the leading whitespace
+    pub span: Span,
+}
+
+/// One line of source code. Every line holds zero or more tokens of code,
+/// followed by an optional comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    /// The tokens of code on the line, in order
+    pub tokens: Vec<Token>,
+    /// The comment at the end of the line, if any. The text includes the
+    /// leading `;`.
+    pub comment: Option<Token>,
+    /// Any whitespace at the end of the line that isn't part of a comment
+    pub trailing: String,
+    /// The characters that end the line. Either `\n`, `\r\n`, or empty for
+    /// the last line in the source.
+    pub line_ending: String,
+}
+
+impl Line {
+    /// Check if the line has no code or comment, i.e. it's empty or only holds
+    /// whitespace.
+    pub fn is_blank(&self) -> bool {
+        self.tokens.is_empty() && self.comment.is_none()
+    }
+
+    /// Check if the first token on this line is the given keyword, e.g. `IF`.
+    /// Keywords are case-insensitive.
+    pub fn starts_with_keyword(&self, keyword: &str) -> bool {
+        match self.tokens.first() {
+            Some(token) => token.text.eq_ignore_ascii_case(keyword),
+            None => false,
+        }
+    }
+}
+
+/// An `IF` or `WHILE` block, with all the lines that it holds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The line that opens the block, which starts with `IF` or `WHILE`
+    pub header: Line,
+    /// The lines in the body of the block, before any `ELSE`
+    pub body: Vec<Item>,
+    /// The `ELSE` line and the lines after it. Only `IF` blocks can have this.
+    pub else_branch: Option<(Line, Vec<Item>)>,
+    /// The `END` line that closes the block. `None` if the source ended before
+    /// the block was closed.
+    pub end: Option<Line>,
+}
+
+/// One item in a sequence of lines, which is either a plain line or a block
+/// that holds its own lines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Line(Line),
+    Block(Box<Block>),
+}
+
+/// The root of a CST, which holds all the lines of a source file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxTree {
+    pub items: Vec<Item>,
+}
+
+impl SyntaxTree {
+    /// Build a CST from some source code. This never fails, because any text
+    /// can be split into lines and tokens, even if it isn't a valid program.
+    pub fn parse(source: &str) -> Self {
+        // The parse context is only needed so we can reuse the RawSpan type.
+        // Nothing in here checks values or reports errors.
+        let errors = RefCell::new(Vec::new());
+        let context = ParseContext {
+            file: 0,
+            value_width: ValueWidth::default(),
+            errors: &errors,
+        };
+
+        let mut input = RawSpan::new_extra(source, context);
+        let mut lines = Vec::new();
+        while !input.fragment().is_empty() {
+            let (rest, line) =
+                line(input).expect("Any line of text should be valid");
+            lines.push(line);
+            input = rest;
+        }
+
+        Self {
+            items: group_items(&mut lines.into_iter().peekable(), &[]),
+        }
+    }
+
+    /// Get the source AST for the tree. The tree doesn't check the contents of
+    /// its lines, so this works by parsing the text of the tree, which means
+    /// any edits made to the tree are included. The text is stored as the
+    /// contents of the given file in `sources`, replacing whatever was there,
+    /// so that all of the spans in the program and the errors refer to it.
+    /// For a tree that hasn't been edited, this gives the exact same program
+    /// and syntax errors as parsing the original source would.
+    pub fn lower(
+        &self,
+        sources: &mut SourceFiles,
+        file: FileId,
+        value_width: ValueWidth,
+    ) -> (Program<Span>, Vec<SourceErrorWrapper<CompileError>>) {
+        sources.set_source(file, self.to_string());
+        parse(sources, file, value_width)
+    }
+}
+
+// ===== Display =====
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.leading, self.text)
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for token in self.tokens.iter().chain(&self.comment) {
+            write!(f, "{}", token)?;
+        }
+        write!(f, "{}{}", self.trailing, self.line_ending)
+    }
+}
+
+impl Display for Block {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header)?;
+        write_items(f, &self.body)?;
+        if let Some((line, body)) = &self.else_branch {
+            write!(f, "{}", line)?;
+            write_items(f, body)?;
+        }
+        if let Some(line) = &self.end {
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Line(line) => write!(f, "{}", line),
+            Self::Block(block) => write!(f, "{}", block),
+        }
+    }
+}
+
+impl Display for SyntaxTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_items(f, &self.items)
+    }
+}
+
+fn write_items(f: &mut Formatter<'_>, items: &[Item]) -> fmt::Result {
+    for item in items {
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+// ===== Parsing =====
+
+/// A `\r` that isn't part of a `\r\n` line ending. This isn't a line ending on
+/// its own, so it's treated like any other character.
+fn lone_cr(input: RawSpan) -> ParseResult<'_, RawSpan> {
+    terminated(tag("\r"), not(char('\n')))(input)
+}
+
+/// A quoted string or character literal. These get special treatment because
+/// they can hold spaces and semicolons. An unclosed quote runs to the end of
+/// the line.
+fn quoted<'a>(
+    quote: char,
+) -> impl Fn(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>> {
+    let stop_chars = match quote {
+        '"' => "\"\\\r\n",
+        _ => "'\\\r\n",
+    };
+    recognize(tuple((
+        char(quote),
+        many0(alt((
+            is_not(stop_chars),
+            recognize(pair(char('\\'), opt(none_of("\r\n")))),
+        ))),
+        opt(char(quote)),
+    )))
+}
+
+/// The text of a single token, which runs until whitespace, the end of the
+/// line, or the start of a comment.
+fn token_text(input: RawSpan) -> ParseResult<'_, RawSpan> {
+    recognize(many1(alt((
+        is_not(" \t\r\n;\"'"),
+        quoted('"'),
+        quoted('\''),
+        lone_cr,
+    ))))(input)
+}
+
+/// Some text on a line, along with the whitespace before it.
+fn token<'a>(
+    text: impl Fn(RawSpan<'a>) -> ParseResult<'a, RawSpan<'a>>,
+) -> impl Fn(RawSpan<'a>) -> ParseResult<'a, Token> {
+    map(pair(space0, text), |(leading, text)| Token {
+        leading: leading.fragment().to_string(),
+        text: text.fragment().to_string(),
+        span: Span::from_raw_span(&text),
+    })
+}
+
+/// A comment, which starts with `;` and runs to the end of the line.
+fn comment_text(input: RawSpan) -> ParseResult<'_, RawSpan> {
+    recognize(preceded(char(';'), many0(alt((is_not("\r\n"), lone_cr)))))(input)
+}
+
+/// Parse a single line, including its line ending. Every character other than
+/// a line ending fits in either a token or a comment, so this can only fail
+/// on empty input.
+fn line(input: RawSpan) -> ParseResult<'_, Line> {
+    let (rest, (tokens, comment, trailing)) =
+        tuple((many0(token(token_text)), opt(token(comment_text)), space0))(
+            input,
+        )?;
+    let (rest, line_ending) = eol_or_eof(rest)?;
+
+    Ok((
+        rest,
+        Line {
+            tokens,
+            comment,
+            trailing: trailing.fragment().to_string(),
+            line_ending: line_ending.fragment().to_string(),
+        },
+    ))
+}
+
+/// Group a sequence of lines into items, nesting the lines of each block
+/// inside of it. Stops at the first line that starts with one of the given
+/// keywords, which is left in the iterator.
+fn group_items(
+    lines: &mut Peekable<impl Iterator<Item = Line>>,
+    closers: &[&str],
+) -> Vec<Item> {
+    let mut items = Vec::new();
+    while let Some(line) = lines.peek() {
+        if closers
+            .iter()
+            .any(|keyword| line.starts_with_keyword(keyword))
+        {
+            break;
+        }
+
+        // unwrap is safe because we just peeked
+        let line = lines.next().unwrap();
+        let item = if line.starts_with_keyword("IF") {
+            let body = group_items(lines, &["ELSE", "END"]);
+            let else_branch = match lines.peek() {
+                Some(line) if line.starts_with_keyword("ELSE") => {
+                    let line = lines.next().unwrap();
+                    Some((line, group_items(lines, &["END"])))
+                }
+                _ => None,
+            };
+            Item::Block(Box::new(Block {
+                header: line,
+                body,
+                else_branch,
+                end: lines.next(),
+            }))
+        } else if line.starts_with_keyword("WHILE") {
+            Item::Block(Box::new(Block {
+                header: line,
+                body: group_items(lines, &["END"]),
+                else_branch: None,
+                end: lines.next(),
+            }))
+        } else {
+            Item::Line(line)
+        };
+        items.push(item);
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to make it a bit easier to create spans for tests
+    fn span(
+        start_line: usize,
+        start_col: usize,
+        end_line: usize,
+        end_col: usize,
+    ) -> Span {
+        Span {
+            // The test implementation of PartialEq doesn't check these fields
+            offset: 0,
+            length: 0,
+
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+            file: 0,
+        }
+    }
+
+    fn token(leading: &str, text: &str, span: Span) -> Token {
+        Token {
+            leading: leading.into(),
+            text: text.into(),
+            span,
+        }
+    }
+
+    /// Get the text of the first token on every line in a list of items
+    fn first_tokens(items: &[Item]) -> Vec<&str> {
+        items
+            .iter()
+            .map(|item| match item {
+                Item::Line(line) => line,
+                Item::Block(block) => &block.header,
+            })
+            .map(|line| line.tokens.first().map_or("", |t| t.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for source in &[
+            "",
+            "\n",
+            "\n\n \t\n",
+            "READ RX0",
+            "READ RX0\nWRITE RX0\n",
+            "READ RX0\r\nWRITE RX0\r\n",
+            "  \tLOOP:   ; comment \t \n    JMP LOOP  \t",
+            "SET RX0 ';' ; a semicolon\nSET RX0 ' '\n",
+            ".include \"my file;.gdlk\"\n",
+            "SET RX0 '\\'' ; escaped quote\n",
+            "SET RX0 \"unclosed ; quote\nREAD RX0\n",
+            "SET RX0 '\\\nREAD RX0\n",
+            "a\rb \r\n\r;\r\r",
+            "IF RX0 == 1\n  ELSE\nWHILE RX0 > 0\n",
+            "END\nELSE\nIF RX0 == 1\nELSE\nELSE\nEND\nEND\n",
+            "SET RX0 'é' ;ñ ✓\n",
+        ] {
+            assert_eq!(&SyntaxTree::parse(source).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            SyntaxTree::parse("  SET\tRX0  ';' ; hi there \r\n  \n"),
+            SyntaxTree {
+                items: vec![
+                    Item::Line(Line {
+                        tokens: vec![
+                            token("  ", "SET", span(1, 3, 1, 6)),
+                            token("\t", "RX0", span(1, 7, 1, 10)),
+                            token("  ", "';'", span(1, 12, 1, 15)),
+                        ],
+                        comment: Some(token(
+                            " ",
+                            "; hi there ",
+                            span(1, 16, 1, 27)
+                        )),
+                        trailing: "".into(),
+                        line_ending: "\r\n".into(),
+                    }),
+                    Item::Line(Line {
+                        tokens: vec![],
+                        comment: None,
+                        trailing: "  ".into(),
+                        line_ending: "\n".into(),
+                    }),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let tree = SyntaxTree::parse(
+            "
+            WHILE RX0 > 0
+                IF RX0 == 1 ; one
+                    WRITE RX0
+                else
+                    WHILE RX1 < 0
+                        ELSE
+                    END
+                END
+                READ RX0
+            END
+            END
+            ",
+        );
+        assert_eq!(first_tokens(&tree.items), vec!["", "WHILE", "END", ""]);
+
+        let outer = match &tree.items[1] {
+            Item::Block(block) => block,
+            _ => panic!("Expected a block"),
+        };
+        assert_eq!(first_tokens(&outer.body), vec!["IF", "READ"]);
+        assert_eq!(outer.else_branch, None);
+        assert_eq!(
+            outer.end.as_ref().unwrap().tokens[0].span,
+            span(11, 13, 11, 16)
+        );
+
+        let inner = match &outer.body[0] {
+            Item::Block(block) => block,
+            _ => panic!("Expected a block"),
+        };
+        assert_eq!(inner.header.comment.as_ref().unwrap().text, "; one");
+        assert_eq!(first_tokens(&inner.body), vec!["WRITE"]);
+        let (else_line, else_body) = inner.else_branch.as_ref().unwrap();
+        assert_eq!(else_line.tokens[0].text, "else");
+        // An ELSE in a WHILE is just a regular line
+        assert_eq!(first_tokens(else_body), vec!["WHILE"]);
+        match &else_body[0] {
+            Item::Block(block) => {
+                assert_eq!(first_tokens(&block.body), vec!["ELSE"]);
+            }
+            _ => panic!("Expected a block"),
+        }
+
+        // Unclosed blocks just run to the end of the source
+        let tree = SyntaxTree::parse("IF RX0 == 1\nREAD RX0\n");
+        match &tree.items[0] {
+            Item::Block(block) => {
+                assert_eq!(first_tokens(&block.body), vec!["READ"]);
+                assert_eq!(block.end, None);
+            }
+            _ => panic!("Expected a block"),
+        }
+    }
+
+    #[test]
+    fn test_lower() {
+        for source in &[
+            "; setup\nREAD RX0\n\nLOOP:\n  ADD RX0 1 ; inc\n  JMP LOOP\n",
+            "IF RX0 == 1\n  WRITE RX0\nEND\nREAD\n",
+        ] {
+            let tree = SyntaxTree::parse(source);
+            let expected = parse(
+                &SourceFiles::new((*source).into()),
+                0,
+                ValueWidth::default(),
+            );
+            let actual = tree.lower(
+                &mut SourceFiles::default(),
+                0,
+                ValueWidth::default(),
+            );
+            assert_eq!(actual.0, expected.0);
+            assert_eq!(format!("{:?}", actual.1), format!("{:?}", expected.1));
+        }
+
+        // Lowering an included file tags every span with that file
+        let source = "READ RX0\nREAD 0\n";
+        let mut sources = SourceFiles::new(String::new());
+        let file = sources.add_include("lib.gdlk".into(), String::new());
+        let (program, errors) = SyntaxTree::parse(source).lower(
+            &mut sources,
+            file,
+            ValueWidth::default(),
+        );
+        assert!(program.body.iter().all(|stmt| stmt.1.file == file));
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Syntax error at lib.gdlk:2:6: Expected register reference"]
+        );
+
+        // Edits to the tree show up in the lowered program
+        let mut tree = SyntaxTree::parse("READ RX0\nWRITE RX0\n");
+        match &mut tree.items[1] {
+            Item::Line(line) => line.tokens[1].text = "RX1".into(),
+            _ => panic!("Expected a line"),
+        }
+        let mut sources = SourceFiles::default();
+        let (program, errors) =
+            tree.lower(&mut sources, 0, ValueWidth::default());
+        assert_eq!(sources.source(0), "READ RX0\nWRITE RX1\n");
+        assert_eq!(
+            program,
+            parse(
+                &SourceFiles::new("READ RX0\nWRITE RX1\n".into()),
+                0,
+                ValueWidth::default()
+            )
+            .0
+        );
+        assert!(errors.is_empty());
+    }
+}
@@ -1,17 +1,123 @@
 use crate::{
     ast::{
         compiled::{Instruction, Program as CompiledProgram},
-        source::{LabelDecl, Program as SourceProgram, Statement},
-        Label, Node, SpanNode,
+        source::{LabelDecl, ResolvedProgram, ResolvedStatement, Statement},
+        Jump, Label, Node, SpanNode,
     },
     util::Span,
     Compiler,
 };
 use std::collections::HashMap;
 
+/// Lowers structured blocks (`IF`/`WHILE`) into plain labels and jumps, so the
+/// rest of delabeling only has to deal with a flat list of statements. The
+/// generated labels start with a `#`, so they can never collide with a label
+/// from the source. The generated jumps keep the spans of the block keywords
+/// and conditions that they came from.
+fn lower_blocks(
+    body: Vec<SpanNode<ResolvedStatement<Span>>>,
+    next_block_id: &mut usize,
+    output: &mut Vec<SpanNode<ResolvedStatement<Span>>>,
+) {
+    for Node(stmt, span) in body {
+        match stmt {
+            // IF cond          JUMP_UNLESS cond ELSE
+            //   body           body
+            // ELSE             JMP END
+            //   else_body      ELSE:
+            // END              else_body
+            //                  END:
+            Statement::If {
+                condition,
+                body,
+                else_body,
+            } => {
+                let id = *next_block_id;
+                *next_block_id += 1;
+                let end_label = format!("#IF.{}.END", id);
+
+                match else_body {
+                    None => {
+                        output.push(jump_unless(condition, end_label.clone()));
+                        lower_blocks(body, next_block_id, output);
+                    }
+                    Some(Node(else_body, else_span)) => {
+                        let else_label = format!("#IF.{}.ELSE", id);
+                        output.push(jump_unless(condition, else_label.clone()));
+                        lower_blocks(body, next_block_id, output);
+                        output.push(jump(end_label.clone(), else_span));
+                        output.push(label(else_label, else_span));
+                        lower_blocks(else_body, next_block_id, output);
+                    }
+                }
+                output.push(label(end_label, span));
+            }
+            // WHILE cond       START:
+            //   body           JUMP_UNLESS cond END
+            // END              body
+            //                  JMP START
+            //                  END:
+            Statement::While {
+                condition,
+                body,
+                end,
+            } => {
+                let id = *next_block_id;
+                *next_block_id += 1;
+                let start_label = format!("#WHILE.{}", id);
+                let end_label = format!("#WHILE.{}.END", id);
+
+                output.push(label(start_label.clone(), condition.1));
+                output.push(jump_unless(condition, end_label.clone()));
+                lower_blocks(body, next_block_id, output);
+                output.push(jump(start_label, end));
+                output.push(label(end_label, end));
+            }
+            stmt => output.push(Node(stmt, span)),
+        }
+    }
+}
+
+/// Creates a jump to the given label that is taken when the given block
+/// condition is _false_.
+fn jump_unless(
+    Node(condition, span): SpanNode<Jump<Span>>,
+    label: Label,
+) -> SpanNode<ResolvedStatement<Span>> {
+    let negated = match condition {
+        Jump::Jeq(a, b) => Jump::Jne(a, b),
+        Jump::Jne(a, b) => Jump::Jeq(a, b),
+        Jump::Jlt(a, b) => Jump::Jge(a, b),
+        Jump::Jge(a, b) => Jump::Jlt(a, b),
+        Jump::Jgt(a, b) => Jump::Jle(a, b),
+        Jump::Jle(a, b) => Jump::Jgt(a, b),
+        // The parser only generates compare jumps for conditions
+        _ => unreachable!("Invalid block condition: {:?}", condition),
+    };
+    Node(
+        Statement::Jump(Node(negated, span), Node(label, span)),
+        span,
+    )
+}
+
+/// Creates an unconditional jump to the given label.
+fn jump(label: Label, span: Span) -> SpanNode<ResolvedStatement<Span>> {
+    Node(
+        Statement::Jump(Node(Jump::Jmp, span), Node(label, span)),
+        span,
+    )
+}
+
+/// Creates a label declaration.
+fn label(label: Label, span: Span) -> SpanNode<ResolvedStatement<Span>> {
+    Node(Statement::Label(Node(LabelDecl(label), span)), span)
+}
+
 /// Build a mapping of all labels to the their instruction indexes. The indexes
 /// exclude the labels themselves.
-fn map_labels(body: &[SpanNode<Statement<Span>>]) -> HashMap<Label, isize> {
+fn map_labels(
+    body: &[SpanNode<ResolvedStatement<Span>>],
+) -> HashMap<Label, isize> {
     let mut label_map: HashMap<Label, isize> = HashMap::new();
     for (i, stmt) in body.iter().enumerate() {
         if let Node(Statement::Label(Node(LabelDecl(label), _)), _) = stmt {
@@ -28,10 +134,16 @@ fn map_labels(body: &[SpanNode<Statement<Span>>]) -> HashMap<Label, isize> {
 fn map_statement(
     label_map: &HashMap<Label, isize>,
     i: usize,
-    stmt_node: SpanNode<Statement<Span>>,
+    stmt_node: SpanNode<ResolvedStatement<Span>>,
 ) -> SpanNode<Instruction<Span>> {
     stmt_node.map(|stmt| match stmt {
-        Statement::Label(_) => unreachable!(),
+        // Includes are expanded after parsing, directives are removed during
+        // validation, and blocks are lowered before this
+        Statement::Label(_)
+        | Statement::Directive(_)
+        | Statement::Include(_)
+        | Statement::If { .. }
+        | Statement::While { .. } => unreachable!(),
         Statement::Operator(op) => Instruction::Operator(op),
         Statement::Jump(jump, Node(label, _)) => {
             Instruction::Jump(
@@ -41,18 +153,24 @@ fn map_statement(
                 *label_map.get(&label).unwrap() - i as isize,
             )
         }
+        Statement::Call(Node(label, _)) => {
+            Instruction::Call(*label_map.get(&label).unwrap() - i as isize)
+        }
+        Statement::ComputedJump(target) => Instruction::ComputedJump(target),
+        Statement::Return => Instruction::Return,
+        Statement::Halt => Instruction::Halt,
     })
 }
 
-impl Compiler<SourceProgram<Span>> {
+impl Compiler<ResolvedProgram<Span>> {
     /// Removes labels from the source, replacing their references with relative
-    /// index offsets.
+    /// index offsets. Blocks are lowered into labels and jumps first.
     pub(crate) fn delabel(self) -> Compiler<CompiledProgram<Span>> {
-        let label_map = map_labels(&self.ast.body);
+        let mut body = Vec::new();
+        lower_blocks(self.ast.body, &mut 0, &mut body);
+        let label_map = map_labels(&body);
 
-        let instructions: Vec<Node<Instruction<_>, _>> = self
-            .ast
-            .body
+        let instructions: Vec<Node<Instruction<_>, _>> = body
             .into_iter()
             // Need to filter FIRST so labels don't get tracked in the
             // indexes
@@ -64,9 +182,10 @@ impl Compiler<SourceProgram<Span>> {
             .map(|(i, stmt_node)| map_statement(&label_map, i, stmt_node))
             .collect();
         Compiler {
-            source: self.source,
+            sources: self.sources,
             hardware_spec: self.hardware_spec,
             ast: CompiledProgram { instructions },
+            warnings: self.warnings,
         }
     }
 }
@@ -75,8 +194,9 @@ impl Compiler<SourceProgram<Span>> {
 mod tests {
     use super::*;
     use crate::{
-        ast::{Jump, Operator, RegisterRef},
+        ast::{Jump, Operator, RegisterRef, ValueSource},
         models::HardwareSpec,
+        util::SourceFiles,
     };
 
     #[test]
@@ -89,6 +209,7 @@ mod tests {
             start_col: 0,
             end_line: 0,
             end_col: 0,
+            file: 0,
         };
         let body = vec![
             Node(
@@ -133,9 +254,10 @@ mod tests {
             Node(Statement::Label(Node(LabelDecl("END".into()), span)), span),
         ];
         let compiler = Compiler {
-            source: "".into(),
+            sources: SourceFiles::default(),
             hardware_spec: HardwareSpec::default(),
-            ast: SourceProgram { body },
+            ast: ResolvedProgram { body },
+            warnings: Vec::new(),
         };
         assert_eq!(
             compiler.delabel().ast.instructions,
@@ -160,4 +282,81 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_lower_blocks() {
+        let span = Span {
+            offset: 0,
+            length: 0,
+            start_line: 0,
+            start_col: 0,
+            end_line: 0,
+            end_col: 0,
+            file: 0,
+        };
+        let value = |v| Node(ValueSource::Const(Node(v, span)), span);
+        let read = Node(
+            Statement::Operator(Node(
+                Operator::Read(Node(RegisterRef::User(0), span)),
+                span,
+            )),
+            span,
+        );
+
+        // WHILE 1 > 0
+        //     IF 1 == 0
+        //         READ RX0
+        //     ELSE
+        //     END
+        // END
+        let body = vec![Node(
+            Statement::While {
+                condition: Node(Jump::Jgt(value(1), value(0)), span),
+                body: vec![Node(
+                    Statement::If {
+                        condition: Node(Jump::Jeq(value(1), value(0)), span),
+                        body: vec![read.clone()],
+                        else_body: Some(Node(vec![], span)),
+                    },
+                    span,
+                )],
+                end: span,
+            },
+            span,
+        )];
+        let compiler = Compiler {
+            sources: SourceFiles::default(),
+            hardware_spec: HardwareSpec::default(),
+            ast: ResolvedProgram { body },
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            compiler.delabel().ast.instructions,
+            vec![
+                Node(
+                    Instruction::Jump(
+                        Node(Jump::Jle(value(1), value(0)), span),
+                        5
+                    ),
+                    span
+                ),
+                Node(
+                    Instruction::Jump(
+                        Node(Jump::Jne(value(1), value(0)), span),
+                        3
+                    ),
+                    span
+                ),
+                Node(
+                    Instruction::Operator(Node(
+                        Operator::Read(Node(RegisterRef::User(0), span)),
+                        span
+                    )),
+                    span
+                ),
+                Node(Instruction::Jump(Node(Jump::Jmp, span), 1), span),
+                Node(Instruction::Jump(Node(Jump::Jmp, span), -4), span),
+            ]
+        );
+    }
 }
@@ -2,17 +2,45 @@
 
 #[cfg(feature = "wasm")]
 use crate::ast::wasm::SourceElement;
-use crate::util::{self, Span};
+use crate::{
+    ast::LangValue,
+    consts::MAX_MEMORY_SIZE,
+    models::ValueWidth,
+    util::{self, SourceFiles, Span},
+};
 use failure::Fail;
 use serde::Serialize;
 use std::fmt::{self, Debug, Display, Formatter};
 
+/// How serious a [SourceError] is. Errors stop the program from compiling or
+/// running, while warnings are just informational.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
 /// A trait for any error that originates in source code. [SourceError]s rely on
 /// having source code present in order to display themselves.
 pub trait SourceError: 'static + Send + Sync + Debug + Serialize {
     /// A simple type label for this error, e.g. `"syntax"` or `"runtime"`.
     fn type_label(&self) -> &'static str;
 
+    /// How serious this error is. Most source errors are real errors, so
+    /// that's the default.
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
     /// Format this error into a simple message. `spanned_src` is the slice of
     /// the source code that corresponds to this error's [Span]. This needs to
     /// be provided by the caller in order to create a proper error message.
@@ -32,18 +60,39 @@ pub enum CompileError {
     InvalidRegisterRef,
     /// Referenced a stack with an invalid identifier
     InvalidStackRef,
+    /// Referenced a queue with an invalid identifier
+    InvalidQueueRef,
     /// Tried to write to a read-only register
     UnwritableRegister,
     /// Defined the same label more than once
     DuplicateLabel { original: Span },
     /// Referenced a label that wasn't defined
     InvalidLabel,
+    /// Defined the same `.const`/`.alias` name more than once
+    DuplicateSymbol { original: Span },
+    /// Referenced a `.const`/`.alias` name that wasn't defined
+    UndefinedSymbol,
+    /// Used a `.alias` name in a constant expression
+    NonConstantSymbol,
+    /// A constant expression overflowed the value width
+    ConstantOverflow,
+    /// A constant expression divided by zero
+    ConstantDivideByZero,
+    /// Used an instruction that the hardware doesn't allow
+    DisallowedInstruction { name: &'static str },
+    /// The compiled program has more instructions than the hardware allows
+    TooManyInstructions { count: usize, max: usize },
+    /// The loader couldn't provide the file named in a `.include`
+    IncludeNotFound,
+    /// A file includes itself, either directly or through other includes
+    CircularInclude,
 }
 
 impl SourceError for CompileError {
     fn type_label(&self) -> &'static str {
         match self {
             Self::Syntax { .. } => "Syntax",
+            Self::IncludeNotFound | Self::CircularInclude => "Include",
             _ => "Validation",
         }
     }
@@ -59,6 +108,9 @@ impl SourceError for CompileError {
             Self::InvalidStackRef => {
                 write!(f, "Invalid reference to stack `{}`", spanned_src)
             }
+            Self::InvalidQueueRef => {
+                write!(f, "Invalid reference to queue `{}`", spanned_src)
+            }
             Self::UnwritableRegister => write!(
                 f,
                 "Cannot write to read-only register `{}`",
@@ -75,6 +127,93 @@ impl SourceError for CompileError {
             Self::InvalidLabel => {
                 write!(f, "Invalid reference to label `{}`", spanned_src)
             }
+            Self::DuplicateSymbol {
+                original: original_span,
+            } => write!(
+                f,
+                "Duplicate definition of name `{}`, \
+                    originally defined on line {}",
+                spanned_src, original_span.start_line,
+            ),
+            Self::UndefinedSymbol => {
+                write!(f, "Undefined name `{}`", spanned_src)
+            }
+            Self::NonConstantSymbol => {
+                write!(f, "Name `{}` is not a constant", spanned_src)
+            }
+            Self::ConstantOverflow => write!(
+                f,
+                "Arithmetic overflow in constant expression `{}`",
+                spanned_src
+            ),
+            Self::ConstantDivideByZero => write!(
+                f,
+                "Divide by zero in constant expression `{}`",
+                spanned_src
+            ),
+            Self::DisallowedInstruction { name } => write!(
+                f,
+                "Instruction `{}` is not allowed on this hardware",
+                name
+            ),
+            Self::TooManyInstructions { count, max } => write!(
+                f,
+                "Program has {} instructions, but the hardware allows at most {}",
+                count, max
+            ),
+            Self::IncludeNotFound => {
+                write!(f, "Could not load file `{}`", spanned_src)
+            }
+            Self::CircularInclude => {
+                write!(f, "Circular include of file `{}`", spanned_src)
+            }
+        }
+    }
+}
+
+/// A warning about code that is valid, but probably isn't what the user meant.
+/// These are found by the lint pass, and never cause compilation to fail.
+#[derive(Copy, Clone, Debug, Serialize)]
+pub enum CompileWarning {
+    /// Code that comes after an instruction that never continues to the next
+    /// one (e.g. `JMP`), with no label in between
+    UnreachableCode,
+    /// A label that is never jumped to or called
+    UnusedLabel,
+    /// A value written to a user register that is never read anywhere
+    DeadStore,
+    /// `READ` into `RZR`, which throws the value away
+    ReadIntoNull,
+    /// A value pushed onto a stack that is never popped anywhere
+    UnpoppedPush,
+}
+
+impl SourceError for CompileWarning {
+    fn type_label(&self) -> &'static str {
+        "Lint"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn fmt_msg(&self, f: &mut Formatter<'_>, spanned_src: &str) -> fmt::Result {
+        match self {
+            Self::UnreachableCode => write!(f, "Unreachable code"),
+            Self::UnusedLabel => write!(
+                f,
+                "Label `{}` is never used",
+                spanned_src.trim_end_matches(':')
+            ),
+            Self::DeadStore => {
+                write!(f, "Value written to `{}` is never read", spanned_src)
+            }
+            Self::ReadIntoNull => {
+                write!(f, "Input read into `{}` is thrown away", spanned_src)
+            }
+            Self::UnpoppedPush => {
+                write!(f, "Value pushed onto `{}` is never popped", spanned_src)
+            }
         }
     }
 }
@@ -86,14 +225,35 @@ impl SourceError for CompileError {
 pub enum RuntimeError {
     /// DIV attempted with a zero divisor
     DivideByZero,
+    /// Arithmetic result didn't fit in the value width, and the hardware is
+    /// set to trap on overflow
+    Overflow,
     /// READ attempted while input is empty
     EmptyInput,
     /// PUSH attemped onto a stack that is at capacity
     StackOverflow,
     /// POP attempted from an empty stack
     EmptyStack,
+    /// PEEK, DUP, or SWAP attempted on a stack without enough values
+    StackUnderflow,
+    /// ENQ attempted onto a queue that is at capacity
+    QueueOverflow,
+    /// DEQ attempted from an empty queue
+    EmptyQueue,
     /// Execution attempted after the program has hit the CPU cycle limit
     TooManyCycles,
+    /// CALL attempted while the call stack is at capacity
+    CallStackOverflow,
+    /// RET attempted while not inside a subroutine
+    EmptyCallStack,
+    /// LOAD or STORE attempted with an address outside of memory
+    MemoryOutOfBounds { address: LangValue },
+    /// Indirect register reference used with an index that isn't a register
+    InvalidRegisterIndex { index: LangValue },
+    /// Indirect stack reference used with an index that isn't a stack
+    InvalidStackIndex { index: LangValue },
+    /// Computed JMP attempted to an index outside of the program
+    InvalidJumpTarget { target: LangValue },
 }
 
 impl SourceError for RuntimeError {
@@ -104,6 +264,9 @@ impl SourceError for RuntimeError {
     fn fmt_msg(&self, f: &mut Formatter<'_>, spanned_src: &str) -> fmt::Result {
         match self {
             Self::DivideByZero => write!(f, "Divide by zero"),
+            Self::Overflow => {
+                write!(f, "Arithmetic overflow in `{}`", spanned_src)
+            }
             Self::StackOverflow => {
                 write!(f, "Overflow on stack `{}`", spanned_src)
             }
@@ -113,19 +276,141 @@ impl SourceError for RuntimeError {
             Self::EmptyStack => {
                 write!(f, "Cannot pop from empty stack `{}`", spanned_src)
             }
+            Self::StackUnderflow => {
+                write!(f, "Not enough values on stack `{}`", spanned_src)
+            }
+            Self::QueueOverflow => {
+                write!(f, "Overflow on queue `{}`", spanned_src)
+            }
+            Self::EmptyQueue => {
+                write!(f, "Cannot dequeue from empty queue `{}`", spanned_src)
+            }
             Self::TooManyCycles => write!(
                 f,
                 "Maximum number of cycles reached, \
                 cannot execute instruction `{}`",
                 spanned_src
             ),
+            Self::CallStackOverflow => write!(
+                f,
+                "Maximum call depth reached, cannot execute `{}`",
+                spanned_src
+            ),
+            Self::EmptyCallStack => {
+                write!(f, "Cannot return from outside of a subroutine")
+            }
+            Self::MemoryOutOfBounds { address } => write!(
+                f,
+                "Memory address {} is out of bounds, cannot execute `{}`",
+                address, spanned_src
+            ),
+            Self::InvalidRegisterIndex { index } => write!(
+                f,
+                "Register index {} is out of range in `{}`",
+                index, spanned_src
+            ),
+            Self::InvalidStackIndex { index } => write!(
+                f,
+                "Stack index {} is out of range in `{}`",
+                index, spanned_src
+            ),
+            Self::InvalidJumpTarget { target } => write!(
+                f,
+                "Jump target {} is outside of the program, cannot execute `{}`",
+                target, spanned_src
+            ),
+        }
+    }
+}
+
+/// An error caused by a [HardwareSpec](crate::HardwareSpec) whose settings are
+/// out of bounds.
+#[derive(Copy, Clone, Debug, Fail, Serialize)]
+pub enum HardwareSpecError {
+    /// The memory size is larger than [MAX_MEMORY_SIZE]
+    MemoryTooLarge { memory_size: usize },
+    /// There are capacities listed for more stacks than the hardware has
+    TooManyStackLengths {
+        num_stack_lengths: usize,
+        num_stacks: usize,
+    },
+}
+
+impl Display for HardwareSpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MemoryTooLarge { memory_size } => write!(
+                f,
+                "Memory size {} is larger than the maximum of {}",
+                memory_size, MAX_MEMORY_SIZE
+            ),
+            Self::TooManyStackLengths {
+                num_stack_lengths,
+                num_stacks,
+            } => write!(
+                f,
+                "{} stack lengths were given, but there are only {} stacks",
+                num_stack_lengths, num_stacks
+            ),
+        }
+    }
+}
+
+/// An error caused by a [ProgramSpec](crate::ProgramSpec) that can't run on
+/// a certain piece of hardware.
+#[derive(Copy, Clone, Debug, Fail, Serialize)]
+pub enum ProgramSpecError {
+    /// A value in the input or expected output doesn't fit in the hardware's
+    /// value width
+    ValueOutOfRange { value: LangValue, width: ValueWidth },
+}
+
+impl Display for ProgramSpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ValueOutOfRange { value, width } => write!(
+                f,
+                "Value {} in program spec is out of range for {}-bit values",
+                value,
+                width.bits()
+            ),
+        }
+    }
+}
+
+/// An error caused by a hardware spec or program spec that a machine can't be
+/// allocated for.
+#[derive(Copy, Clone, Debug, Fail, Serialize)]
+pub enum SpecError {
+    Hardware(HardwareSpecError),
+    Program(ProgramSpecError),
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hardware(error) => write!(f, "{}", error),
+            Self::Program(error) => write!(f, "{}", error),
         }
     }
 }
 
+impl From<HardwareSpecError> for SpecError {
+    fn from(error: HardwareSpecError) -> Self {
+        Self::Hardware(error)
+    }
+}
+
+impl From<ProgramSpecError> for SpecError {
+    fn from(error: ProgramSpecError) -> Self {
+        Self::Program(error)
+    }
+}
+
 /// A wrapper around a [SourceError], that holds some extra data:
 /// - The [Span] of the source code that caused the error
 /// - The offending chunk of source code itself
+/// - The path of the file that the error is in, if it's an included file
 ///
 /// This type on its own can be formatted, without any external data.
 #[derive(Clone, Debug, Fail, Serialize)]
@@ -133,14 +418,18 @@ pub struct SourceErrorWrapper<E: SourceError> {
     error: E,
     span: Span,
     spanned_source: String,
+    path: Option<String>,
 }
 
 impl<E: SourceError> SourceErrorWrapper<E> {
-    pub fn new(error: E, span: Span, src: &str) -> Self {
+    pub fn new(error: E, span: Span, sources: &SourceFiles) -> Self {
         Self {
             error,
             span,
-            spanned_source: span.get_source_slice(src).into(),
+            spanned_source: span
+                .get_source_slice(sources.source(span.file))
+                .into(),
+            path: sources.path(span.file).map(String::from),
         }
     }
 
@@ -153,11 +442,15 @@ impl<E: SourceError> Display for SourceErrorWrapper<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} error at {}:{}: ",
+            "{} {} at ",
             self.error.type_label(),
-            self.span.start_line,
-            self.span.start_col,
+            self.error.severity()
         )?;
+        // Errors in the main source don't need a path, since there's only one
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path)?;
+        }
+        write!(f, "{}:{}: ", self.span.start_line, self.span.start_col)?;
         self.error.fmt_msg(f, &self.spanned_source)?;
         Ok(())
     }
@@ -181,18 +474,18 @@ impl<E: SourceError> From<&SourceErrorWrapper<E>> for SourceElement {
 pub struct WithSource<E: SourceError> {
     errors: Vec<SourceErrorWrapper<E>>,
     #[serde(skip)]
-    source: String,
+    sources: SourceFiles,
 }
 
 impl<E: SourceError> WithSource<E> {
     /// Wrap a collection of errors with its source code.
     pub(crate) fn new(
         errors: impl IntoIterator<Item = SourceErrorWrapper<E>>,
-        source: String,
+        sources: SourceFiles,
     ) -> Self {
         Self {
             errors: errors.into_iter().collect(),
-            source,
+            sources,
         }
     }
 
@@ -213,7 +506,11 @@ impl<E: SourceError> Display for WithSource<E> {
 
             write!(f, "{}", error)?;
             if f.alternate() {
-                util::fmt_src_highlights(f, &error.span, &self.source)?;
+                util::fmt_src_highlights(
+                    f,
+                    &error.span,
+                    self.sources.source(error.span.file),
+                )?;
             }
         }
         Ok(())
@@ -0,0 +1,411 @@
+//! A formatter for GDLK source code. Formatting is done on the
+//! [CST](crate::cst), so every comment is kept, and the formatted program
+//! compiles to exactly the same thing as the original. The canonical style is:
+//!
+//! - Instructions, keywords, and register/stack/queue references are uppercase,
+//!   and directives are lowercase
+//! - Tokens are separated by a single space
+//! - Statements after a label are indented one level under it, and the body of
+//!   a block is indented one level under the block
+//! - Trailing comments on consecutive lines are aligned with each other
+//! - Runs of blank lines are collapsed into one, and the file ends with a
+//!   single newline
+
+use crate::{
+    consts::{QUEUE_REF_TAG, STACK_REF_TAG},
+    cst::{Item, Line, SyntaxTree, Token},
+    parse::is_reserved_name,
+};
+use std::iter;
+
+/// Number of spaces in one level of indentation
+const INDENT_WIDTH: usize = 4;
+
+/// One formatted line, before comments are aligned
+struct OutputLine {
+    /// Indentation level
+    indent: usize,
+    /// The tokens of code, joined together. Empty for a comment-only line.
+    code: String,
+    comment: Option<String>,
+    is_label: bool,
+}
+
+impl OutputLine {
+    /// Width of the line, not including its comment
+    fn width(&self) -> usize {
+        self.indent * INDENT_WIDTH + self.code.chars().count()
+    }
+
+    fn has_code(&self) -> bool {
+        !self.code.is_empty()
+    }
+}
+
+/// Format some source code into the canonical style. This works on any input,
+/// even if it has syntax errors, because only the layout and case of the code
+/// are changed.
+///
+/// ```
+/// use gdlk::format_source;
+///
+/// assert_eq!(
+///     format_source("loop:\nread rx0 ; get\n  write   rx0"),
+///     "loop:\n    READ RX0 ; get\n    WRITE RX0\n"
+/// );
+/// ```
+pub fn format_source(source: &str) -> String {
+    let tree = SyntaxTree::parse(source);
+    // None represents a blank line
+    let mut lines: Vec<Option<OutputLine>> = Vec::new();
+    format_items(&tree.items, 0, &mut lines);
+    indent_label_comments(&mut lines);
+    render(&lines)
+}
+
+/// Format a sequence of items, all starting at the given indentation level.
+fn format_items(
+    items: &[Item],
+    indent: usize,
+    output: &mut Vec<Option<OutputLine>>,
+) {
+    // Everything after a label gets indented under it
+    let mut statement_indent = indent;
+    for item in items {
+        match item {
+            Item::Line(line) if is_label(line) => {
+                output.push(format_line(line, indent));
+                statement_indent = indent + 1;
+            }
+            Item::Line(line) => {
+                output.push(format_line(line, statement_indent));
+            }
+            Item::Block(block) => {
+                output.push(format_line(&block.header, statement_indent));
+                format_items(&block.body, statement_indent + 1, output);
+                if let Some((line, body)) = &block.else_branch {
+                    output.push(format_line(line, statement_indent));
+                    format_items(body, statement_indent + 1, output);
+                }
+                if let Some(line) = &block.end {
+                    output.push(format_line(line, statement_indent));
+                }
+            }
+        }
+    }
+}
+
+/// Check if a line holds a label declaration.
+fn is_label(line: &Line) -> bool {
+    match line.tokens.first() {
+        Some(token) => token.text.ends_with(':'),
+        None => false,
+    }
+}
+
+/// Format the code and comment of a single line. Returns `None` for a blank
+/// line.
+fn format_line(line: &Line, indent: usize) -> Option<OutputLine> {
+    if line.is_blank() {
+        return None;
+    }
+    Some(OutputLine {
+        indent,
+        code: format_tokens(&line.tokens),
+        comment: line
+            .comment
+            .as_ref()
+            .map(|comment| comment.text.trim_end().to_string()),
+        is_label: is_label(line),
+    })
+}
+
+/// Normalize the case of each token in a statement, and join them together.
+fn format_tokens(tokens: &[Token]) -> String {
+    let mnemonic = match tokens.first() {
+        Some(token) => token.text.as_str(),
+        None => return String::new(),
+    };
+    let first = if mnemonic.ends_with(':') {
+        // Labels are case-sensitive
+        mnemonic.to_string()
+    } else if mnemonic.starts_with('.') {
+        mnemonic.to_lowercase()
+    } else {
+        mnemonic.to_uppercase()
+    };
+
+    let last_index = tokens.len() - 1;
+    let args = tokens.iter().enumerate().skip(1).map(|(i, token)| {
+        if is_label_arg(mnemonic, &token.text, i == last_index) {
+            token.text.clone()
+        } else {
+            format_ref(&token.text)
+        }
+    });
+    iter::once(first).chain(args).collect::<Vec<_>>().join(" ")
+}
+
+/// Check if an argument to an instruction is a label. Labels are
+/// case-sensitive, so they can't be normalized like other arguments.
+fn is_label_arg(mnemonic: &str, arg: &str, is_last: bool) -> bool {
+    match mnemonic.to_uppercase().as_str() {
+        "CALL" => true,
+        // Anything other than a register is a label, including names like
+        // "s1" which look like a stack
+        "JMP" => {
+            let arg = arg.to_uppercase();
+            !arg.starts_with('[')
+                && (!is_reserved_name(&arg)
+                    || arg.starts_with(STACK_REF_TAG)
+                    || arg.starts_with(QUEUE_REF_TAG))
+        }
+        "JEZ" | "JNZ" | "JGZ" | "JLZ" | "JEQ" | "JNE" | "JLT" | "JGT"
+        | "JLE" | "JGE" => is_last,
+        _ => false,
+    }
+}
+
+/// Uppercase a register, stack, or queue reference, including indirect ones
+/// like `S[RX0]`. Anything else is returned as is, because user-defined names
+/// are case-sensitive.
+fn format_ref(text: &str) -> String {
+    if is_reserved_name(text) {
+        text.to_uppercase()
+    } else if text.len() >= 2 && text.starts_with('[') && text.ends_with(']') {
+        format!("[{}]", format_ref(&text[1..text.len() - 1]))
+    } else if (text.starts_with('s') || text.starts_with('S'))
+        && text[1..].starts_with('[')
+    {
+        format!("{}{}", STACK_REF_TAG, format_ref(&text[1..]))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Comments directly above a label describe the label, so they should line
+/// up with it, rather than with the statements above.
+fn indent_label_comments(lines: &mut [Option<OutputLine>]) {
+    let mut label_indent = None;
+    for line in lines.iter_mut().rev() {
+        match line {
+            Some(line) if !line.has_code() => {
+                if let Some(indent) = label_indent {
+                    line.indent = indent;
+                }
+            }
+            Some(line) if line.is_label => label_indent = Some(line.indent),
+            _ => label_indent = None,
+        }
+    }
+}
+
+/// Convert formatted lines into the final output. This aligns trailing
+/// comments within each run of consecutive lines of code, and cleans up
+/// blank lines.
+fn render(lines: &[Option<OutputLine>]) -> String {
+    // Find the column that comments should start at for each line
+    let mut comment_columns = vec![0; lines.len()];
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start..lines.len())
+            .find(|&i| lines[i].as_ref().filter(|l| l.has_code()).is_none())
+            .unwrap_or(lines.len());
+        let column = lines[start..end]
+            .iter()
+            .flatten()
+            .filter(|line| line.comment.is_some())
+            .map(OutputLine::width)
+            .max()
+            .unwrap_or(0);
+        for comment_column in &mut comment_columns[start..end] {
+            *comment_column = column;
+        }
+        start = end + 1;
+    }
+
+    let mut output = String::new();
+    let mut needs_blank = false;
+    for (line, comment_column) in lines.iter().zip(comment_columns) {
+        let line = match line {
+            Some(line) => line,
+            None => {
+                // Skip blank lines at the start of the file
+                needs_blank = !output.is_empty();
+                continue;
+            }
+        };
+
+        // This drops blank lines at the end, and collapses multiple ones
+        if needs_blank {
+            output.push('\n');
+            needs_blank = false;
+        }
+        let mut text = " ".repeat(line.indent * INDENT_WIDTH);
+        text.push_str(&line.code);
+        if let Some(comment) = &line.comment {
+            if line.has_code() {
+                text.push_str(&" ".repeat(comment_column - line.width() + 1));
+            }
+            text.push_str(comment);
+        }
+        output.push_str(&text);
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format the source, and make sure that formatting it again doesn't
+    /// change anything
+    fn format(source: &str) -> String {
+        let formatted = format_source(source);
+        assert_eq!(format_source(&formatted), formatted);
+        formatted
+    }
+
+    #[test]
+    fn test_case() {
+        assert_eq!(
+            format(
+                "
+.CONST max 0x1f
+.Alias counter rx1
+.include \"Lib.gdlk\"
+set rx0 max
+read [rx0]
+push rs0 s[rx1]
+pop s[counter] rzr
+enq 'a' q0
+if rli > Max
+    Else
+end
+jez rx0 s1
+jmp s1
+jmp rx2
+call rx0
+s1:
+rx0:
+jmp [Rx1]
+"
+            ),
+            "\
+.const max 0x1f
+.alias counter RX1
+.include \"Lib.gdlk\"
+SET RX0 max
+READ [RX0]
+PUSH RS0 S[RX1]
+POP S[counter] RZR
+ENQ 'a' Q0
+IF RLI > Max
+ELSE
+END
+JEZ RX0 s1
+JMP s1
+JMP RX2
+CALL rx0
+s1:
+rx0:
+    JMP [RX1]
+"
+        );
+    }
+
+    #[test]
+    fn test_indentation() {
+        assert_eq!(
+            format(
+                "
+  READ RX0
+START:
+; about the loop
+
+    ; more about the loop
+  .loop:
+WHILE RX0 > 0
+        ; inside the loop
+  IF RX0 == 1
+  WRITE RX0
+      ELSE
+    .inner:
+      SUB RX0 1
+  END
+       END
+ JMP START
+ IF RX0 == 1
+ WRITE RX0
+"
+            ),
+            "\
+READ RX0
+START:
+    ; about the loop
+
+; more about the loop
+.loop:
+    WHILE RX0 > 0
+        ; inside the loop
+        IF RX0 == 1
+            WRITE RX0
+        ELSE
+            .inner:
+                SUB RX0 1
+        END
+    END
+    JMP START
+    IF RX0 == 1
+        WRITE RX0
+"
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            format(
+                ";top\r\nREAD RX0 ; read it  \r\n\
+                 ADD   RX0   1 ;   add one\r\n\
+                 SUB RX0 1\r\n\
+                 MUL RX0 RX0\t;square\r\n\
+                 ; split\r\n\
+                 WRITE RX0 ;w\r\n\
+                 \r\n\
+                 SET RX0 ';' ; semicolon",
+            ),
+            "\
+;top
+READ RX0    ; read it
+ADD RX0 1   ;   add one
+SUB RX0 1
+MUL RX0 RX0 ;square
+; split
+WRITE RX0 ;w
+
+SET RX0 ';' ; semicolon
+"
+        );
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        assert_eq!(format(""), "");
+        assert_eq!(format("\n  \n\t\n"), "");
+        assert_eq!(
+            format("\n\nREAD RX0\n\n\n\nWRITE RX0\n  \n\n"),
+            "READ RX0\n\nWRITE RX0\n"
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        // Bad code should still be laid out, without losing anything
+        assert_eq!(
+            format("read rx0 rx1 'x\nfoo: bar \"baz ; qux\n  END\nELSE\n"),
+            "READ RX0 RX1 'x\nfoo: bar \"baz ; qux\n    END\n    ELSE\n"
+        );
+    }
+}
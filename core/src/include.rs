@@ -0,0 +1,179 @@
+//! Support for splitting a program across multiple files, via the `.include`
+//! directive. Where included files actually come from is up to the caller,
+//! through the [SourceLoader] trait.
+
+use crate::{
+    ast::{
+        source::{Block, Program, Statement},
+        Node,
+    },
+    consts::MAX_INCLUDE_DEPTH,
+    error::{CompileError, SourceErrorWrapper},
+    models::ValueWidth,
+    parse::parse,
+    util::{SourceFiles, Span},
+    Compiler,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Provides the source code for files that are pulled in with `.include`.
+/// This lets each environment decide where files come from, e.g. the CLI
+/// reads them from disk, while a web page might look them up in memory.
+pub trait SourceLoader {
+    /// Load the source code of a file. Paths in the main program are given
+    /// as written in the `.include` directive, with any `.` and `..`
+    /// segments collapsed. Paths in an included
+    /// file are relative to the directory of that file, so they're joined
+    /// onto it before being passed here, e.g. `.include "b.gdlk"` in
+    /// `lib/a.gdlk` loads `lib/b.gdlk`. Returns `None` if the file can't be
+    /// loaded.
+    fn load(&self, path: &str) -> Option<String>;
+}
+
+/// Loads files from an in-memory map of path to source code.
+impl SourceLoader for HashMap<String, String> {
+    fn load(&self, path: &str) -> Option<String> {
+        self.get(path).cloned()
+    }
+}
+
+/// State needed while expanding includes
+struct Context<'a> {
+    loader: &'a dyn SourceLoader,
+    value_width: ValueWidth,
+    sources: SourceFiles,
+    /// Paths of the files that are currently being expanded, from the
+    /// outermost to the innermost. Used to detect circular includes.
+    path_stack: Vec<String>,
+    /// Paths of every file that has already been expanded. Each file is only
+    /// included once, so that two files can share a common include.
+    included: HashSet<String>,
+    errors: Vec<SourceErrorWrapper<CompileError>>,
+}
+
+impl Context<'_> {
+    /// Replace every `.include` in the body with the parsed contents of the
+    /// included file. Includes inside blocks are expanded as well.
+    fn expand_body(&mut self, body: &mut Block<Span>) {
+        let mut expanded = Vec::with_capacity(body.len());
+        for mut stmt in body.drain(..) {
+            match stmt {
+                Node(Statement::Include(path), _) => {
+                    if let Some(included) = self.load(path) {
+                        expanded.extend(included);
+                    }
+                }
+                _ => {
+                    for body in stmt.0.bodies_mut() {
+                        self.expand_body(body);
+                    }
+                    expanded.push(stmt);
+                }
+            }
+        }
+        *body = expanded;
+    }
+
+    /// Load, parse, and expand one included file. Returns `None` if the file
+    /// couldn't be loaded. Any errors are added to the context. If the file
+    /// has syntax errors, the lines that did parse are still returned. If the
+    /// file was already included somewhere else, it's empty.
+    fn load(
+        &mut self,
+        Node(path, span): Node<String, Span>,
+    ) -> Option<Block<Span>> {
+        let path =
+            resolve_path(self.path_stack.last().map(String::as_str), &path);
+        if self.path_stack.contains(&path)
+            || self.path_stack.len() >= MAX_INCLUDE_DEPTH
+        {
+            self.error(CompileError::CircularInclude, span);
+            return None;
+        }
+        if self.included.contains(&path) {
+            return Some(Vec::new());
+        }
+
+        let source = match self.loader.load(&path) {
+            Some(source) => source,
+            None => {
+                self.error(CompileError::IncludeNotFound, span);
+                return None;
+            }
+        };
+        let file = self.sources.add_include(path.clone(), source);
+        let (Program { mut body }, errors) =
+            parse(&self.sources, file, self.value_width);
+        self.errors.extend(errors);
+        self.path_stack.push(path);
+        self.expand_body(&mut body);
+        let path = self.path_stack.pop().unwrap();
+        self.included.insert(path);
+        Some(body)
+    }
+
+    fn error(&mut self, error: CompileError, span: Span) {
+        self.errors
+            .push(SourceErrorWrapper::new(error, span, &self.sources));
+    }
+}
+
+/// Resolve an include path written in the given file. `None` means the main
+/// program, whose includes are used as-is. Otherwise the path is joined onto
+/// the directory of the including file, and any `.` and `..` segments are
+/// collapsed so that the same file always gets the same path. Absolute paths
+/// are never joined.
+fn resolve_path(including: Option<&str>, path: &str) -> String {
+    let dir = match including {
+        Some(including) if !path.starts_with('/') => {
+            including.rfind('/').map_or("", |i| &including[..=i])
+        }
+        _ => "",
+    };
+
+    let joined = format!("{}{}", dir, path);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." && !last.is_empty() => {
+                    segments.pop();
+                }
+                _ => segments.push(segment),
+            },
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+impl Compiler<Program<Span>> {
+    /// Expands all `.include` directives in the program, using the given
+    /// loader to get the source of each included file. Included files can
+    /// include other files too. Any errors from loading or parsing files are
+    /// returned alongside the expanded program, so that compilation can
+    /// continue and report all errors at once.
+    pub(crate) fn include(
+        mut self,
+        loader: &dyn SourceLoader,
+    ) -> (Self, Vec<SourceErrorWrapper<CompileError>>) {
+        let mut context = Context {
+            loader,
+            value_width: self.hardware_spec.value_width,
+            sources: self.sources,
+            path_stack: Vec::new(),
+            included: HashSet::new(),
+            errors: Vec::new(),
+        };
+        context.expand_body(&mut self.ast.body);
+
+        let compiler = Compiler {
+            sources: context.sources,
+            hardware_spec: self.hardware_spec,
+            ast: self.ast,
+            warnings: self.warnings,
+        };
+        (compiler, context.errors)
+    }
+}
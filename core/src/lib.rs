@@ -12,6 +12,7 @@
 //!     num_registers: 1,
 //!     num_stacks: 0,
 //!     max_stack_length: 0,
+//!     ..Default::default()
 //! };
 //! let program_spec = ProgramSpec::new(vec![1], vec![2]);
 //!
@@ -29,7 +30,7 @@
 //! ).unwrap();
 //!
 //! // Execute
-//! let mut machine = compiled.allocate(&program_spec);
+//! let mut machine = compiled.allocate(&program_spec).unwrap();
 //! machine.execute_all().unwrap();
 //! assert!(machine.successful());
 //! ```
@@ -38,23 +39,33 @@
 #![feature(or_patterns)]
 
 pub mod ast;
+pub mod cfg;
 mod consts;
+pub mod cst;
 mod delabel;
 pub mod error;
+mod format;
+mod include;
+mod lint;
 mod machine;
 mod models;
 mod parse;
+mod resolve;
 mod util;
 mod validate;
 
-pub use consts::MAX_CYCLE_COUNT;
+pub use consts::{MAX_CYCLE_COUNT, MAX_MEMORY_SIZE};
+pub use format::format_source;
+pub use include::SourceLoader;
 pub use machine::*;
 pub use models::*;
-pub use util::Span;
+pub use util::{FileId, SourceFiles, Span};
 
 use ast::compiled::Program;
-use error::{CompileError, WithSource};
-use std::fmt::Debug;
+use error::{
+    CompileError, CompileWarning, SourceErrorWrapper, SpecError, WithSource,
+};
+use std::{collections::HashMap, fmt::Debug};
 
 /// Struct used to compile a program. `T` represents the current type of the
 /// program. It starts as a [String], and as the compiler executes, the program
@@ -63,9 +74,11 @@ use std::fmt::Debug;
 #[derive(Debug)]
 pub struct Compiler<T: Debug> {
     // These are deliberately private, to prevent direct construction
-    source: String,
+    sources: SourceFiles,
     hardware_spec: HardwareSpec,
     ast: T,
+    /// Warnings from the lint pass, which don't stop compilation
+    warnings: Vec<SourceErrorWrapper<CompileWarning>>,
 }
 
 impl Compiler<()> {
@@ -74,22 +87,50 @@ impl Compiler<()> {
     /// resulting compiled program can be used directly (e.g. for interactive
     /// syntax) or used to allocate a [Machine] that can be executed. See
     /// library-level documentation for more info.
+    ///
+    /// Any `.include` directives in the source will fail to load. Use
+    /// [Self::compile_with_loader] to support them.
     pub fn compile(
         source: String,
         hardware_spec: HardwareSpec,
     ) -> Result<Compiler<Program<Span>>, WithSource<CompileError>> {
-        Ok(Self {
+        Self::compile_with_loader(
             source,
             hardware_spec,
+            &HashMap::<String, String>::new(),
+        )
+    }
+
+    /// Compile a source program, like [Self::compile], but use the given
+    /// [SourceLoader] to get the source of any files that are pulled in with
+    /// `.include`.
+    pub fn compile_with_loader(
+        source: String,
+        hardware_spec: HardwareSpec,
+        loader: &dyn SourceLoader,
+    ) -> Result<Compiler<Program<Span>>, WithSource<CompileError>> {
+        // Parsing and includes can recover from errors, so hang onto those
+        // errors and let validation report them, along with its own
+        let (compiler, mut errors) = Self {
+            sources: SourceFiles::new(source),
+            hardware_spec,
             ast: (),
+            warnings: Vec::new(),
         }
         .debug()
-        .parse()?
-        .debug()
-        .validate()?
-        .debug()
-        .delabel()
-        .debug())
+        .parse();
+        let (compiler, include_errors) = compiler.debug().include(loader);
+        errors.extend(include_errors);
+
+        compiler
+            .debug()
+            .validate(errors)?
+            .debug()
+            .lint()
+            .debug()
+            .delabel()
+            .debug()
+            .check_instruction_count()
     }
 }
 
@@ -99,11 +140,35 @@ impl Compiler<Program<Span>> {
         &self.ast
     }
 
+    /// Returns the source files that the program was compiled from.
+    pub fn sources(&self) -> &SourceFiles {
+        &self.sources
+    }
+
+    /// Returns the warnings that were found while compiling the program. The
+    /// warnings don't prevent the program from running, but they probably
+    /// point to mistakes.
+    pub fn warnings(&self) -> WithSource<CompileWarning> {
+        WithSource::new(self.warnings.clone(), self.sources.clone())
+    }
+
     /// Allocate a new [Machine] to execute a compiled program. The returned
     /// machine can then be executed. `program_spec` defines the parameters
-    /// under which the program will execute.
-    pub fn allocate(self, program_spec: &ProgramSpec) -> Machine {
-        Machine::new(self.hardware_spec, program_spec, self.ast, self.source)
+    /// under which the program will execute. Returns an error if the hardware
+    /// spec is out of bounds (see [HardwareSpec::validate]), or if the program
+    /// spec can't run on it (see [ProgramSpec::validate]).
+    pub fn allocate(
+        self,
+        program_spec: &ProgramSpec,
+    ) -> Result<Machine, SpecError> {
+        self.hardware_spec.validate()?;
+        program_spec.validate(&self.hardware_spec)?;
+        Ok(Machine::new(
+            self.hardware_spec,
+            program_spec,
+            self.ast,
+            self.sources,
+        ))
     }
 }
 
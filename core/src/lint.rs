@@ -0,0 +1,319 @@
+//! Lints for code that is valid, but probably isn't what the user meant. Unlike
+//! validation, lints never stop a program from compiling. They just produce
+//! warnings.
+
+use crate::{
+    ast::{
+        source::{LabelDecl, ResolvedProgram, ResolvedStatement, Statement},
+        Jump, Label, Node, Operator, RegisterRef, SpanNode, StackId,
+        StackOperand, StackRef, UserRegisterId, ValueSource,
+    },
+    error::{CompileWarning, SourceErrorWrapper},
+    util::Span,
+    Compiler,
+};
+use std::collections::HashSet;
+
+/// Everything in the program that the lints care about. This is collected in
+/// one pass over the program, and then each lint checks what it needs.
+#[derive(Default)]
+struct Usage<'a> {
+    /// Labels that are jumped to or called. A reference to a local label
+    /// also counts as a reference to the global label that scopes it.
+    referenced_labels: HashSet<&'a str>,
+    /// Every label declaration
+    declared_labels: Vec<(&'a Label, Span)>,
+    /// User registers that are read anywhere
+    read_registers: HashSet<UserRegisterId>,
+    /// If there is an indirect register read, any register could be read
+    reads_any_register: bool,
+    /// Every direct write to a user register
+    register_writes: Vec<(UserRegisterId, Span)>,
+    /// Stacks that are popped anywhere
+    popped_stacks: HashSet<StackId>,
+    /// If there is an indirect pop, any stack could be popped
+    pops_any_stack: bool,
+    /// Every push onto a stack. The stack is `None` for an indirect push.
+    pushes: Vec<(Option<StackId>, Span)>,
+    /// Every `READ` into `RZR`
+    null_reads: Vec<Span>,
+}
+
+impl<'a> Usage<'a> {
+    /// Record all usages in a body, including inside blocks.
+    fn add_body(&mut self, body: &'a [SpanNode<ResolvedStatement<Span>>]) {
+        for stmt in body {
+            self.add_statement(stmt.value());
+            for nested_body in stmt.value().bodies() {
+                self.add_body(nested_body);
+            }
+        }
+    }
+
+    fn add_statement(&mut self, stmt: &'a ResolvedStatement<Span>) {
+        match stmt {
+            Statement::Label(Node(LabelDecl(label), span)) => {
+                self.declared_labels.push((label, *span));
+            }
+            Statement::Operator(Node(op, _)) => self.add_operator(op),
+            Statement::Jump(Node(jump, _), Node(label, _)) => {
+                self.add_jump(jump);
+                self.reference_label(label);
+            }
+            Statement::ComputedJump(Node(target, _)) => self.read_value(target),
+            Statement::Call(Node(label, _)) => self.reference_label(label),
+            Statement::If {
+                condition: Node(jump, _),
+                ..
+            }
+            | Statement::While {
+                condition: Node(jump, _),
+                ..
+            } => self.add_jump(jump),
+            Statement::Return | Statement::Halt => {}
+            // These are removed before linting
+            Statement::Directive(_) | Statement::Include(_) => unreachable!(),
+        }
+    }
+
+    fn add_operator(&mut self, op: &Operator<Span>) {
+        match op {
+            Operator::Read(dst) => {
+                if let Node(RegisterRef::Null, span) = dst {
+                    self.null_reads.push(*span);
+                }
+                self.write_register(dst);
+            }
+            Operator::Write(Node(src, _)) => self.read_value(src),
+            Operator::Set(dst, Node(src, _)) => {
+                self.write_register(dst);
+                self.read_value(src);
+            }
+            // These use the destination's old value, so it's a read too
+            Operator::Add(Node(dst, _), Node(src, _))
+            | Operator::Sub(Node(dst, _), Node(src, _))
+            | Operator::Mul(Node(dst, _), Node(src, _))
+            | Operator::Div(Node(dst, _), Node(src, _))
+            | Operator::Mod(Node(dst, _), Node(src, _))
+            | Operator::And(Node(dst, _), Node(src, _))
+            | Operator::Or(Node(dst, _), Node(src, _))
+            | Operator::Xor(Node(dst, _), Node(src, _))
+            | Operator::Shl(Node(dst, _), Node(src, _))
+            | Operator::Shr(Node(dst, _), Node(src, _)) => {
+                self.read_register(dst);
+                self.read_value(src);
+            }
+            Operator::Neg(Node(dst, _)) => self.read_register(dst),
+            Operator::Cmp(dst, Node(src_1, _), Node(src_2, _)) => {
+                self.write_register(dst);
+                self.read_value(src_1);
+                self.read_value(src_2);
+            }
+            Operator::Rand(dst) => self.write_register(dst),
+            Operator::Push(Node(src, _), Node(stack, span)) => {
+                self.read_value(src);
+                let stack_id = self.stack_id(stack);
+                self.pushes.push((stack_id, *span));
+            }
+            Operator::Pop(Node(stack, _), dst) => {
+                match self.stack_id(stack) {
+                    Some(stack_id) => {
+                        self.popped_stacks.insert(stack_id);
+                    }
+                    None => self.pops_any_stack = true,
+                }
+                self.write_register(dst);
+            }
+            Operator::Peek(Node(stack, _), dst) => {
+                self.stack_id(stack);
+                self.write_register(dst);
+            }
+            Operator::Dup(Node(stack, _)) | Operator::Swap(Node(stack, _)) => {
+                self.stack_id(stack);
+            }
+            Operator::Enq(Node(src, _), _) => self.read_value(src),
+            Operator::Deq(_, dst) => self.write_register(dst),
+            Operator::Load(dst, Node(address, _)) => {
+                self.write_register(dst);
+                self.read_value(address);
+            }
+            Operator::Store(Node(src, _), Node(address, _)) => {
+                self.read_value(src);
+                self.read_value(address);
+            }
+        }
+    }
+
+    fn add_jump(&mut self, jump: &Jump<Span>) {
+        match jump {
+            Jump::Jmp => {}
+            Jump::Jez(Node(src, _))
+            | Jump::Jnz(Node(src, _))
+            | Jump::Jlz(Node(src, _))
+            | Jump::Jgz(Node(src, _)) => self.read_value(src),
+            Jump::Jeq(Node(src_1, _), Node(src_2, _))
+            | Jump::Jne(Node(src_1, _), Node(src_2, _))
+            | Jump::Jlt(Node(src_1, _), Node(src_2, _))
+            | Jump::Jgt(Node(src_1, _), Node(src_2, _))
+            | Jump::Jle(Node(src_1, _), Node(src_2, _))
+            | Jump::Jge(Node(src_1, _), Node(src_2, _)) => {
+                self.read_value(src_1);
+                self.read_value(src_2);
+            }
+        }
+    }
+
+    fn reference_label(&mut self, label: &'a Label) {
+        self.referenced_labels.insert(label);
+        // Local labels are qualified as `GLOBAL.local`, and using the local
+        // label means the global one is needed to scope it
+        if let Some(i) = label.find('.') {
+            if i > 0 {
+                self.referenced_labels.insert(&label[..i]);
+            }
+        }
+    }
+
+    fn read_value(&mut self, src: &ValueSource<Span>) {
+        if let ValueSource::Register(Node(reg_ref, _)) = src {
+            self.read_register(reg_ref);
+        }
+    }
+
+    fn read_register(&mut self, reg_ref: &RegisterRef) {
+        match reg_ref {
+            RegisterRef::User(reg_id) => {
+                self.read_registers.insert(*reg_id);
+            }
+            RegisterRef::Indirect(index_reg) => {
+                self.reads_any_register = true;
+                self.read_register(index_reg);
+            }
+            _ => {}
+        }
+    }
+
+    fn write_register(&mut self, reg_ref: &SpanNode<RegisterRef>) {
+        match reg_ref {
+            Node(RegisterRef::User(reg_id), span) => {
+                self.register_writes.push((*reg_id, *span));
+            }
+            // We don't know which register gets written, but the index is
+            // definitely read
+            Node(RegisterRef::Indirect(index_reg), _) => {
+                self.read_register(index_reg)
+            }
+            _ => {}
+        }
+    }
+
+    /// Get the ID of the stack in an operand, if it's known statically. If
+    /// the stack is indirect, its index register is read.
+    fn stack_id(&mut self, stack: &StackOperand) -> Option<StackId> {
+        match stack {
+            StackOperand::Direct(StackRef(stack_id)) => Some(*stack_id),
+            StackOperand::Indirect(index_reg) => {
+                self.read_register(index_reg);
+                None
+            }
+        }
+    }
+
+    /// Get warnings for all the lints that depend on usage.
+    fn warnings(&self) -> Vec<(CompileWarning, Span)> {
+        let mut warnings = Vec::new();
+
+        for (label, span) in &self.declared_labels {
+            if !self.referenced_labels.contains(label.as_str()) {
+                warnings.push((CompileWarning::UnusedLabel, *span));
+            }
+        }
+
+        if !self.reads_any_register {
+            for (reg_id, span) in &self.register_writes {
+                if !self.read_registers.contains(reg_id) {
+                    warnings.push((CompileWarning::DeadStore, *span));
+                }
+            }
+        }
+
+        for span in &self.null_reads {
+            warnings.push((CompileWarning::ReadIntoNull, *span));
+        }
+
+        if !self.pops_any_stack {
+            for (stack_id, span) in &self.pushes {
+                let popped = match stack_id {
+                    Some(stack_id) => self.popped_stacks.contains(stack_id),
+                    // An indirect push could go to any stack, so it's only
+                    // a problem if nothing is ever popped
+                    None => !self.popped_stacks.is_empty(),
+                };
+                if !popped {
+                    warnings.push((CompileWarning::UnpoppedPush, *span));
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Find code that comes right after a statement that never continues to the
+/// next one. A label makes the code after it reachable again, since it could
+/// be jumped to. Only the first statement in each unreachable run is reported.
+/// A computed jump only ends the reachable code if its target is a constant,
+/// since a register could hold the index of the next statement.
+fn find_unreachable(
+    body: &[SpanNode<ResolvedStatement<Span>>],
+    warnings: &mut Vec<(CompileWarning, Span)>,
+) {
+    let mut reachable = true;
+    let mut reported = false;
+    for stmt in body {
+        match stmt.value() {
+            Statement::Label(_) => {
+                reachable = true;
+                reported = false;
+            }
+            _ if !reachable => {
+                if !reported {
+                    warnings.push((
+                        CompileWarning::UnreachableCode,
+                        *stmt.metadata(),
+                    ));
+                    reported = true;
+                }
+            }
+            Statement::Jump(Node(Jump::Jmp, _), _)
+            | Statement::ComputedJump(Node(ValueSource::Const(_), _))
+            | Statement::Return
+            | Statement::Halt => reachable = false,
+            _ => {}
+        }
+        for nested_body in stmt.value().bodies() {
+            find_unreachable(nested_body, warnings);
+        }
+    }
+}
+
+impl Compiler<ResolvedProgram<Span>> {
+    /// Checks the program for code that is probably a mistake, and records a
+    /// warning for each one. This has to run after validation, so that all
+    /// symbols are resolved and all labels have their unique names.
+    pub(crate) fn lint(mut self) -> Self {
+        let mut usage = Usage::default();
+        usage.add_body(&self.ast.body);
+        let mut warnings = usage.warnings();
+        find_unreachable(&self.ast.body, &mut warnings);
+
+        // Each lint found its own warnings, so put them back in source order
+        warnings.sort_by_key(|(_, span)| (span.file, span.offset));
+        let sources = &self.sources;
+        self.warnings
+            .extend(warnings.into_iter().map(|(warning, span)| {
+                SourceErrorWrapper::new(warning, span, sources)
+            }));
+        self
+    }
+}
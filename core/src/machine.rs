@@ -3,17 +3,20 @@ use crate::ast::wasm::{LangValueArrayMap, LangValueMap, SourceElement};
 use crate::{
     ast::{
         compiled::{Instruction, Program},
-        Jump, LangValue, Node, Operator, RegisterRef, SpanNode, StackRef,
-        ValueSource,
+        Jump, LangValue, Node, Operator, QueueRef, RegisterRef, SpanNode,
+        StackId, StackOperand, StackRef, ValueSource,
     },
-    consts::MAX_CYCLE_COUNT,
     debug,
     error::{RuntimeError, SourceErrorWrapper, WithSource},
-    models::{HardwareSpec, ProgramSpec},
-    util::Span,
+    models::{HardwareSpec, OverflowMode, ProgramSpec},
+    util::{Rng, SourceFiles, Span},
 };
 use std::{
-    cmp::Ordering, collections::HashMap, convert::TryInto, iter, num::Wrapping,
+    cmp::Ordering,
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    iter,
+    rc::Rc,
 };
 #[cfg(feature = "wasm")]
 use wasm_bindgen::{prelude::*, JsCast};
@@ -32,8 +35,10 @@ pub struct Machine {
     // serialization. We store these ourselves instead of keeping references
     // to the originals because it just makes life a lot easier.
     hardware_spec: HardwareSpec,
-    source: String,
-    program: Program<Span>,
+    sources: SourceFiles,
+    /// Shared so that the current instruction can be borrowed while the rest
+    /// of the machine state changes, without copying it
+    program: Rc<Program<Span>>,
     expected_output: Vec<LangValue>,
 
     // Runtime state
@@ -52,6 +57,19 @@ pub struct Machine {
     /// The series of stacks that act as the programs RAM. The number of stacks
     /// and their capacity is determined by the initializating hardware spec.
     stacks: Vec<Vec<LangValue>>,
+    /// The series of queues. Like the input, values are always removed from
+    /// the front, which is fine because these will be small.
+    queues: Vec<Vec<LangValue>>,
+    /// Random-access memory, which can be read with LOAD and written with
+    /// STORE. The size is fixed by the hardware spec, and every cell starts
+    /// at zero.
+    memory: Vec<LangValue>,
+    /// Return addresses for all subroutine calls that are currently in
+    /// progress. The top of the stack is where the next RET will resume.
+    call_stack: Vec<usize>,
+    /// Generator for RAND, seeded by the program spec so that every run is
+    /// reproducible
+    rng: Rng,
     /// The number of instructions that have been executed so far. This is not
     /// unique, so repeated instructions are counted multiple times.
     cycle_count: usize,
@@ -68,24 +86,31 @@ impl Machine {
         hardware_spec: HardwareSpec,
         program_spec: &ProgramSpec,
         program: Program<Span>,
-        source: String,
+        sources: SourceFiles,
     ) -> Self {
         let registers =
             iter::repeat(0).take(hardware_spec.num_registers).collect();
 
         // Initialize `num_stacks` new stacks. Set an initial capacity
         // for each one to prevent grows during program operation
-        let stacks = iter::repeat_with(|| {
-            Vec::with_capacity(hardware_spec.max_stack_length)
+        let stacks = (0..hardware_spec.num_stacks)
+            .map(|stack_id| {
+                Vec::with_capacity(hardware_spec.stack_capacity(stack_id))
+            })
+            .collect();
+        let queues = iter::repeat_with(|| {
+            Vec::with_capacity(hardware_spec.max_queue_length)
         })
-        .take(hardware_spec.num_stacks)
+        .take(hardware_spec.num_queues)
         .collect();
+        let memory = vec![0; hardware_spec.memory_size];
+        let call_stack = Vec::with_capacity(hardware_spec.max_call_depth);
 
         Self {
             // Static data
             hardware_spec,
-            program,
-            source,
+            program: Rc::new(program),
+            sources,
             expected_output: program_spec.expected_output().into(),
 
             // Runtime state
@@ -94,6 +119,10 @@ impl Machine {
             output: Vec::new(),
             registers,
             stacks,
+            queues,
+            memory,
+            call_stack,
+            rng: Rng::new(program_spec.seed()),
 
             // Performance stats
             cycle_count: 0,
@@ -103,43 +132,187 @@ impl Machine {
 
     /// Gets a source value, which could either be a constant or a register.
     /// If the value is a constant, just return that. If it's a register,
-    /// return the value from that register. Panics if the register reference is
-    /// invalid (shouldn't be possible because of validation).
-    fn get_val_from_src(&self, src: &SpanNode<ValueSource<Span>>) -> LangValue {
+    /// return the value from that register. Returns an error if the register
+    /// is indirect and its index is out of range. Panics if the register
+    /// reference is invalid (shouldn't be possible because of validation).
+    fn get_val_from_src(
+        &self,
+        src: &SpanNode<ValueSource<Span>>,
+    ) -> Result<LangValue, (RuntimeError, Span)> {
         match src.value() {
-            ValueSource::Const(Node(val, _)) => *val,
-            ValueSource::Register(reg_ref) => self.get_reg(*reg_ref.value()),
+            ValueSource::Const(Node(val, _)) => Ok(*val),
+            ValueSource::Register(reg_ref) => self.read_reg(reg_ref),
         }
     }
 
+    /// Resolves an indirect register reference to the user register that its
+    /// index currently points to. Direct references are returned unchanged.
+    /// `span` is the span of the operand, which is used for the error if the
+    /// index is out of range.
+    fn resolve_reg(
+        &self,
+        reg: &RegisterRef,
+        span: Span,
+    ) -> Result<RegisterRef, (RuntimeError, Span)> {
+        match reg {
+            RegisterRef::Indirect(index_reg) => {
+                let index = self.get_reg(&self.resolve_reg(index_reg, span)?);
+                match index.try_into() {
+                    Ok(reg_id) if reg_id < self.registers.len() => {
+                        Ok(RegisterRef::User(reg_id))
+                    }
+                    _ => Err((
+                        RuntimeError::InvalidRegisterIndex { index },
+                        span,
+                    )),
+                }
+            }
+            _ => Ok(reg.clone()),
+        }
+    }
+
+    /// Reads the value from the given register, resolving it first if it's
+    /// indirect.
+    fn read_reg(
+        &self,
+        reg: &SpanNode<RegisterRef>,
+    ) -> Result<LangValue, (RuntimeError, Span)> {
+        Ok(self.get_reg(&self.resolve_reg(reg.value(), *reg.metadata())?))
+    }
+
     /// Gets the value from the given register. The register reference is
-    /// assumed to be valid (should be validated at build time). Will panic if
-    /// it isn't valid.
-    fn get_reg(&self, reg: RegisterRef) -> LangValue {
+    /// assumed to be valid (should be validated at build time) and direct.
+    /// Will panic if it isn't valid or is indirect.
+    fn get_reg(&self, reg: &RegisterRef) -> LangValue {
         match reg {
             RegisterRef::Null => 0,
-            // These conversion unwraps are safe because we know that input
-            // and stack lengths are bounded by validation rules to fit into an
-            // i32 (max length is 256 at the time of writing this)
-            RegisterRef::InputLength => self.input.len().try_into().unwrap(),
+            RegisterRef::InputLength => self.get_length_value(self.input.len()),
+            // The PC is bounded by the program length, which can also be
+            // bigger than the value width
+            RegisterRef::ProgramCounter => {
+                self.get_length_value(self.program_counter)
+            }
+            RegisterRef::CycleCount => self.get_length_value(self.cycle_count),
             RegisterRef::StackLength(stack_id) => {
-                self.stacks[stack_id].len().try_into().unwrap()
+                self.get_length_value(self.stacks[*stack_id].len())
+            }
+            RegisterRef::QueueLength(queue_id) => {
+                self.get_length_value(self.queues[*queue_id].len())
+            }
+            RegisterRef::User(reg_id) => *self.registers.get(*reg_id).unwrap(),
+            RegisterRef::Indirect(_) => {
+                panic!("Unresolved indirect register {:?}", reg)
             }
-            RegisterRef::User(reg_id) => *self.registers.get(reg_id).unwrap(),
         }
     }
 
-    /// Sets the register to the given value. The register reference is
+    /// Converts a length (e.g. of the input or a stack) to a value. Lengths can
+    /// be bigger than the hardware's value width allows (e.g. 256 on 8-bit
+    /// hardware), so the value is clamped to the width.
+    fn get_length_value(&self, length: usize) -> LangValue {
+        // This conversion unwrap is safe because we know that input and stack
+        // lengths are bounded by validation rules (max length is 256 at the
+        // time of writing this)
+        self.hardware_spec
+            .value_width
+            .saturate(length.try_into().unwrap())
+    }
+
+    /// Sets the register to the given value. Returns an error if the register
+    /// is indirect and its index is out of range. The register reference is
     /// assumed to be valid and writable (should be validated at build time).
     /// Will panic if it isn't valid/writable.
-    fn set_reg(&mut self, reg: &SpanNode<RegisterRef>, value: LangValue) {
-        match reg.value() {
+    fn set_reg(
+        &mut self,
+        reg: &SpanNode<RegisterRef>,
+        value: LangValue,
+    ) -> Result<(), (RuntimeError, Span)> {
+        match self.resolve_reg(reg.value(), *reg.metadata())? {
             RegisterRef::Null => {} // /dev/null behavior - trash any input
-            RegisterRef::InputLength | RegisterRef::StackLength(_) => {
+            RegisterRef::InputLength
+            | RegisterRef::ProgramCounter
+            | RegisterRef::CycleCount
+            | RegisterRef::StackLength(_)
+            | RegisterRef::QueueLength(_) => {
                 panic!("Unwritable register {:?}", reg)
             }
             RegisterRef::User(reg_id) => {
-                self.registers[*reg_id] = value;
+                self.registers[reg_id] = value;
+            }
+            // Resolving never returns an indirect reference
+            RegisterRef::Indirect(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Fits the result of an arithmetic operation into the hardware's value
+    /// width. If the value doesn't fit, the hardware's overflow mode decides
+    /// what happens. `span` is the span of the instruction, which is used for
+    /// the error if the value overflows on trapping hardware.
+    fn fit_value(
+        &self,
+        value: i64,
+        span: Span,
+    ) -> Result<LangValue, (RuntimeError, Span)> {
+        let width = self.hardware_spec.value_width;
+        if width.contains(value) {
+            // We know it fits, so the cast is lossless
+            return Ok(value as LangValue);
+        }
+        match self.hardware_spec.overflow_mode {
+            OverflowMode::Wrapping => Ok(width.wrap(value)),
+            OverflowMode::Saturating => Ok(width.saturate(value)),
+            OverflowMode::Trapping => Err((RuntimeError::Overflow, span)),
+        }
+    }
+
+    /// Applies an arithmetic operation to the value in `dst` and the value
+    /// from `src`, and stores the result in `dst`. The operation is calculated
+    /// with extra precision, then fit back into the value width.
+    fn apply_arithmetic(
+        &mut self,
+        dst: &SpanNode<RegisterRef>,
+        src: &SpanNode<ValueSource<Span>>,
+        span: Span,
+        op: impl Fn(i64, i64) -> i64,
+    ) -> Result<(), (RuntimeError, Span)> {
+        let value = op(
+            self.read_reg(dst)?.into(),
+            self.get_val_from_src(src)?.into(),
+        );
+        self.set_reg(dst, self.fit_value(value, span)?)
+    }
+
+    /// Gets the number of bits to shift by for a shift operator. Shift amounts
+    /// wrap around the number of bits in a value.
+    fn get_shift_amount(
+        &self,
+        src: &SpanNode<ValueSource<Span>>,
+    ) -> Result<u32, (RuntimeError, Span)> {
+        let bits = self.hardware_spec.value_width.bits() as LangValue;
+        // rem_euclid is never negative, so the cast is lossless
+        Ok(self.get_val_from_src(src)?.rem_euclid(bits) as u32)
+    }
+
+    /// Gets the ID of the stack that a stack operand refers to. For indirect
+    /// operands, returns an error if the index is out of range. Direct stack
+    /// references are assumed to be valid (should be validated at build time).
+    fn get_stack_id(
+        &self,
+        stack_ref: &SpanNode<StackOperand>,
+    ) -> Result<StackId, (RuntimeError, Span)> {
+        match stack_ref {
+            Node(StackOperand::Direct(StackRef(stack_id)), _) => Ok(*stack_id),
+            Node(StackOperand::Indirect(index_reg), span) => {
+                let index = self.get_reg(&self.resolve_reg(index_reg, *span)?);
+                match index.try_into() {
+                    Ok(stack_id) if stack_id < self.stacks.len() => {
+                        Ok(stack_id)
+                    }
+                    _ => {
+                        Err((RuntimeError::InvalidStackIndex { index }, *span))
+                    }
+                }
             }
         }
     }
@@ -149,15 +322,16 @@ impl Machine {
     /// reference is invalid, will panic (should be validated at build time).
     fn push_stack(
         &mut self,
-        stack_ref: &SpanNode<StackRef>,
+        stack_ref: &SpanNode<StackOperand>,
         value: LangValue,
     ) -> Result<(), (RuntimeError, Span)> {
+        let stack_id = self.get_stack_id(stack_ref)?;
         // Have to access this first cause borrow checker
-        let max_stack_length = self.hardware_spec.max_stack_length;
-        let stack = &mut self.stacks[stack_ref.value().0];
+        let capacity = self.hardware_spec.stack_capacity(stack_id);
+        let stack = &mut self.stacks[stack_id];
 
         // If the stack is capacity, make sure we're not over it
-        if stack.len() >= max_stack_length {
+        if stack.len() >= capacity {
             return Err((RuntimeError::StackOverflow, *stack_ref.metadata()));
         }
 
@@ -171,9 +345,10 @@ impl Machine {
     /// build time).
     fn pop_stack(
         &mut self,
-        stack_ref: &SpanNode<StackRef>,
+        stack_ref: &SpanNode<StackOperand>,
     ) -> Result<LangValue, (RuntimeError, Span)> {
-        let stack = &mut self.stacks[stack_ref.value().0];
+        let stack_id = self.get_stack_id(stack_ref)?;
+        let stack = &mut self.stacks[stack_id];
 
         if let Some(val) = stack.pop() {
             Ok(val)
@@ -182,6 +357,76 @@ impl Machine {
         }
     }
 
+    /// Gets the top `count` values of the given stack, without removing them.
+    /// The last element of the returned slice is the top of the stack. If the
+    /// stack has fewer than `count` values, an error is returned. If the stack
+    /// reference is invalid, will panic (should be validated at build time).
+    fn get_stack_top(
+        &mut self,
+        stack_ref: &SpanNode<StackOperand>,
+        count: usize,
+    ) -> Result<&mut [LangValue], (RuntimeError, Span)> {
+        let stack_id = self.get_stack_id(stack_ref)?;
+        let stack = &mut self.stacks[stack_id];
+
+        if stack.len() < count {
+            return Err((RuntimeError::StackUnderflow, *stack_ref.metadata()));
+        }
+
+        let len = stack.len();
+        Ok(&mut stack[len - count..])
+    }
+
+    /// Adds the given value to the back of the given queue. If the queue is
+    /// at capacity, an error is returned. If the queue reference is invalid,
+    /// will panic (should be validated at build time).
+    fn enqueue(
+        &mut self,
+        queue_ref: &SpanNode<QueueRef>,
+        value: LangValue,
+    ) -> Result<(), (RuntimeError, Span)> {
+        // Have to access this first cause borrow checker
+        let max_queue_length = self.hardware_spec.max_queue_length;
+        let queue = &mut self.queues[queue_ref.value().0];
+
+        if queue.len() >= max_queue_length {
+            return Err((RuntimeError::QueueOverflow, *queue_ref.metadata()));
+        }
+
+        queue.push(value);
+        Ok(())
+    }
+
+    /// Removes the element at the front of the given queue. If the queue is
+    /// empty, an error is returned. If the queue reference is invalid, will
+    /// panic (should be validated at build time).
+    fn dequeue(
+        &mut self,
+        queue_ref: &SpanNode<QueueRef>,
+    ) -> Result<LangValue, (RuntimeError, Span)> {
+        let queue = &mut self.queues[queue_ref.value().0];
+
+        if queue.is_empty() {
+            Err((RuntimeError::EmptyQueue, *queue_ref.metadata()))
+        } else {
+            Ok(queue.remove(0))
+        }
+    }
+
+    /// Converts a value to an index into memory. If the value is negative or
+    /// past the end of memory, an error is returned. `span` is the span of the
+    /// instruction, which is used for the error.
+    fn get_memory_index(
+        &self,
+        address: LangValue,
+        span: Span,
+    ) -> Result<usize, (RuntimeError, Span)> {
+        match address.try_into() {
+            Ok(index) if index < self.memory.len() => Ok(index),
+            _ => Err((RuntimeError::MemoryOutOfBounds { address }, span)),
+        }
+    }
+
     /// Internal function to execute the next instruction. The return value
     /// is the same as [Self::execute_next], except the error needs to be
     /// wrapped before being handed to the user.
@@ -191,15 +436,16 @@ impl Machine {
             return Ok(false);
         }
 
-        let instr_node: SpanNode<Instruction<Span>> =
-            match self.program.instructions.get(self.program_counter) {
-                Some(instr_node) => *instr_node,
+        let program = Rc::clone(&self.program);
+        let instr_node: &SpanNode<Instruction<Span>> =
+            match program.instructions.get(self.program_counter) {
+                Some(instr_node) => instr_node,
                 // out of instructions to execute, just give up
                 None => return Ok(false),
             };
 
         // Prevent infinite loops
-        if self.cycle_count >= MAX_CYCLE_COUNT {
+        if self.cycle_count >= self.hardware_spec.max_cycles {
             // Include the instruction that triggered the error
             return Err((RuntimeError::TooManyCycles, *instr_node.metadata()));
         }
@@ -222,68 +468,133 @@ impl Machine {
                         } else {
                             // Remove the first element in the input
                             let val = self.input.remove(0);
-                            self.set_reg(&reg, val);
+                            self.set_reg(&reg, val)?;
                         }
                     }
                     Operator::Write(src) => {
-                        self.output.push(self.get_val_from_src(&src));
+                        self.output.push(self.get_val_from_src(&src)?);
                     }
                     Operator::Set(dst, src) => {
-                        self.set_reg(&dst, self.get_val_from_src(&src));
+                        self.set_reg(&dst, self.get_val_from_src(&src)?)?;
                     }
                     Operator::Add(dst, src) => {
+                        self.apply_arithmetic(&dst, &src, *span, |a, b| a + b)?;
+                    }
+                    Operator::Sub(dst, src) => {
+                        self.apply_arithmetic(&dst, &src, *span, |a, b| a - b)?;
+                    }
+                    Operator::Mul(dst, src) => {
+                        self.apply_arithmetic(&dst, &src, *span, |a, b| a * b)?;
+                    }
+                    Operator::Div(dst, src) => {
+                        if self.get_val_from_src(&src)? == 0 {
+                            return Err((RuntimeError::DivideByZero, *span));
+                        }
+                        // This rounds towards zero. It can still overflow,
+                        // e.g. MIN / -1
+                        self.apply_arithmetic(&dst, &src, *span, |a, b| a / b)?;
+                    }
+                    Operator::Mod(dst, src) => {
+                        if self.get_val_from_src(&src)? == 0 {
+                            return Err((RuntimeError::DivideByZero, *span));
+                        }
+                        self.apply_arithmetic(&dst, &src, *span, |a, b| a % b)?;
+                    }
+                    Operator::Neg(dst) => {
+                        let value = -i64::from(self.read_reg(&dst)?);
+                        self.set_reg(&dst, self.fit_value(value, *span)?)?;
+                    }
+                    Operator::And(dst, src) => {
                         self.set_reg(
                             &dst,
-                            (Wrapping(self.get_reg(*dst.value()))
-                                + Wrapping(self.get_val_from_src(&src)))
-                            .0,
-                        );
+                            self.read_reg(&dst)?
+                                & self.get_val_from_src(&src)?,
+                        )?;
                     }
-                    Operator::Sub(dst, src) => {
+                    Operator::Or(dst, src) => {
                         self.set_reg(
                             &dst,
-                            (Wrapping(self.get_reg(*dst.value()))
-                                - Wrapping(self.get_val_from_src(&src)))
-                            .0,
-                        );
+                            self.read_reg(&dst)?
+                                | self.get_val_from_src(&src)?,
+                        )?;
                     }
-                    Operator::Mul(dst, src) => {
+                    Operator::Xor(dst, src) => {
                         self.set_reg(
                             &dst,
-                            (Wrapping(self.get_reg(*dst.value()))
-                                * Wrapping(self.get_val_from_src(&src)))
-                            .0,
-                        );
+                            self.read_reg(&dst)?
+                                ^ self.get_val_from_src(&src)?,
+                        )?;
                     }
-                    Operator::Div(dst, src) => {
-                        let divisor = self.get_val_from_src(&src);
-                        let dividend = self.get_reg(*dst.value());
-                        if divisor != 0 {
-                            // This does flooring division
-                            self.set_reg(&dst, dividend / divisor);
-                        } else {
-                            return Err((RuntimeError::DivideByZero, *span));
-                        }
+                    Operator::Shl(dst, src) => {
+                        // Bits shifted off the top are always thrown away,
+                        // regardless of the overflow mode
+                        let width = self.hardware_spec.value_width;
+                        let shift = self.get_shift_amount(&src)?;
+                        let value = i64::from(self.read_reg(&dst)?);
+                        self.set_reg(&dst, width.wrap(value << shift))?;
+                    }
+                    Operator::Shr(dst, src) => {
+                        let shift = self.get_shift_amount(&src)?;
+                        self.set_reg(&dst, self.read_reg(&dst)? >> shift)?;
                     }
                     Operator::Cmp(dst, src_1, src_2) => {
-                        let val_1 = self.get_val_from_src(&src_1);
-                        let val_2 = self.get_val_from_src(&src_2);
+                        let val_1 = self.get_val_from_src(&src_1)?;
+                        let val_2 = self.get_val_from_src(&src_2)?;
                         let cmp = match val_1.cmp(&val_2) {
                             Ordering::Less => -1,
                             Ordering::Equal => 0,
                             Ordering::Greater => 1,
                         };
-                        self.set_reg(&dst, cmp);
+                        self.set_reg(&dst, cmp)?;
+                    }
+                    Operator::Rand(dst) => {
+                        // Reinterpreting the bits is fine, since the value
+                        // just gets wrapped into the value width anyway
+                        let value = self.rng.next_u64() as i64;
+                        let width = self.hardware_spec.value_width;
+                        self.set_reg(&dst, width.wrap(value))?;
                     }
                     Operator::Push(src, stack_ref) => {
                         self.push_stack(
                             &stack_ref,
-                            self.get_val_from_src(&src),
+                            self.get_val_from_src(&src)?,
                         )?;
                     }
                     Operator::Pop(stack_ref, dst) => {
                         let popped = self.pop_stack(&stack_ref)?;
-                        self.set_reg(&dst, popped);
+                        self.set_reg(&dst, popped)?;
+                    }
+                    Operator::Peek(stack_ref, dst) => {
+                        let top = self.get_stack_top(&stack_ref, 1)?[0];
+                        self.set_reg(&dst, top)?;
+                    }
+                    Operator::Dup(stack_ref) => {
+                        let top = self.get_stack_top(&stack_ref, 1)?[0];
+                        self.push_stack(&stack_ref, top)?;
+                    }
+                    Operator::Swap(stack_ref) => {
+                        self.get_stack_top(&stack_ref, 2)?.swap(0, 1);
+                    }
+                    Operator::Enq(src, queue_ref) => {
+                        self.enqueue(&queue_ref, self.get_val_from_src(&src)?)?;
+                    }
+                    Operator::Deq(queue_ref, dst) => {
+                        let dequeued = self.dequeue(&queue_ref)?;
+                        self.set_reg(&dst, dequeued)?;
+                    }
+                    Operator::Load(dst, addr) => {
+                        let index = self.get_memory_index(
+                            self.get_val_from_src(&addr)?,
+                            *span,
+                        )?;
+                        self.set_reg(&dst, self.memory[index])?;
+                    }
+                    Operator::Store(src, addr) => {
+                        let index = self.get_memory_index(
+                            self.get_val_from_src(&addr)?,
+                            *span,
+                        )?;
+                        self.memory[index] = self.get_val_from_src(&src)?;
                     }
                 }
                 1
@@ -293,10 +604,34 @@ impl Machine {
             Instruction::Jump(Node(jump, _), offset) => {
                 let should_jump = match jump {
                     Jump::Jmp => true,
-                    Jump::Jez(src) => self.get_val_from_src(&src) == 0,
-                    Jump::Jnz(src) => self.get_val_from_src(&src) != 0,
-                    Jump::Jlz(src) => self.get_val_from_src(&src) < 0,
-                    Jump::Jgz(src) => self.get_val_from_src(&src) > 0,
+                    Jump::Jez(src) => self.get_val_from_src(&src)? == 0,
+                    Jump::Jnz(src) => self.get_val_from_src(&src)? != 0,
+                    Jump::Jlz(src) => self.get_val_from_src(&src)? < 0,
+                    Jump::Jgz(src) => self.get_val_from_src(&src)? > 0,
+                    Jump::Jeq(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            == self.get_val_from_src(&src_2)?
+                    }
+                    Jump::Jne(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            != self.get_val_from_src(&src_2)?
+                    }
+                    Jump::Jlt(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            < self.get_val_from_src(&src_2)?
+                    }
+                    Jump::Jgt(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            > self.get_val_from_src(&src_2)?
+                    }
+                    Jump::Jle(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            <= self.get_val_from_src(&src_2)?
+                    }
+                    Jump::Jge(src_1, src_2) => {
+                        self.get_val_from_src(&src_1)?
+                            >= self.get_val_from_src(&src_2)?
+                    }
                 };
                 if should_jump {
                     *offset
@@ -304,6 +639,51 @@ impl Machine {
                     1
                 }
             }
+
+            Instruction::ComputedJump(target) => {
+                let target = self.get_val_from_src(&target)?;
+                match usize::try_from(target) {
+                    Ok(index) if index < self.program.instructions.len() => {
+                        index as isize - self.program_counter as isize
+                    }
+                    _ => {
+                        return Err((
+                            RuntimeError::InvalidJumpTarget { target },
+                            *instr_node.metadata(),
+                        ))
+                    }
+                }
+            }
+
+            // Subroutines
+            Instruction::Call(offset) => {
+                if self.call_stack.len() >= self.hardware_spec.max_call_depth {
+                    return Err((
+                        RuntimeError::CallStackOverflow,
+                        *instr_node.metadata(),
+                    ));
+                }
+                // Resume at the instruction after this one upon return
+                self.call_stack.push(self.program_counter + 1);
+                *offset
+            }
+            Instruction::Return => match self.call_stack.pop() {
+                Some(return_addr) => {
+                    return_addr as isize - self.program_counter as isize
+                }
+                None => {
+                    return Err((
+                        RuntimeError::EmptyCallStack,
+                        *instr_node.metadata(),
+                    ))
+                }
+            },
+            // Skip to the end of the program, so that halting is
+            // indistinguishable from running out of instructions
+            Instruction::Halt => {
+                self.program.instructions.len() as isize
+                    - self.program_counter as isize
+            }
         };
 
         // Advance the pc by the specified number of instructions (for jumps)
@@ -333,9 +713,9 @@ impl Machine {
                     iter::once(SourceErrorWrapper::new(
                         error,
                         span,
-                        &self.source,
+                        &self.sources,
                     )),
-                    self.source.clone(),
+                    self.sources.clone(),
                 ));
                 Err(self.error.as_ref().unwrap())
             }
@@ -376,7 +756,10 @@ impl Machine {
         self.hardware_spec
             .all_register_refs()
             .into_iter()
-            .map(|reg_ref| (reg_ref, self.get_reg(reg_ref)))
+            .map(|reg_ref| {
+                let value = self.get_reg(&reg_ref);
+                (reg_ref, value)
+            })
             .collect()
     }
 
@@ -389,6 +772,28 @@ impl Machine {
             .collect()
     }
 
+    /// Get all queues and their current values. The front of each queue is
+    /// the first element.
+    pub fn queues(&self) -> HashMap<QueueRef, &[LangValue]> {
+        self.hardware_spec
+            .all_queue_refs()
+            .into_iter()
+            .map(|queue_ref| (queue_ref, self.queues[queue_ref.0].as_slice()))
+            .collect()
+    }
+
+    /// Get the current contents of memory. The index of each value is its
+    /// address.
+    pub fn memory(&self) -> &[LangValue] {
+        self.memory.as_slice()
+    }
+
+    /// Get the return addresses of all in-progress subroutine calls. The last
+    /// element is the innermost call.
+    pub fn call_stack(&self) -> &[usize] {
+        self.call_stack.as_slice()
+    }
+
     /// Get the runtime error that halted execution of this machine. If no error
     /// has occurred, return `None`.
     pub fn error(&self) -> Option<&WithSource<RuntimeError>> {
@@ -415,8 +820,18 @@ impl Machine {
         self.cycle_count
     }
 
+    /// Get the number of instructions in the compiled program. Unlike
+    /// [Self::cycle_count], this is fixed for the lifetime of the machine.
+    #[cfg_attr(
+        feature = "wasm",
+        wasm_bindgen(getter, js_name = "instructionCount")
+    )]
+    pub fn instruction_count(&self) -> usize {
+        self.program.instructions.len()
+    }
+
     /// Checks if this machine has finished executing. This could be by normal
-    /// completion or by runtime error.
+    /// completion (including `HALT`) or by runtime error.
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter, js_name = "terminated"))]
     pub fn terminated(&self) -> bool {
         // Check for normal complete
@@ -426,7 +841,8 @@ impl Machine {
     }
 
     /// Checks if this machine has completed successfully. The criteria are:
-    /// 1. Program is terminated (all instructions have been executed)
+    /// 1. Program is terminated (all instructions have been executed, or
+    ///    `HALT` was executed)
     /// 2. Program terminated normally (a runtime error did NOT occur)
     /// 3. Input buffer has been exhausted (all input has been consumed)
     /// 4. Output buffer matches the expected output, as defined by the
@@ -446,13 +862,20 @@ impl Machine {
         self.input.clone()
     }
 
-    /// A wrapper for [Self::input], to be called from wasm.
+    /// A wrapper for [Self::output], to be called from wasm.
     #[cfg(feature = "wasm")]
     #[wasm_bindgen(getter, js_name = "output")]
     pub fn wasm_output(&self) -> Vec<LangValue> {
         self.output.clone()
     }
 
+    /// A wrapper for [Self::memory], to be called from wasm.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(getter, js_name = "memory")]
+    pub fn wasm_memory(&self) -> Vec<LangValue> {
+        self.memory.clone()
+    }
+
     /// A wrapper for [Self::registers], to be called from wasm. We can't send
     /// maps through wasm, so this returns a [JsValue] which is an object
     /// mapping register names (strings) to their values (`LangValue`).
@@ -489,6 +912,26 @@ impl Machine {
             .unchecked_into()
     }
 
+    /// A wrapper for [Self::queues], to be called from wasm. We can't send
+    /// maps through wasm, so this returns a [JsValue] which is an object
+    /// mapping queue names (strings) to their values (`Vec<LangValue>`).
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(getter, js_name = "queues")]
+    pub fn wasm_queues(&self) -> LangValueArrayMap {
+        // Convert the keys of the queues map to strings
+        let queues_by_name: HashMap<String, &[LangValue]> = self
+            .queues()
+            .into_iter()
+            .map(|(queue_ref, queue_value)| {
+                (queue_ref.to_string(), queue_value)
+            })
+            .collect();
+        // Convert the hashmap to a js object. Be careful here!
+        JsValue::from_serde(&queues_by_name)
+            .unwrap()
+            .unchecked_into()
+    }
+
     /// A wrapper for [Self::error], to be called from wasm. We can't send
     /// maps through wasm, so this returns a simplified error as a
     /// [SourceElement].
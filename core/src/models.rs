@@ -4,23 +4,367 @@
 
 #[cfg(feature = "wasm")]
 use crate::ast::wasm::StringArray;
-use crate::ast::{LangValue, RegisterRef, StackRef};
+use crate::{
+    ast::{LangValue, QueueRef, RegisterRef, StackId, StackRef},
+    consts::{MAX_CYCLE_COUNT, MAX_MEMORY_SIZE},
+    error::{HardwareSpecError, ProgramSpecError},
+};
 use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::{prelude::*, JsCast};
 
+/// The number of bits in each value that a program works with. Every value is
+/// stored as a [LangValue], but is always kept within the range of the width.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum ValueWidth {
+    Bits8 = 8,
+    Bits16 = 16,
+    Bits32 = 32,
+}
+
+impl ValueWidth {
+    /// The number of bits in a value of this width.
+    pub fn bits(self) -> u32 {
+        self as u32
+    }
+
+    /// The smallest value that fits in this width.
+    pub fn min_value(self) -> LangValue {
+        -self.max_value() - 1
+    }
+
+    /// The largest value that fits in this width.
+    pub fn max_value(self) -> LangValue {
+        // Subtract before shifting the last bit in, so that 32 bits can't
+        // overflow
+        ((1 << (self.bits() - 2)) - 1) * 2 + 1
+    }
+
+    /// Checks if the value fits in this width.
+    pub fn contains(self, value: i64) -> bool {
+        i64::from(self.min_value()) <= value
+            && value <= i64::from(self.max_value())
+    }
+
+    /// Truncates the value to this width, i.e. throws away any higher bits.
+    pub fn wrap(self, value: i64) -> LangValue {
+        let shift = 64 - self.bits();
+        // Shift the excess bits off the top, then shift back down to recover
+        // the sign. The result always fits, so the cast is lossless.
+        ((value << shift) >> shift) as LangValue
+    }
+
+    /// Clamps the value to the range of this width.
+    pub fn saturate(self, value: i64) -> LangValue {
+        // The result always fits, so the cast is lossless
+        value
+            .max(self.min_value().into())
+            .min(self.max_value().into()) as LangValue
+    }
+}
+
+impl TryFrom<u8> for ValueWidth {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        match bits {
+            8 => Ok(Self::Bits8),
+            16 => Ok(Self::Bits16),
+            32 => Ok(Self::Bits32),
+            _ => Err(format!(
+                "invalid value width {}, expected 8, 16 or 32",
+                bits
+            )),
+        }
+    }
+}
+
+impl From<ValueWidth> for u8 {
+    fn from(width: ValueWidth) -> Self {
+        width as u8
+    }
+}
+
+impl Default for ValueWidth {
+    fn default() -> Self {
+        Self::Bits16
+    }
+}
+
+/// What happens when the result of an arithmetic operation doesn't fit in the
+/// hardware's [ValueWidth].
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowMode {
+    /// The result wraps around, e.g. `MAX + 1 = MIN`
+    Wrapping,
+    /// The result is clamped, e.g. `MAX + 1 = MAX`
+    Saturating,
+    /// A runtime error is triggered
+    Trapping,
+}
+
+impl Default for OverflowMode {
+    fn default() -> Self {
+        Self::Wrapping
+    }
+}
+
+/// The different kinds of instructions, without any of their arguments. This
+/// is used to restrict which instructions a [HardwareSpec] allows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum InstructionKind {
+    Read,
+    Write,
+    Set,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Neg,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Cmp,
+    Rand,
+    Push,
+    Pop,
+    Peek,
+    Dup,
+    Swap,
+    Enq,
+    Deq,
+    Load,
+    Store,
+    Jmp,
+    Jez,
+    Jnz,
+    Jlz,
+    Jgz,
+    Jeq,
+    Jne,
+    Jlt,
+    Jgt,
+    Jle,
+    Jge,
+    Call,
+    Ret,
+    Halt,
+}
+
+impl InstructionKind {
+    /// Every instruction kind, in the order they're declared.
+    pub const ALL: &'static [Self] = &[
+        Self::Read,
+        Self::Write,
+        Self::Set,
+        Self::Add,
+        Self::Sub,
+        Self::Mul,
+        Self::Div,
+        Self::Mod,
+        Self::Neg,
+        Self::And,
+        Self::Or,
+        Self::Xor,
+        Self::Shl,
+        Self::Shr,
+        Self::Cmp,
+        Self::Rand,
+        Self::Push,
+        Self::Pop,
+        Self::Peek,
+        Self::Dup,
+        Self::Swap,
+        Self::Enq,
+        Self::Deq,
+        Self::Load,
+        Self::Store,
+        Self::Jmp,
+        Self::Jez,
+        Self::Jnz,
+        Self::Jlz,
+        Self::Jgz,
+        Self::Jeq,
+        Self::Jne,
+        Self::Jlt,
+        Self::Jgt,
+        Self::Jle,
+        Self::Jge,
+        Self::Call,
+        Self::Ret,
+        Self::Halt,
+    ];
+
+    /// Get the name of this instruction, as it appears in source code.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Read => "READ",
+            Self::Write => "WRITE",
+            Self::Set => "SET",
+            Self::Add => "ADD",
+            Self::Sub => "SUB",
+            Self::Mul => "MUL",
+            Self::Div => "DIV",
+            Self::Mod => "MOD",
+            Self::Neg => "NEG",
+            Self::And => "AND",
+            Self::Or => "OR",
+            Self::Xor => "XOR",
+            Self::Shl => "SHL",
+            Self::Shr => "SHR",
+            Self::Cmp => "CMP",
+            Self::Rand => "RAND",
+            Self::Push => "PUSH",
+            Self::Pop => "POP",
+            Self::Peek => "PEEK",
+            Self::Dup => "DUP",
+            Self::Swap => "SWAP",
+            Self::Enq => "ENQ",
+            Self::Deq => "DEQ",
+            Self::Load => "LOAD",
+            Self::Store => "STORE",
+            Self::Jmp => "JMP",
+            Self::Jez => "JEZ",
+            Self::Jnz => "JNZ",
+            Self::Jlz => "JLZ",
+            Self::Jgz => "JGZ",
+            Self::Jeq => "JEQ",
+            Self::Jne => "JNE",
+            Self::Jlt => "JLT",
+            Self::Jgt => "JGT",
+            Self::Jle => "JLE",
+            Self::Jge => "JGE",
+            Self::Call => "CALL",
+            Self::Ret => "RET",
+            Self::Halt => "HALT",
+        }
+    }
+
+    /// Get the instruction kind with the given name. The name is
+    /// case-insensitive, just like in source code. Returns `None` if there is
+    /// no instruction with that name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Is this one of the two-operand jumps (`JEQ`, `JNE`, etc.)?
+    pub fn is_compare_jump(self) -> bool {
+        match self {
+            Self::Jeq
+            | Self::Jne
+            | Self::Jlt
+            | Self::Jgt
+            | Self::Jle
+            | Self::Jge => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for InstructionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 /// The "hardware" that a program can execute on. This defines computing
 /// constraints. This is needed both at compile time and runtime.
+///
+/// This type isn't `Copy`, because some of its fields (e.g.
+/// [Self::stack_lengths]) are lists. Use `clone` to duplicate it.
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct HardwareSpec {
     // TODO make these readonly and camel case in wasm
     /// Number of registers available
     pub num_registers: usize,
     /// Maximum number of stacks permitted
     pub num_stacks: usize,
-    /// Maximum size of each stack
+    /// Maximum size of each stack, unless overridden by [Self::stack_lengths]
     pub max_stack_length: usize,
+    /// Maximum size of each individual stack, indexed by stack ID. Any stack
+    /// that isn't listed here uses [Self::max_stack_length]. Can't be longer
+    /// than [Self::num_stacks]. Defaults to empty, i.e. all stacks share the
+    /// same capacity.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    #[serde(default)]
+    pub stack_lengths: Vec<usize>,
+    /// Number of cells of random-access memory, which is accessed with `LOAD`
+    /// and `STORE`. Can be at most [MAX_MEMORY_SIZE]. Defaults to zero, i.e.
+    /// no memory.
+    #[serde(default)]
+    pub memory_size: usize,
+    /// Number of queues available. Defaults to zero.
+    #[serde(default)]
+    pub num_queues: usize,
+    /// Maximum size of each queue. Defaults to zero.
+    #[serde(default)]
+    pub max_queue_length: usize,
+    /// Maximum depth of nested subroutine calls, i.e. the capacity of the
+    /// call stack. Defaults to zero, which means `CALL` is unusable.
+    #[serde(default)]
+    pub max_call_depth: usize,
+    /// Whether the two-operand jumps (`JEQ`, `JNE`, `JLT`, `JGT`, `JLE`,
+    /// `JGE`) can be used. Defaults to true.
+    #[serde(default = "default_compare_jumps_enabled")]
+    pub compare_jumps_enabled: bool,
+    /// Whether structured control flow blocks (`IF`/`ELSE`/`WHILE`) can be
+    /// used. If disabled, all control flow has to be written with labels and
+    /// jumps. Defaults to true.
+    #[serde(default = "default_blocks_enabled")]
+    pub blocks_enabled: bool,
+    /// Maximum number of cycles that a program can run for before being
+    /// killed. Defaults to [MAX_CYCLE_COUNT].
+    #[serde(default = "default_max_cycles")]
+    pub max_cycles: usize,
+    /// Maximum number of instructions that a compiled program can contain.
+    /// Defaults to `None`, i.e. no limit.
+    #[serde(default)]
+    pub max_instructions: Option<usize>,
+    /// Number of bits in each value. Defaults to 16.
+    #[serde(default)]
+    pub value_width: ValueWidth,
+    /// Behavior of arithmetic that overflows the value width. Defaults to
+    /// wrapping.
+    #[serde(default)]
+    pub overflow_mode: OverflowMode,
+    /// If given, programs can only use these instructions. Defaults to `None`,
+    /// i.e. every instruction is allowed.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    #[serde(default)]
+    pub allowed_instructions: Option<Vec<InstructionKind>>,
+    /// Instructions that programs can't use, on top of any restriction from
+    /// [Self::allowed_instructions]. Defaults to empty.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    #[serde(default)]
+    pub disallowed_instructions: Vec<InstructionKind>,
+}
+
+fn default_compare_jumps_enabled() -> bool {
+    true
+}
+
+fn default_blocks_enabled() -> bool {
+    true
+}
+
+fn default_max_cycles() -> usize {
+    MAX_CYCLE_COUNT
 }
 
 // Functions that DON'T get exported to wasm
@@ -33,6 +377,9 @@ impl HardwareSpec {
         // RSx registers
         register_refs
             .extend((0..self.num_stacks).map(RegisterRef::StackLength));
+        // RQx registers
+        register_refs
+            .extend((0..self.num_queues).map(RegisterRef::QueueLength));
         // RXx registers
         register_refs.extend((0..self.num_registers).map(RegisterRef::User));
         register_refs
@@ -42,6 +389,40 @@ impl HardwareSpec {
     pub fn all_stack_refs(&self) -> Vec<StackRef> {
         (0..self.num_stacks).map(StackRef).collect()
     }
+
+    /// Get a list of all [QueueRef]s that exist for this hardware.
+    pub fn all_queue_refs(&self) -> Vec<QueueRef> {
+        (0..self.num_queues).map(QueueRef).collect()
+    }
+
+    /// Checks that this hardware's settings are within bounds, so that a
+    /// machine can be allocated for it.
+    pub fn validate(&self) -> Result<(), HardwareSpecError> {
+        if self.memory_size > MAX_MEMORY_SIZE {
+            return Err(HardwareSpecError::MemoryTooLarge {
+                memory_size: self.memory_size,
+            });
+        }
+        if self.stack_lengths.len() > self.num_stacks {
+            return Err(HardwareSpecError::TooManyStackLengths {
+                num_stack_lengths: self.stack_lengths.len(),
+                num_stacks: self.num_stacks,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks if programs on this hardware are allowed to use the given kind
+    /// of instruction.
+    pub fn is_instruction_allowed(&self, kind: InstructionKind) -> bool {
+        let in_allow_list = match &self.allowed_instructions {
+            Some(allowed) => allowed.contains(&kind),
+            None => true,
+        };
+        (self.compare_jumps_enabled || !kind.is_compare_jump())
+            && in_allow_list
+            && !self.disallowed_instructions.contains(&kind)
+    }
 }
 
 // Functions that get exported to wasm
@@ -57,9 +438,21 @@ impl HardwareSpec {
             num_registers,
             num_stacks,
             max_stack_length,
+            ..Default::default()
         }
     }
 
+    /// Get the maximum size of the given stack. This is the stack's own
+    /// capacity from [Self::stack_lengths] if it has one, otherwise the shared
+    /// [Self::max_stack_length].
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = "stackCapacity"))]
+    pub fn stack_capacity(&self, stack_id: StackId) -> usize {
+        self.stack_lengths
+            .get(stack_id)
+            .copied()
+            .unwrap_or(self.max_stack_length)
+    }
+
     /// A wrapper around [Self::all_register_refs] to be called from wasm.
     /// The elements of the returned vector are strings representing the name
     /// of each register.
@@ -95,6 +488,80 @@ impl HardwareSpec {
         // Convert the vec to a js array. Be careful here!
         JsValue::from_serde(&stack_names).unwrap().unchecked_into()
     }
+
+    /// A wrapper around [Self::all_queue_refs] to be called from wasm.
+    /// The elements of the returned vector are strings representing the name
+    /// of each queue.
+    ///
+    /// TODO change to `Vec<String>` after
+    /// https://github.com/rustwasm/wasm-bindgen/issues/168
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(getter, js_name = "queues")]
+    pub fn wasm_queues(&self) -> StringArray {
+        let queue_names: Vec<String> = self
+            .all_queue_refs()
+            .into_iter()
+            .map(|queue_ref| queue_ref.to_string())
+            .collect();
+        // Convert the vec to a js array. Be careful here!
+        JsValue::from_serde(&queue_names).unwrap().unchecked_into()
+    }
+
+    /// A setter for [Self::stack_lengths], to be called from wasm.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = "setStackLengths")]
+    pub fn wasm_set_stack_lengths(&mut self, stack_lengths: Vec<usize>) {
+        self.stack_lengths = stack_lengths;
+    }
+
+    /// A setter for [Self::allowed_instructions], to be called from wasm. The
+    /// value should be an array of instruction names, or `null` to allow every
+    /// instruction. Names are case-insensitive.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = "setAllowedInstructions")]
+    pub fn wasm_set_allowed_instructions(
+        &mut self,
+        names: JsValue,
+    ) -> Result<(), JsValue> {
+        let names: Option<Vec<String>> = names
+            .into_serde()
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.allowed_instructions =
+            names.map(instruction_kinds_from_names).transpose()?;
+        Ok(())
+    }
+
+    /// A setter for [Self::disallowed_instructions], to be called from wasm.
+    /// The value should be an array of instruction names. Names are
+    /// case-insensitive.
+    #[cfg(feature = "wasm")]
+    #[wasm_bindgen(js_name = "setDisallowedInstructions")]
+    pub fn wasm_set_disallowed_instructions(
+        &mut self,
+        names: JsValue,
+    ) -> Result<(), JsValue> {
+        let names: Vec<String> = names
+            .into_serde()
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.disallowed_instructions = instruction_kinds_from_names(names)?;
+        Ok(())
+    }
+}
+
+/// Convert instruction names from JS into [InstructionKind]s, using the same
+/// case-insensitive lookup as [InstructionKind::from_name].
+#[cfg(feature = "wasm")]
+fn instruction_kinds_from_names(
+    names: Vec<String>,
+) -> Result<Vec<InstructionKind>, JsValue> {
+    names
+        .iter()
+        .map(|name| {
+            InstructionKind::from_name(name).ok_or_else(|| {
+                JsValue::from_str(&format!("Unknown instruction `{}`", name))
+            })
+        })
+        .collect()
 }
 
 // Useful for tests and prototyping
@@ -104,6 +571,19 @@ impl Default for HardwareSpec {
             num_registers: 1,
             num_stacks: 0,
             max_stack_length: 0,
+            stack_lengths: Vec::new(),
+            memory_size: 0,
+            num_queues: 0,
+            max_queue_length: 0,
+            max_call_depth: 0,
+            compare_jumps_enabled: default_compare_jumps_enabled(),
+            blocks_enabled: default_blocks_enabled(),
+            max_cycles: default_max_cycles(),
+            max_instructions: None,
+            value_width: ValueWidth::default(),
+            overflow_mode: OverflowMode::default(),
+            allowed_instructions: None,
+            disallowed_instructions: Vec::new(),
         }
     }
 }
@@ -120,6 +600,10 @@ pub struct ProgramSpec {
     /// The correct value to be left in the output when the program exits. The
     /// first element will be the first one pushed, and so on.
     expected_output: Vec<LangValue>,
+    /// Seed for the random number generator used by `RAND`. Every run of the
+    /// same program spec uses the same seed, so runs are reproducible.
+    #[serde(default)]
+    seed: u32,
 }
 
 // Functions that DON'T get exported to wasm
@@ -135,6 +619,28 @@ impl ProgramSpec {
     pub fn expected_output(&self) -> &[LangValue] {
         &self.expected_output
     }
+
+    /// Checks that this program spec can run on the given hardware, i.e. that
+    /// every value in the input and expected output fits in the hardware's
+    /// value width.
+    pub fn validate(
+        &self,
+        hardware_spec: &HardwareSpec,
+    ) -> Result<(), ProgramSpecError> {
+        let width = hardware_spec.value_width;
+        match self
+            .input
+            .iter()
+            .chain(&self.expected_output)
+            .find(|value| !width.contains(i64::from(**value)))
+        {
+            Some(value) => Err(ProgramSpecError::ValueOutOfRange {
+                value: *value,
+                width,
+            }),
+            None => Ok(()),
+        }
+    }
 }
 
 // Functions that get exported to wasm
@@ -145,9 +651,22 @@ impl ProgramSpec {
         ProgramSpec {
             input,
             expected_output,
+            seed: 0,
         }
     }
 
+    /// Get the seed for the random number generator used by `RAND`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Set the seed for the random number generator used by `RAND`.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(setter))]
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
     /// Version of [Self::input] to be called from wasm
     #[cfg(feature = "wasm")]
     #[wasm_bindgen(getter, js_name = "input")]
@@ -172,6 +691,7 @@ impl Default for ProgramSpec {
         Self {
             input: vec![],
             expected_output: vec![],
+            seed: 0,
         }
     }
 }
@@ -187,6 +707,7 @@ mod tests {
                 num_registers: 0,
                 num_stacks: 0,
                 max_stack_length: 0,
+                ..Default::default()
             }
             .all_register_refs(),
             vec![RegisterRef::InputLength],
@@ -197,12 +718,15 @@ mod tests {
                 num_registers: 3,
                 num_stacks: 2,
                 max_stack_length: 0,
+                num_queues: 1,
+                ..Default::default()
             }
             .all_register_refs(),
             vec![
                 RegisterRef::InputLength,
                 RegisterRef::StackLength(0),
                 RegisterRef::StackLength(1),
+                RegisterRef::QueueLength(0),
                 RegisterRef::User(0),
                 RegisterRef::User(1),
                 RegisterRef::User(2),
@@ -210,6 +734,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_value_width() {
+        assert_eq!(ValueWidth::Bits8.min_value(), -128);
+        assert_eq!(ValueWidth::Bits8.max_value(), 127);
+        assert_eq!(
+            ValueWidth::Bits16.min_value(),
+            LangValue::from(i16::min_value())
+        );
+        assert_eq!(
+            ValueWidth::Bits16.max_value(),
+            LangValue::from(i16::max_value())
+        );
+        assert_eq!(ValueWidth::Bits32.min_value(), i32::min_value());
+        assert_eq!(ValueWidth::Bits32.max_value(), i32::max_value());
+
+        assert!(ValueWidth::Bits8.contains(-128));
+        assert!(!ValueWidth::Bits8.contains(128));
+        assert_eq!(ValueWidth::Bits8.wrap(128), -128);
+        assert_eq!(ValueWidth::Bits8.wrap(-129), 127);
+        assert_eq!(ValueWidth::Bits8.wrap(256 + 5), 5);
+        assert_eq!(ValueWidth::Bits32.wrap(1 << 31), i32::min_value());
+        assert_eq!(ValueWidth::Bits8.saturate(1000), 127);
+        assert_eq!(ValueWidth::Bits8.saturate(-1000), -128);
+        assert_eq!(ValueWidth::Bits8.saturate(-5), -5);
+    }
+
+    #[test]
+    fn test_program_spec_validate() {
+        let hardware_spec = HardwareSpec {
+            value_width: ValueWidth::Bits8,
+            ..Default::default()
+        };
+        assert!(ProgramSpec::new(vec![-128, 127], vec![0])
+            .validate(&hardware_spec)
+            .is_ok());
+        assert!(ProgramSpec::new(vec![], vec![128])
+            .validate(&hardware_spec)
+            .is_err());
+        assert!(ProgramSpec::new(vec![-129], vec![])
+            .validate(&hardware_spec)
+            .is_err());
+    }
+
+    #[test]
+    fn test_hardware_spec_validate() {
+        assert!(HardwareSpec::default().validate().is_ok());
+        assert!(HardwareSpec {
+            memory_size: MAX_MEMORY_SIZE,
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(HardwareSpec {
+            memory_size: MAX_MEMORY_SIZE + 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(HardwareSpec {
+            num_stacks: 2,
+            stack_lengths: vec![4, 8],
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(HardwareSpec {
+            num_stacks: 1,
+            stack_lengths: vec![4, 8],
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
     #[test]
     fn test_all_stack_refs() {
         assert_eq!(
@@ -217,6 +815,7 @@ mod tests {
                 num_registers: 0,
                 num_stacks: 0,
                 max_stack_length: 0,
+                ..Default::default()
             }
             .all_stack_refs(),
             vec![],
@@ -227,9 +826,85 @@ mod tests {
                 num_registers: 3,
                 num_stacks: 2,
                 max_stack_length: 0,
+                ..Default::default()
             }
             .all_stack_refs(),
             vec![StackRef(0), StackRef(1),],
         );
     }
+
+    #[test]
+    fn test_all_queue_refs() {
+        assert_eq!(HardwareSpec::default().all_queue_refs(), vec![]);
+        assert_eq!(
+            HardwareSpec {
+                num_queues: 2,
+                ..Default::default()
+            }
+            .all_queue_refs(),
+            vec![QueueRef(0), QueueRef(1)],
+        );
+    }
+
+    #[test]
+    fn test_stack_capacity() {
+        let hardware_spec = HardwareSpec {
+            num_stacks: 3,
+            max_stack_length: 5,
+            stack_lengths: vec![2, 10],
+            ..Default::default()
+        };
+        assert_eq!(hardware_spec.stack_capacity(0), 2);
+        assert_eq!(hardware_spec.stack_capacity(1), 10);
+        assert_eq!(hardware_spec.stack_capacity(2), 5);
+    }
+
+    #[test]
+    fn test_deserialize_hardware_spec() {
+        // Specs from before per-stack capacities were added still work, and
+        // every stack gets the shared capacity
+        let hardware_spec: HardwareSpec = serde_json::from_str(
+            r#"{"num_registers": 1, "num_stacks": 2, "max_stack_length": 4}"#,
+        )
+        .unwrap();
+        assert_eq!(hardware_spec.stack_lengths, Vec::<usize>::new());
+        assert_eq!(hardware_spec.stack_capacity(0), 4);
+        assert_eq!(hardware_spec.stack_capacity(1), 4);
+    }
+
+    #[test]
+    fn test_instruction_kind_from_name() {
+        assert_eq!(
+            InstructionKind::from_name("READ"),
+            Some(InstructionKind::Read)
+        );
+        assert_eq!(
+            InstructionKind::from_name("jEq"),
+            Some(InstructionKind::Jeq)
+        );
+        assert_eq!(InstructionKind::from_name("LABEL"), None);
+        for kind in InstructionKind::ALL {
+            assert_eq!(InstructionKind::from_name(kind.name()), Some(*kind));
+        }
+    }
+
+    #[test]
+    fn test_is_instruction_allowed() {
+        let hardware_spec = HardwareSpec {
+            compare_jumps_enabled: false,
+            allowed_instructions: Some(vec![
+                InstructionKind::Add,
+                InstructionKind::Sub,
+                InstructionKind::Jeq,
+            ]),
+            disallowed_instructions: vec![InstructionKind::Sub],
+            ..Default::default()
+        };
+        assert!(hardware_spec.is_instruction_allowed(InstructionKind::Add));
+        assert!(!hardware_spec.is_instruction_allowed(InstructionKind::Sub));
+        assert!(!hardware_spec.is_instruction_allowed(InstructionKind::Jeq));
+        assert!(!hardware_spec.is_instruction_allowed(InstructionKind::Mul));
+        assert!(HardwareSpec::default()
+            .is_instruction_allowed(InstructionKind::Mul));
+    }
 }
@@ -1,22 +1,32 @@
 use crate::{
     ast::{
-        source::{LabelDecl, Program, Statement},
-        Jump, Label, LangValue, Node, Operator, RegisterRef, SpanNode, StackId,
-        StackRef, UserRegisterId, ValueSource,
+        source::{
+            BinaryOp, Block, Directive, Expr, LabelDecl, Program,
+            RegisterOperand, Statement, ValueOperand,
+        },
+        Jump, Label, LangValue, Node, Operator, QueueId, QueueRef, RegisterRef,
+        SpanNode, StackId, StackOperand, StackRef, Symbol, UserRegisterId,
     },
     consts::{
-        INPUT_LENGTH_REGISTER_REF, NULL_REGISTER_REF,
-        STACK_LENGTH_REGISTER_REF_TAG, STACK_REF_TAG, USER_REGISTER_REF_TAG,
+        CYCLE_COUNT_REGISTER_REF, INPUT_LENGTH_REGISTER_REF, NULL_REGISTER_REF,
+        PROGRAM_COUNTER_REGISTER_REF, QUEUE_LENGTH_REGISTER_REF_TAG,
+        QUEUE_REF_TAG, STACK_LENGTH_REGISTER_REF_TAG, STACK_REF_TAG,
+        USER_REGISTER_REF_TAG,
     },
-    error::{CompileError, SourceErrorWrapper, WithSource},
-    util::{RawSpan, Span},
+    error::{CompileError, SourceErrorWrapper},
+    models::ValueWidth,
+    util::{FileId, ParseContext, RawSpan, SourceFiles, Span},
     Compiler,
 };
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag, tag_no_case, take_while1},
-    character::complete::{char, digit1, line_ending, space0, space1},
-    combinator::{all_consuming, cut, map, map_res, opt, peek, recognize},
+    bytes::complete::{is_a, is_not, tag, tag_no_case, take_while1},
+    character::complete::{
+        char, digit1, hex_digit1, line_ending, none_of, space0, space1,
+    },
+    combinator::{
+        all_consuming, cut, map, map_res, not, opt, peek, recognize, verify,
+    },
     error::{
         context, make_error, ErrorKind, ParseError, VerboseError,
         VerboseErrorKind,
@@ -25,8 +35,10 @@ use nom::{
     sequence::{delimited, preceded, terminated, tuple},
     IResult, Offset, Slice,
 };
+use std::cell::RefCell;
 
-type ParseResult<'a, T> = IResult<RawSpan<'a>, T, VerboseError<RawSpan<'a>>>;
+pub(crate) type ParseResult<'a, T> =
+    IResult<RawSpan<'a>, T, VerboseError<RawSpan<'a>>>;
 
 /// A trait for parsing into AST nodes. Any AST node that can be parsed from the
 /// source should implement this trait.
@@ -40,13 +52,7 @@ trait Parse<'a>: Sized {
     /// Attempt to parse the input into the AST node, and include source span
     /// metadata as well.
     fn parse_node(input: RawSpan<'a>) -> ParseResult<'a, SpanNode<Self>> {
-        let new_input = input; // need to copy so we can compare old pos vs new
-        let (i, value) = Self::parse(new_input)?;
-
-        let index = input.offset(&i);
-        let raw_span = input.slice(..index);
-
-        Ok((i, Node(value, Span::from_raw_span(&raw_span))))
+        with_span(Self::parse)(input)
     }
 }
 
@@ -69,16 +75,34 @@ impl<'a> Parse<'a> for usize {
 
 impl<'a> Parse<'a> for LangValue {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
-        map_res(recognize(tuple((opt(char('-')), digit1))), |s: RawSpan| {
-            s.fragment().parse::<LangValue>()
-        })(input)
+        let width = input.extra.value_width;
+        let (i, value) = terminated(
+            alt((char_literal, int_literal)),
+            // Don't accept something like "0_GT_1", which could be a label
+            not(take_while1(|c: char| c.is_alphanumeric() || c == '_')),
+        )(input)?;
+
+        // The value has to fit in the hardware's value width. The literal is
+        // well-formed at this point, so this is a hard failure.
+        match value {
+            Some(value) if width.contains(value) => Ok((i, value as LangValue)),
+            _ => Err(nom::Err::Failure(VerboseError::add_context(
+                input,
+                out_of_range_context(width),
+                VerboseError::from_error_kind(input, ErrorKind::Verify),
+            ))),
+        }
     }
 }
 
 impl<'a> Parse<'a> for Label {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
         map(
-            take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            recognize(preceded(
+                // A leading . makes it a local label
+                opt(char('.')),
+                take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            )),
             |s: RawSpan| Label::from(*s.fragment()),
         )(input)
     }
@@ -99,6 +123,15 @@ impl<'a> Parse<'a> for StackRef {
     }
 }
 
+impl<'a> Parse<'a> for QueueRef {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        map(
+            preceded(tag_no_case(QUEUE_REF_TAG), QueueId::parse),
+            QueueRef,
+        )(input)
+    }
+}
+
 impl<'a> Parse<'a> for RegisterRef {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
         alt((
@@ -108,6 +141,14 @@ impl<'a> Parse<'a> for RegisterRef {
             map(tag_no_case(INPUT_LENGTH_REGISTER_REF), |_| {
                 RegisterRef::InputLength
             }),
+            // "RPC" => RegisterRef::ProgramCounter
+            map(tag_no_case(PROGRAM_COUNTER_REGISTER_REF), |_| {
+                RegisterRef::ProgramCounter
+            }),
+            // "RCY" => RegisterRef::CycleCount
+            map(tag_no_case(CYCLE_COUNT_REGISTER_REF), |_| {
+                RegisterRef::CycleCount
+            }),
             // "RSx" => RegisterRef::StackLength(x)
             map(
                 preceded(
@@ -116,6 +157,14 @@ impl<'a> Parse<'a> for RegisterRef {
                 ),
                 RegisterRef::StackLength,
             ),
+            // "RQx" => RegisterRef::QueueLength(x)
+            map(
+                preceded(
+                    tag_no_case(QUEUE_LENGTH_REGISTER_REF_TAG),
+                    cut(QueueId::parse),
+                ),
+                RegisterRef::QueueLength,
+            ),
             // "RXx" => RegisterRef::User(x)
             map(
                 preceded(
@@ -124,78 +173,240 @@ impl<'a> Parse<'a> for RegisterRef {
                 ),
                 RegisterRef::User,
             ),
+            // "[RX0]" => RegisterRef::Indirect(RX0)
+            map(
+                delimited(char('['), RegisterRef::parse, char(']')),
+                |index_reg| RegisterRef::Indirect(Box::new(index_reg)),
+            ),
+        ))(input)
+    }
+}
+
+impl<'a> Parse<'a> for RegisterOperand {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        alt((
+            // The symbol has to go first, so that names that start with a
+            // register tag (e.g. "RXA") don't trip the cut in the register
+            // parser
+            map(symbol, RegisterOperand::Alias),
+            // "[RX0]" => RegisterOperand::Indirect(RX0)
+            map(indirect_index, |index_reg| {
+                RegisterOperand::Indirect(Box::new(index_reg))
+            }),
+            map(RegisterRef::parse, RegisterOperand::Register),
+        ))(input)
+    }
+}
+
+impl<'a> Parse<'a> for StackOperand<RegisterOperand> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        alt((
+            // "S0" => StackOperand::Direct(S0)
+            map(StackRef::parse, StackOperand::Direct),
+            // "S[RX0]" => StackOperand::Indirect(RX0)
+            map(
+                preceded(tag_no_case(STACK_REF_TAG), indirect_index),
+                StackOperand::Indirect,
+            ),
         ))(input)
     }
 }
 
-impl<'a> Parse<'a> for ValueSource<Span> {
+impl<'a> Parse<'a> for ValueOperand<Span> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
         alt((
             // "1" => const value
-            map(LangValue::parse_node, ValueSource::Const),
+            // "LIMIT" => named constant or register alias
+            // "LIMIT*2+1" => constant expression
+            map(expr, |Node(expr, span)| match expr {
+                Expr::Literal(value) => ValueOperand::Const(Node(value, span)),
+                Expr::Symbol(name) => ValueOperand::Symbol(Node(name, span)),
+                expr => ValueOperand::Expr(Node(expr, span)),
+            }),
             // "RX1" => register
-            map(RegisterRef::parse_node, ValueSource::Register),
+            map(RegisterOperand::parse_node, ValueOperand::Register),
         ))(input)
     }
 }
 
-impl<'a> Parse<'a> for Operator<Span> {
+impl<'a> Parse<'a> for Operator<Span, RegisterOperand, ValueOperand<Span>> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        // alt only supports a limited number of parsers, so the operators are
+        // split into groups
         alt((
-            tag_with_args("READ", register_ref_arg, Operator::Read),
-            tag_with_args("WRITE", value_source_arg, Operator::Write),
-            tag_with_args(
-                "SET",
-                tuple((register_ref_arg, value_source_arg)),
-                |(dst, src)| Operator::Set(dst, src),
-            ),
-            tag_with_args(
-                "ADD",
-                tuple((register_ref_arg, value_source_arg)),
-                |(dst, src)| Operator::Add(dst, src),
-            ),
+            // Register operators
+            alt((
+                tag_with_args("READ", register_ref_arg, Operator::Read),
+                tag_with_args("WRITE", value_source_arg, Operator::Write),
+                tag_with_args(
+                    "SET",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Set(dst, src),
+                ),
+                tag_with_args(
+                    "ADD",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Add(dst, src),
+                ),
+                tag_with_args(
+                    "SUB",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Sub(dst, src),
+                ),
+                tag_with_args(
+                    "MUL",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Mul(dst, src),
+                ),
+                tag_with_args(
+                    "DIV",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Div(dst, src),
+                ),
+                tag_with_args(
+                    "MOD",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Mod(dst, src),
+                ),
+                tag_with_args("NEG", register_ref_arg, Operator::Neg),
+                tag_with_args(
+                    "AND",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::And(dst, src),
+                ),
+                tag_with_args(
+                    "OR",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Or(dst, src),
+                ),
+                tag_with_args(
+                    "XOR",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Xor(dst, src),
+                ),
+                tag_with_args(
+                    "SHL",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Shl(dst, src),
+                ),
+                tag_with_args(
+                    "SHR",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, src)| Operator::Shr(dst, src),
+                ),
+                tag_with_args(
+                    "CMP",
+                    tuple((
+                        register_ref_arg,
+                        value_source_arg,
+                        value_source_arg,
+                    )),
+                    |(dst, src_1, src_2)| Operator::Cmp(dst, src_1, src_2),
+                ),
+                tag_with_args("RAND", register_ref_arg, Operator::Rand),
+            )),
+            // Storage operators
+            alt((
+                tag_with_args(
+                    "PUSH",
+                    tuple((value_source_arg, stack_ref_arg)),
+                    |(src, stack)| Operator::Push(src, stack),
+                ),
+                tag_with_args(
+                    "POP",
+                    tuple((stack_ref_arg, register_ref_arg)),
+                    |(stack, dst)| Operator::Pop(stack, dst),
+                ),
+                tag_with_args(
+                    "PEEK",
+                    tuple((stack_ref_arg, register_ref_arg)),
+                    |(stack, dst)| Operator::Peek(stack, dst),
+                ),
+                tag_with_args("DUP", stack_ref_arg, Operator::Dup),
+                tag_with_args("SWAP", stack_ref_arg, Operator::Swap),
+                tag_with_args(
+                    "ENQ",
+                    tuple((value_source_arg, queue_ref_arg)),
+                    |(src, queue)| Operator::Enq(src, queue),
+                ),
+                tag_with_args(
+                    "DEQ",
+                    tuple((queue_ref_arg, register_ref_arg)),
+                    |(queue, dst)| Operator::Deq(queue, dst),
+                ),
+                tag_with_args(
+                    "LOAD",
+                    tuple((register_ref_arg, value_source_arg)),
+                    |(dst, addr)| Operator::Load(dst, addr),
+                ),
+                tag_with_args(
+                    "STORE",
+                    tuple((value_source_arg, value_source_arg)),
+                    |(src, addr)| Operator::Store(src, addr),
+                ),
+            )),
+        ))(input)
+    }
+}
+
+impl<'a> Parse<'a> for Jump<Span, ValueOperand<Span>> {
+    fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
+        alt((
+            map(tag_no_case("JMP"), |_| Jump::Jmp),
+            tag_with_args("JEZ", value_source_arg, Jump::Jez),
+            tag_with_args("JNZ", value_source_arg, Jump::Jnz),
+            tag_with_args("JGZ", value_source_arg, Jump::Jgz),
+            tag_with_args("JLZ", value_source_arg, Jump::Jlz),
             tag_with_args(
-                "SUB",
-                tuple((register_ref_arg, value_source_arg)),
-                |(dst, src)| Operator::Sub(dst, src),
+                "JEQ",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jeq(src_1, src_2),
             ),
             tag_with_args(
-                "MUL",
-                tuple((register_ref_arg, value_source_arg)),
-                |(dst, src)| Operator::Mul(dst, src),
+                "JNE",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jne(src_1, src_2),
             ),
             tag_with_args(
-                "DIV",
-                tuple((register_ref_arg, value_source_arg)),
-                |(dst, src)| Operator::Div(dst, src),
+                "JLT",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jlt(src_1, src_2),
             ),
             tag_with_args(
-                "CMP",
-                tuple((register_ref_arg, value_source_arg, value_source_arg)),
-                |(dst, src_1, src_2)| Operator::Cmp(dst, src_1, src_2),
+                "JGT",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jgt(src_1, src_2),
             ),
             tag_with_args(
-                "PUSH",
-                tuple((value_source_arg, stack_ref_arg)),
-                |(src, stack)| Operator::Push(src, stack),
+                "JLE",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jle(src_1, src_2),
             ),
             tag_with_args(
-                "POP",
-                tuple((stack_ref_arg, register_ref_arg)),
-                |(stack, dst)| Operator::Pop(stack, dst),
+                "JGE",
+                tuple((value_source_arg, value_source_arg)),
+                |(src_1, src_2)| Jump::Jge(src_1, src_2),
             ),
         ))(input)
     }
 }
 
-impl<'a> Parse<'a> for Jump<Span> {
+impl<'a> Parse<'a> for Directive<Span> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
         alt((
-            map(tag_no_case("JMP"), |_| Jump::Jmp),
-            tag_with_args("JEZ", value_source_arg, Jump::Jez),
-            tag_with_args("JNZ", value_source_arg, Jump::Jnz),
-            tag_with_args("JGZ", value_source_arg, Jump::Jgz),
-            tag_with_args("JLZ", value_source_arg, Jump::Jlz),
+            tag_with_args(
+                ".const",
+                tuple((symbol_arg, arg("value", LangValue::parse_node))),
+                |(name, value)| Directive::Const(name, value),
+            ),
+            tag_with_args(
+                ".alias",
+                tuple((
+                    symbol_arg,
+                    arg("register reference", RegisterRef::parse_node),
+                )),
+                |(name, reg)| Directive::Alias(name, reg),
+            ),
         ))(input)
     }
 }
@@ -203,8 +414,30 @@ impl<'a> Parse<'a> for Jump<Span> {
 impl<'a> Parse<'a> for Statement<Span> {
     fn parse(input: RawSpan<'a>) -> ParseResult<'a, Self> {
         alt((
+            tag_with_args(".include", include_path_arg, Statement::Include),
+            map(Directive::parse_node, Statement::Directive),
             map(LabelDecl::parse_node, Statement::Label),
             map(Operator::parse_node, Statement::Operator),
+            tag_with_args("CALL", label_arg, Statement::Call),
+            map(
+                terminated(tag_no_case("RET"), stmt_token_terminator),
+                |_| Statement::Return,
+            ),
+            map(
+                terminated(tag_no_case("HALT"), stmt_token_terminator),
+                |_| Statement::Halt,
+            ),
+            if_block,
+            while_block,
+            // This has to go before the label jumps, because a register
+            // reference could also be parsed as a label
+            map(
+                preceded(
+                    terminated(tag_no_case("JMP"), stmt_token_terminator),
+                    computed_jump_target,
+                ),
+                Statement::ComputedJump,
+            ),
             // semi-hack, necessary because of how the AST is organized to
             // share code between source and compiled
             map(tuple((Jump::parse_node, label_arg)), |(jmp, lbl)| {
@@ -227,8 +460,10 @@ impl<'a> Parse<'a> for Program<Span> {
                         .filter_map(std::convert::identity)
                         .collect();
 
-                    // If the program is empty, that's no bueno
-                    if body.is_empty() {
+                    // If the program is empty, that's no bueno. Unless every
+                    // line had a syntax error, then we've already complained.
+                    if body.is_empty() && input.extra.errors.borrow().is_empty()
+                    {
                         Err(
                             make_error::<RawSpan<'a>, VerboseError<RawSpan<'a>>>(
                                 input,
@@ -249,6 +484,55 @@ impl<'a> Parse<'a> for Program<Span> {
 
 // ===== Combinators =====
 
+/// Wraps a parser so that its output includes source span metadata.
+fn with_span<'a, O, F>(
+    parser: F,
+) -> impl Fn(RawSpan<'a>) -> ParseResult<'a, SpanNode<O>>
+where
+    F: Fn(RawSpan<'a>) -> ParseResult<'a, O>,
+{
+    move |input: RawSpan<'a>| {
+        let new_input = input; // need to copy so we can compare old pos vs new
+        let (i, value) = parser(new_input)?;
+
+        let index = input.offset(&i);
+        let raw_span = input.slice(..index);
+
+        Ok((i, Node(value, Span::from_raw_span(&raw_span))))
+    }
+}
+
+/// Parses a left-associative chain of binary operations, e.g. `1+2-3`. Once
+/// an operator has been parsed, the operand after it is required.
+fn binary_chain<'a, P, O>(
+    operand: P,
+    operator: O,
+) -> impl Fn(RawSpan<'a>) -> ParseResult<'a, SpanNode<Expr<Span>>>
+where
+    P: Fn(RawSpan<'a>) -> ParseResult<'a, SpanNode<Expr<Span>>>,
+    O: Fn(RawSpan<'a>) -> ParseResult<'a, BinaryOp>,
+{
+    move |input: RawSpan<'a>| {
+        let (mut i, mut lhs) = operand(input)?;
+        loop {
+            let (after_op, op) = match operator(i) {
+                Ok(result) => result,
+                Err(nom::Err::Error(_)) => return Ok((i, lhs)),
+                Err(err) => return Err(err),
+            };
+            let (rest, rhs) = context("value", cut(&operand))(after_op)?;
+
+            // The span of the new node covers both operands
+            let raw_span = input.slice(..input.offset(&rest));
+            lhs = Node(
+                Expr::Binary(op, Box::new(lhs), Box::new(rhs)),
+                Span::from_raw_span(&raw_span),
+            );
+            i = rest;
+        }
+    }
+}
+
 fn arg<'a, O, F>(
     context_label: &'static str,
     arg_parser: F,
@@ -294,21 +578,347 @@ where
 
 // ===== Parsers =====
 
-/// Parse a [RegisterRef] argument to an instruction
-fn register_ref_arg(input: RawSpan) -> ParseResult<'_, SpanNode<RegisterRef>> {
-    arg("register reference", RegisterRef::parse_node)(input)
+/// Checks if a name could be confused with a register, stack, or queue
+/// reference.
+/// Reserved names can't be used as symbols.
+pub(crate) fn is_reserved_name(name: &str) -> bool {
+    let name = name.to_uppercase();
+    let is_tagged_ref = |tag: &str| {
+        name.starts_with(tag)
+            && name[tag.len()..].starts_with(|c: char| c.is_ascii_digit())
+    };
+
+    name == NULL_REGISTER_REF
+        || name == INPUT_LENGTH_REGISTER_REF
+        || name == PROGRAM_COUNTER_REGISTER_REF
+        || name == CYCLE_COUNT_REGISTER_REF
+        || is_tagged_ref(STACK_LENGTH_REGISTER_REF_TAG)
+        || is_tagged_ref(QUEUE_LENGTH_REGISTER_REF_TAG)
+        || is_tagged_ref(USER_REGISTER_REF_TAG)
+        || is_tagged_ref(STACK_REF_TAG)
+        || is_tagged_ref(QUEUE_REF_TAG)
+}
+
+/// Parse a [Symbol], i.e. a user-defined name for a constant or register. A
+/// symbol can't start with a digit, and can't be a reserved name.
+fn symbol(input: RawSpan) -> ParseResult<'_, Symbol> {
+    map(
+        verify(
+            take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+            |s: &RawSpan| {
+                let frag = s.fragment();
+                !frag.starts_with(|c: char| c.is_ascii_digit())
+                    && !is_reserved_name(frag)
+            },
+        ),
+        |s: RawSpan| Symbol::from(*s.fragment()),
+    )(input)
+}
+
+/// Parse a constant expression, e.g. `LIMIT*2+1`. `*`, `/` and `%` bind
+/// tighter than `+` and `-`, and parentheses can be used for grouping.
+/// Whitespace isn't allowed, since it's used to separate arguments.
+fn expr(input: RawSpan) -> ParseResult<'_, SpanNode<Expr<Span>>> {
+    binary_chain(
+        expr_term,
+        alt((
+            map(char('+'), |_| BinaryOp::Add),
+            map(char('-'), |_| BinaryOp::Sub),
+        )),
+    )(input)
+}
+
+/// Parse a product/quotient in a constant expression, e.g. `3*4`
+fn expr_term(input: RawSpan) -> ParseResult<'_, SpanNode<Expr<Span>>> {
+    binary_chain(
+        expr_factor,
+        alt((
+            map(char('*'), |_| BinaryOp::Mul),
+            map(char('/'), |_| BinaryOp::Div),
+            map(char('%'), |_| BinaryOp::Mod),
+        )),
+    )(input)
+}
+
+/// Parse a single operand in a constant expression: a literal, a symbol, a
+/// negated operand, or a parenthesized expression. A minus sign directly in
+/// front of a number is part of the literal, so it's only parsed as negation
+/// in front of anything else, e.g. `-LIMIT` or `-(3)`.
+fn expr_factor(input: RawSpan) -> ParseResult<'_, SpanNode<Expr<Span>>> {
+    alt((
+        with_span(map(LangValue::parse, Expr::Literal)),
+        with_span(map(symbol, Expr::Symbol)),
+        with_span(map(preceded(char('-'), expr_factor), |operand| {
+            Expr::Neg(Box::new(operand))
+        })),
+        preceded(
+            char('('),
+            cut(terminated(
+                context("value", expr),
+                context("closing parenthesis", char(')')),
+            )),
+        ),
+    ))(input)
+}
+
+/// Parse an integer literal, in decimal (`65`), hex (`0x41`) or binary
+/// (`0b1000001`) form, with an optional leading minus sign. Returns `None` if
+/// the literal is too big to even fit in an `i64`.
+fn int_literal(input: RawSpan) -> ParseResult<'_, Option<i64>> {
+    map(
+        tuple((
+            opt(char('-')),
+            alt((
+                map(preceded(tag_no_case("0x"), hex_digit1), |s| (s, 16)),
+                map(preceded(tag_no_case("0b"), is_a("01")), |s| (s, 2)),
+                map(digit1, |s| (s, 10)),
+            )),
+        )),
+        |(sign, (digits, radix)): (_, (RawSpan, u32))| {
+            let value = i64::from_str_radix(digits.fragment(), radix).ok()?;
+            Some(if sign.is_some() { -value } else { value })
+        },
+    )(input)
+}
+
+/// Parse a character literal, e.g. `'A'` or `'\n'`. The value is the
+/// character's Unicode code point. Nothing else can start with a quote, so
+/// any error after the opening quote is a hard failure.
+fn char_literal(input: RawSpan) -> ParseResult<'_, Option<i64>> {
+    map(
+        preceded(
+            char('\''),
+            cut(terminated(
+                context("character", alt((escaped_char, none_of("\\'\r\n")))),
+                context("closing quote", char('\'')),
+            )),
+        ),
+        |c| Some(u32::from(c).into()),
+    )(input)
+}
+
+/// Parse an escape sequence in a character literal, e.g. `\n`.
+fn escaped_char(input: RawSpan) -> ParseResult<'_, char> {
+    preceded(
+        char('\\'),
+        cut(context(
+            "escape sequence",
+            alt((
+                map(char('n'), |_| '\n'),
+                map(char('r'), |_| '\r'),
+                map(char('t'), |_| '\t'),
+                map(char('0'), |_| '\0'),
+                char('\\'),
+                char('\''),
+                char('"'),
+            )),
+        )),
+    )(input)
+}
+
+/// The error context for a literal that doesn't fit in the value width.
+/// Contexts have to be static, so the bounds can't be formatted on the fly.
+fn out_of_range_context(width: ValueWidth) -> &'static str {
+    match width {
+        ValueWidth::Bits8 => "value between -128 and 127",
+        ValueWidth::Bits16 => "value between -32768 and 32767",
+        ValueWidth::Bits32 => "value between -2147483648 and 2147483647",
+    }
+}
+
+/// Parse the register that holds the index for an indirect reference, e.g.
+/// the "[RX0]" in "S[RX0]".
+fn indirect_index(input: RawSpan) -> ParseResult<'_, RegisterOperand> {
+    delimited(char('['), RegisterOperand::parse, char(']'))(input)
+}
+
+/// Parse a [RegisterOperand] argument to an instruction. This can be an alias
+/// for a register, which will be resolved during validation.
+fn register_ref_arg(
+    input: RawSpan,
+) -> ParseResult<'_, SpanNode<RegisterOperand>> {
+    arg("register reference", RegisterOperand::parse_node)(input)
+}
+
+/// Parse a [Symbol] argument to a directive
+fn symbol_arg(input: RawSpan) -> ParseResult<'_, SpanNode<Symbol>> {
+    arg("name", with_span(symbol))(input)
+}
+
+/// Parse a [StackOperand] argument to an instruction
+fn stack_ref_arg(
+    input: RawSpan,
+) -> ParseResult<'_, SpanNode<StackOperand<RegisterOperand>>> {
+    arg("stack reference", StackOperand::parse_node)(input)
 }
 
-/// Parse a [StackRef] argument to an instruction
-fn stack_ref_arg(input: RawSpan) -> ParseResult<'_, SpanNode<StackRef>> {
-    arg("stack reference", StackRef::parse_node)(input)
+/// Parse a [QueueRef] argument to an instruction
+fn queue_ref_arg(input: RawSpan) -> ParseResult<'_, SpanNode<QueueRef>> {
+    arg("queue reference", QueueRef::parse_node)(input)
 }
 
-/// Parse a [ValueSource] argument to an instruction
+/// Parse a [ValueOperand] argument to an instruction
 fn value_source_arg(
     input: RawSpan,
-) -> ParseResult<'_, SpanNode<ValueSource<Span>>> {
-    arg("value", ValueSource::parse_node)(input)
+) -> ParseResult<'_, SpanNode<ValueOperand<Span>>> {
+    arg("value", ValueOperand::parse_node)(input)
+}
+
+/// Parse the target of a computed jump, i.e. a register, constant or
+/// constant expression. This deliberately doesn't accept a lone symbol, or use
+/// any cuts, so that anything that could be a label can fall through to the
+/// label jump parser. Symbols bound to a constant or register are turned into
+/// computed jumps during resolution instead.
+fn computed_jump_target(
+    input: RawSpan,
+) -> ParseResult<'_, SpanNode<ValueOperand<Span>>> {
+    delimited(
+        space1,
+        verify(ValueOperand::parse_node, |Node(target, _)| match target {
+            ValueOperand::Symbol(_) => false,
+            _ => true,
+        }),
+        stmt_token_terminator,
+    )(input)
+}
+
+/// Parse the condition of an `IF` or `WHILE` block, e.g. `RX0 > 0`. The
+/// condition is represented as the compare jump that it's equivalent to.
+fn condition_arg(
+    input: RawSpan,
+) -> ParseResult<'_, SpanNode<Jump<Span, ValueOperand<Span>>>> {
+    context(
+        "value",
+        preceded(
+            space1,
+            with_span(map(
+                tuple((
+                    context(
+                        "value",
+                        terminated(
+                            ValueOperand::parse_node,
+                            stmt_token_terminator,
+                        ),
+                    ),
+                    arg(
+                        "comparison operator",
+                        alt((
+                            map(tag("=="), |_| Jump::Jeq as fn(_, _) -> _),
+                            map(tag("!="), |_| Jump::Jne as fn(_, _) -> _),
+                            map(tag("<="), |_| Jump::Jle as fn(_, _) -> _),
+                            map(tag(">="), |_| Jump::Jge as fn(_, _) -> _),
+                            map(tag("<"), |_| Jump::Jlt as fn(_, _) -> _),
+                            map(tag(">"), |_| Jump::Jgt as fn(_, _) -> _),
+                        )),
+                    ),
+                    value_source_arg,
+                )),
+                |(lhs, jump, rhs)| jump(lhs, rhs),
+            )),
+        ),
+    )(input)
+}
+
+/// Parses a keyword that's part of a block (e.g. `ELSE` or `END`), along
+/// with any leading whitespace.
+fn block_keyword<'a>(
+    keyword: &'static str,
+) -> impl Fn(RawSpan<'a>) -> ParseResult<'a, SpanNode<()>> {
+    preceded(
+        space0,
+        with_span(map(
+            terminated(tag_no_case(keyword), stmt_token_terminator),
+            |_| (),
+        )),
+    )
+}
+
+/// Parse the lines in the body of a block, up to (but not including) the
+/// `ELSE` or `END` that closes it.
+fn block_body(input: RawSpan) -> ParseResult<'_, Block<Span>> {
+    map(
+        many0(preceded(
+            not(alt((block_keyword("ELSE"), block_keyword("END")))),
+            line,
+        )),
+        // filter out empty lines
+        |lines| {
+            lines
+                .into_iter()
+                .filter_map(std::convert::identity)
+                .collect()
+        },
+    )(input)
+}
+
+/// Parse an `IF` block, with an optional `ELSE` branch.
+fn if_block(input: RawSpan) -> ParseResult<'_, Statement<Span>> {
+    map(
+        preceded(
+            terminated(tag_no_case("IF"), stmt_token_terminator),
+            context(
+                "IF",
+                cut(tuple((
+                    terminated(condition_arg, end_of_statement),
+                    if_block_rest,
+                ))),
+            ),
+        ),
+        |(condition, (body, else_body, _))| Statement::If {
+            condition,
+            body,
+            else_body,
+        },
+    )(input)
+}
+
+/// The parts of an `IF` block after its first line: the body, the optional
+/// `ELSE` branch, and the `END` keyword.
+type IfBlockRest = (Block<Span>, Option<Node<Block<Span>, Span>>, SpanNode<()>);
+
+/// Parse everything in an `IF` block after its first line: the body, the
+/// optional `ELSE` branch, and the closing `END`.
+fn if_block_rest(input: RawSpan) -> ParseResult<'_, IfBlockRest> {
+    tuple((
+        block_body,
+        opt(map(
+            tuple((
+                terminated(block_keyword("ELSE"), end_of_statement),
+                block_body,
+            )),
+            |(Node((), span), body)| Node(body, span),
+        )),
+        context("END", block_keyword("END")),
+    ))(input)
+}
+
+/// Parse a `WHILE` block.
+fn while_block(input: RawSpan) -> ParseResult<'_, Statement<Span>> {
+    map(
+        preceded(
+            terminated(tag_no_case("WHILE"), stmt_token_terminator),
+            context(
+                "WHILE",
+                cut(tuple((
+                    terminated(condition_arg, end_of_statement),
+                    while_block_rest,
+                ))),
+            ),
+        ),
+        |(condition, (body, Node((), end)))| Statement::While {
+            condition,
+            body,
+            end,
+        },
+    )(input)
+}
+
+/// Parse everything in a `WHILE` block after its first line: the body and the
+/// closing `END`.
+fn while_block_rest(
+    input: RawSpan,
+) -> ParseResult<'_, (Block<Span>, SpanNode<()>)> {
+    tuple((block_body, context("END", block_keyword("END"))))(input)
 }
 
 /// Parse a [Label] argument to an instruction
@@ -316,6 +926,23 @@ fn label_arg(input: RawSpan) -> ParseResult<'_, SpanNode<Label>> {
     arg("label", Label::parse_node)(input)
 }
 
+/// Parses the quoted path in an `.include` directive. The span of the output
+/// doesn't include the quotes.
+fn include_path_arg(input: RawSpan) -> ParseResult<'_, SpanNode<String>> {
+    arg(
+        "file path",
+        preceded(
+            char('"'),
+            cut(terminated(
+                with_span(map(is_not("\"\r\n"), |s: RawSpan| {
+                    s.fragment().to_string()
+                })),
+                context("closing quote", char('"')),
+            )),
+        ),
+    )(input)
+}
+
 /// The terminator that always follows a token in a statement (which is an
 /// instruction, argument, or label declaration). This does not consume the
 /// terminator, just check that it exists.
@@ -326,7 +953,7 @@ fn stmt_token_terminator(input: RawSpan) -> ParseResult<'_, RawSpan> {
 
 /// Parse a line ending, or return success if the input is empty (we've reached
 /// the end of the file).
-fn eol_or_eof(input: RawSpan) -> ParseResult<'_, RawSpan> {
+pub(crate) fn eol_or_eof(input: RawSpan) -> ParseResult<'_, RawSpan> {
     if input.fragment().is_empty() {
         Ok((input, input))
     } else {
@@ -357,84 +984,187 @@ fn end_of_statement(input: RawSpan) -> ParseResult<'_, ()> {
 }
 
 /// Parse a single line, up to and including either end of line or end of file.
+/// If the line has a syntax error, the error is recorded in the parse context,
+/// and the rest of the line that the error is on is skipped. That way, parsing
+/// can pick back up on the next line and find any other errors.
 fn line(input: RawSpan) -> ParseResult<'_, Option<SpanNode<Statement<Span>>>> {
     if input.fragment().is_empty() {
         // many0 fails if the parser consumers nothing, so we want to fail when
         // we normally would consume nothing
-        Err(nom::Err::Error(VerboseError::from_error_kind(
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
             input,
             ErrorKind::Eof,
-        )))
-    } else {
-        alt((
-            // These contexts are for debugging only. Any error should have
-            // a more precise context that can be shown to the user.
-            context("empty line [debug]", map(end_of_statement, |_| None)),
-            context(
-                "line w/ statement [debug]",
-                cut(map(
-                    delimited(
-                        space0,
-                        context("statement", Statement::parse_node),
-                        end_of_statement,
-                    ),
-                    Some,
-                )),
-            ),
-        ))(input)
+        )));
     }
+
+    match statement_line(input) {
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+            let (rest, error_line) = recover(&e)?;
+
+            // If the first line of a block is bad, the block never got parsed,
+            // so skip the rest of it. Otherwise its END would be reported as
+            // another error.
+            let starts_with = |keyword| {
+                preceded(
+                    space0,
+                    terminated(tag_no_case(keyword), stmt_token_terminator),
+                )(input)
+                .is_ok()
+            };
+            let rest = if error_line != input.location_line() {
+                rest
+            } else if starts_with("IF") {
+                skip_lines(map(if_block_rest, |_| ()), rest)?
+            } else if starts_with("WHILE") {
+                skip_lines(map(while_block_rest, |_| ()), rest)?
+            } else {
+                rest
+            };
+            Ok((rest, None))
+        }
+        result => result,
+    }
+}
+
+/// Record a syntax error in the parse context, then skip to the end of the line
+/// that the error occurred on. The error could be on a later line than where
+/// the failed parser started (e.g. in a block). Returns the remaining input and
+/// the line number of the error.
+fn recover<'a>(e: &VerboseError<RawSpan<'a>>) -> ParseResult<'a, u32> {
+    let (raw_span, error) = syntax_error(e);
+    raw_span
+        .extra
+        .errors
+        .borrow_mut()
+        .push((error, Span::from_position(&raw_span)));
+    let (rest, _) = terminated(opt(is_not("\r\n")), eol_or_eof)(raw_span)?;
+    Ok((rest, raw_span.location_line()))
 }
 
-/// Parse a full program
-fn parse(
-    input: &str,
-) -> Result<Program<Span>, Vec<SourceErrorWrapper<CompileError>>> {
-    match Program::parse(RawSpan::new(input)) {
-        Ok((_, program)) => Ok(program),
+/// Skip over some lines with the given parser, throwing away its output. If
+/// the parser fails, its error is recorded and skipped like any other.
+fn skip_lines<'a>(
+    parser: impl Fn(RawSpan<'a>) -> ParseResult<'a, ()>,
+    input: RawSpan<'a>,
+) -> Result<RawSpan<'a>, nom::Err<VerboseError<RawSpan<'a>>>> {
+    match parser(input) {
+        Ok((rest, ())) => Ok(rest),
         Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-            // Grab the first error in the chain that is a Context, which means
-            // we labelled it ourselves. Everything else is generated by nom
-            // which means it's useless.
-            let (raw_span, context) = e
-                .errors
-                .iter()
-                .filter_map(|err| match err {
-                    (span, VerboseErrorKind::Context(context)) => {
-                        Some((span, context))
-                    }
-                    _ => None,
-                })
-                .next()
-                // This indicates we're missing a context() call somewhere
-                .expect("No context errors available");
-
-            Err(vec![SourceErrorWrapper::new(
-                CompileError::Syntax { expected: context },
-                // the actual fragment here is just the remaining source, so
-                // it's not useful - just use the position from it
-                Span::from_position(raw_span),
-                input,
-            )])
+            Ok(recover(&e)?.0)
         }
-        // only possible in streaming mode
-        Err(nom::Err::Incomplete(_needed)) => unreachable!(),
+        Err(e) => Err(e),
     }
 }
 
+/// Parse a single line that is either empty or holds one statement, without
+/// any error recovery.
+fn statement_line(
+    input: RawSpan,
+) -> ParseResult<'_, Option<SpanNode<Statement<Span>>>> {
+    alt((
+        // These contexts are for debugging only. Any error should have
+        // a more precise context that can be shown to the user.
+        context("empty line [debug]", map(end_of_statement, |_| None)),
+        context(
+            "line w/ statement [debug]",
+            cut(map(
+                delimited(
+                    space0,
+                    context("statement", Statement::parse_node),
+                    end_of_statement,
+                ),
+                Some,
+            )),
+        ),
+    ))(input)
+}
+
+/// Convert a parse error into a compile error, along with the position in the
+/// source where it occurred.
+fn syntax_error<'a>(
+    e: &VerboseError<RawSpan<'a>>,
+) -> (RawSpan<'a>, CompileError) {
+    // Grab the first error in the chain that is a Context, which means we
+    // labelled it ourselves. Everything else is generated by nom which means
+    // it's useless.
+    let (raw_span, context) = e
+        .errors
+        .iter()
+        .filter_map(|err| match err {
+            (span, VerboseErrorKind::Context(context)) => Some((span, context)),
+            _ => None,
+        })
+        .next()
+        // This indicates we're missing a context() call somewhere
+        .expect("No context errors available");
+    (*raw_span, CompileError::Syntax { expected: context })
+}
+
+/// Parse one file from the given sources as a full program. Literal values must
+/// fit in the given value width. Any `.include` directives are left in the
+/// output, to be expanded later.
+///
+/// The parser recovers from syntax errors by skipping the offending line, so
+/// this always returns a program, along with every syntax error that was
+/// found. If there were errors, the program only holds the lines that parsed
+/// successfully.
+pub(crate) fn parse(
+    sources: &SourceFiles,
+    file: FileId,
+    value_width: ValueWidth,
+) -> (Program<Span>, Vec<SourceErrorWrapper<CompileError>>) {
+    let errors = RefCell::new(Vec::new());
+    let context = ParseContext {
+        file,
+        value_width,
+        errors: &errors,
+    };
+    let program =
+        match Program::parse(RawSpan::new_extra(sources.source(file), context))
+        {
+            Ok((_, program)) => program,
+            // Each line recovers from its own errors, so this only happens
+            // when the program is empty
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                let (raw_span, error) = syntax_error(&e);
+                // the actual fragment here is just the remaining source, so
+                // it's not useful - just use the position from it
+                errors
+                    .borrow_mut()
+                    .push((error, Span::from_position(&raw_span)));
+                Program { body: Vec::new() }
+            }
+            // only possible in streaming mode
+            Err(nom::Err::Incomplete(_needed)) => unreachable!(),
+        };
+
+    let errors = errors
+        .into_inner()
+        .into_iter()
+        .map(|(error, span)| SourceErrorWrapper::new(error, span, sources))
+        .collect();
+    (program, errors)
+}
+
 impl Compiler<()> {
     /// Parses source code from the given input, into an abstract syntax tree.
+    /// Returns the partial program along with any syntax errors, so that later
+    /// stages can still report errors on the lines that did parse.
     pub(crate) fn parse(
         self,
-    ) -> Result<Compiler<Program<Span>>, WithSource<CompileError>> {
-        match parse(&self.source) {
-            // Ok(program) => Ok(self.replace_ast(program)),
-            Ok(program) => Ok(Compiler {
-                source: self.source,
-                hardware_spec: self.hardware_spec,
-                ast: program,
-            }),
-            Err(errors) => Err(WithSource::new(errors, self.source)),
-        }
+    ) -> (
+        Compiler<Program<Span>>,
+        Vec<SourceErrorWrapper<CompileError>>,
+    ) {
+        let (program, errors) =
+            parse(&self.sources, 0, self.hardware_spec.value_width);
+        let compiler = Compiler {
+            sources: self.sources,
+            hardware_spec: self.hardware_spec,
+            ast: program,
+            warnings: self.warnings,
+        };
+        (compiler, errors)
     }
 }
 
@@ -442,6 +1172,22 @@ impl Compiler<()> {
 mod tests {
     use super::*;
 
+    // Most tests don't care about the value width, so use the default
+    fn parse(
+        input: &str,
+    ) -> Result<Program<Span>, Vec<SourceErrorWrapper<CompileError>>> {
+        let (program, errors) = super::parse(
+            &SourceFiles::new(input.into()),
+            0,
+            ValueWidth::default(),
+        );
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors)
+        }
+    }
+
     // Helper to make it a bit easier to create spans for tests
     fn span(
         start_line: usize,
@@ -458,6 +1204,7 @@ mod tests {
             start_col,
             end_line,
             end_col,
+            file: 0,
         }
     }
 
@@ -514,6 +1261,8 @@ mod tests {
                 LBL1:
                 LBL_WITH_UNDERSCORE:
                 1LBL:
+                .local:
+                1:
                 "
             )
             .unwrap()
@@ -547,6 +1296,20 @@ mod tests {
                     )),
                     span(5, 17, 5, 22)
                 ),
+                Node(
+                    Statement::Label(Node(
+                        LabelDecl(".local".into()),
+                        span(6, 17, 6, 24)
+                    )),
+                    span(6, 17, 6, 24)
+                ),
+                Node(
+                    Statement::Label(Node(
+                        LabelDecl("1".into()),
+                        span(7, 17, 7, 19)
+                    )),
+                    span(7, 17, 7, 19)
+                ),
             ]
         );
     }
@@ -566,7 +1329,7 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Read(Node(
-                            RegisterRef::User(0),
+                            RegisterOperand::Register(RegisterRef::User(0)),
                             span(2, 22, 2, 25)
                         ),),
                         span(2, 17, 2, 25)
@@ -576,8 +1339,8 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Write(Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(0),
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(0)),
                                 span(3, 23, 3, 26)
                             )),
                             span(3, 23, 3, 26)
@@ -607,9 +1370,15 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Set(
-                            Node(RegisterRef::User(1), span(2, 21, 2, 24)),
                             Node(
-                                ValueSource::Const(Node(4, span(2, 25, 2, 26))),
+                                RegisterOperand::Register(RegisterRef::User(1)),
+                                span(2, 21, 2, 24)
+                            ),
+                            Node(
+                                ValueOperand::Const(Node(
+                                    4,
+                                    span(2, 25, 2, 26)
+                                )),
                                 span(2, 25, 2, 26)
                             )
                         ),
@@ -620,10 +1389,15 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Set(
-                            Node(RegisterRef::User(1), span(3, 21, 3, 24)),
                             Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::InputLength,
+                                RegisterOperand::Register(RegisterRef::User(1)),
+                                span(3, 21, 3, 24)
+                            ),
+                            Node(
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::InputLength
+                                    ),
                                     span(3, 25, 3, 28)
                                 ),),
                                 span(3, 25, 3, 28)
@@ -636,10 +1410,15 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Set(
-                            Node(RegisterRef::User(1), span(4, 21, 4, 24)),
                             Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::StackLength(0),
+                                RegisterOperand::Register(RegisterRef::User(1)),
+                                span(4, 21, 4, 24)
+                            ),
+                            Node(
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::StackLength(0)
+                                    ),
                                     span(4, 25, 4, 28)
                                 ),),
                                 span(4, 25, 4, 28)
@@ -652,10 +1431,15 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Set(
-                            Node(RegisterRef::User(1), span(5, 21, 5, 24)),
                             Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::Null,
+                                RegisterOperand::Register(RegisterRef::User(1)),
+                                span(5, 21, 5, 24)
+                            ),
+                            Node(
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::Null
+                                    ),
                                     span(5, 25, 5, 28)
                                 ),),
                                 span(5, 25, 5, 28)
@@ -676,10 +1460,13 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Add(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(4),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(4)),
                                 span(1, 9, 1, 12)
                             )),
                             span(1, 9, 1, 12)
@@ -699,9 +1486,12 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Add(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Const(Node(-10, span(1, 9, 1, 12))),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Const(Node(-10, span(1, 9, 1, 12))),
                             span(1, 9, 1, 12)
                         )
                     ),
@@ -714,16 +1504,19 @@ mod tests {
 
     #[test]
     fn test_parse_lang_val_max() {
-        let source = format!("Add RX1 {}", LangValue::max_value());
+        let source = format!("Add RX1 {}", ValueWidth::default().max_value());
         assert_eq!(
             parse(&source).unwrap().body,
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Add(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Const(Node(
-                                LangValue::max_value(),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Const(Node(
+                                ValueWidth::default().max_value(),
                                 span(1, 9, 1, 14)
                             )),
                             span(1, 9, 1, 14)
@@ -738,16 +1531,19 @@ mod tests {
 
     #[test]
     fn test_parse_lang_val_min() {
-        let source = format!("Add RX1 {}", LangValue::min_value());
+        let source = format!("Add RX1 {}", ValueWidth::default().min_value());
         assert_eq!(
             parse(&source).unwrap().body,
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Add(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Const(Node(
-                                LangValue::min_value(),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Const(Node(
+                                ValueWidth::default().min_value(),
                                 span(1, 9, 1, 15)
                             )),
                             span(1, 9, 1, 15)
@@ -760,6 +1556,148 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_char_hex_binary_literals() {
+        let set_const = |value, end_col| {
+            Node(
+                Statement::Operator(Node(
+                    Operator::Set(
+                        Node(
+                            RegisterOperand::Register(RegisterRef::User(0)),
+                            span(1, 5, 1, 8),
+                        ),
+                        Node(
+                            ValueOperand::Const(Node(
+                                value,
+                                span(1, 9, 1, end_col),
+                            )),
+                            span(1, 9, 1, end_col),
+                        ),
+                    ),
+                    span(1, 1, 1, end_col),
+                )),
+                span(1, 1, 1, end_col),
+            )
+        };
+
+        assert_eq!(parse("SET RX0 'A'").unwrap().body, vec![set_const(65, 12)]);
+        assert_eq!(
+            parse("SET RX0 '\\n'").unwrap().body,
+            vec![set_const(10, 13)]
+        );
+        assert_eq!(
+            parse("SET RX0 0x7f").unwrap().body,
+            vec![set_const(127, 13)]
+        );
+        assert_eq!(
+            parse("SET RX0 -0B101").unwrap().body,
+            vec![set_const(-5, 15)]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_context() {
+        // Make sure the hardcoded bounds match the real ones
+        for width in
+            &[ValueWidth::Bits8, ValueWidth::Bits16, ValueWidth::Bits32]
+        {
+            assert_eq!(
+                out_of_range_context(*width),
+                format!(
+                    "value between {} and {}",
+                    width.min_value(),
+                    width.max_value()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_const_expr() {
+        // Multiplication binds tighter than addition
+        assert_eq!(
+            parse("ADD RX0 1+2*3").unwrap().body,
+            vec![Node(
+                Statement::Operator(Node(
+                    Operator::Add(
+                        Node(
+                            RegisterOperand::Register(RegisterRef::User(0)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Expr(Node(
+                                Expr::Binary(
+                                    BinaryOp::Add,
+                                    Box::new(Node(
+                                        Expr::Literal(1),
+                                        span(1, 9, 1, 10)
+                                    )),
+                                    Box::new(Node(
+                                        Expr::Binary(
+                                            BinaryOp::Mul,
+                                            Box::new(Node(
+                                                Expr::Literal(2),
+                                                span(1, 11, 1, 12)
+                                            )),
+                                            Box::new(Node(
+                                                Expr::Literal(3),
+                                                span(1, 13, 1, 14)
+                                            )),
+                                        ),
+                                        span(1, 11, 1, 14)
+                                    )),
+                                ),
+                                span(1, 9, 1, 14)
+                            )),
+                            span(1, 9, 1, 14)
+                        )
+                    ),
+                    span(1, 1, 1, 14)
+                )),
+                span(1, 1, 1, 14)
+            )]
+        );
+
+        // A minus sign in front of anything but a number is negation
+        assert_eq!(
+            parse("WRITE -LIMIT").unwrap().body,
+            vec![Node(
+                Statement::Operator(Node(
+                    Operator::Write(Node(
+                        ValueOperand::Expr(Node(
+                            Expr::Neg(Box::new(Node(
+                                Expr::Symbol("LIMIT".into()),
+                                span(1, 8, 1, 13)
+                            ))),
+                            span(1, 7, 1, 13)
+                        )),
+                        span(1, 7, 1, 13)
+                    )),
+                    span(1, 1, 1, 13)
+                )),
+                span(1, 1, 1, 13)
+            )]
+        );
+
+        // A parenthesized symbol is still just a symbol
+        assert_eq!(
+            parse("WRITE (LIMIT)").unwrap().body,
+            vec![Node(
+                Statement::Operator(Node(
+                    Operator::Write(Node(
+                        ValueOperand::Symbol(Node(
+                            "LIMIT".into(),
+                            span(1, 8, 1, 13)
+                        )),
+                        span(1, 7, 1, 14)
+                    )),
+                    span(1, 1, 1, 14)
+                )),
+                span(1, 1, 1, 14)
+            )]
+        );
+    }
+
     #[test]
     fn test_sub() {
         assert_eq!(
@@ -767,10 +1705,13 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Sub(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(4),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(4)),
                                 span(1, 9, 1, 12)
                             )),
                             span(1, 9, 1, 12)
@@ -790,10 +1731,13 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Mul(
-                        Node(RegisterRef::User(1), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(4),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(4)),
                                 span(1, 9, 1, 12)
                             )),
                             span(1, 9, 1, 12)
@@ -813,13 +1757,16 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Cmp(
-                        Node(RegisterRef::User(0), span(1, 5, 1, 8)),
                         Node(
-                            ValueSource::Const(Node(5, span(1, 9, 1, 10))),
+                            RegisterOperand::Register(RegisterRef::User(0)),
+                            span(1, 5, 1, 8)
+                        ),
+                        Node(
+                            ValueOperand::Const(Node(5, span(1, 9, 1, 10))),
                             span(1, 9, 1, 10)
                         ),
                         Node(
-                            ValueSource::Const(Node(10, span(1, 11, 1, 13))),
+                            ValueOperand::Const(Node(10, span(1, 11, 1, 13))),
                             span(1, 11, 1, 13)
                         )
                     ),
@@ -830,6 +1777,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rand() {
+        assert_eq!(
+            parse("RAND RX0").unwrap().body,
+            vec![Node(
+                Statement::Operator(Node(
+                    Operator::Rand(Node(
+                        RegisterOperand::Register(RegisterRef::User(0)),
+                        span(1, 6, 1, 9)
+                    )),
+                    span(1, 1, 1, 9)
+                )),
+                span(1, 1, 1, 9)
+            )]
+        );
+    }
+
     #[test]
     fn test_push() {
         assert_eq!(
@@ -838,13 +1802,16 @@ mod tests {
                 Statement::Operator(Node(
                     Operator::Push(
                         Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(2),
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(2)),
                                 span(1, 6, 1, 9)
                             )),
                             span(1, 6, 1, 9)
                         ),
-                        Node(StackRef(4), span(1, 10, 1, 12))
+                        Node(
+                            StackOperand::Direct(StackRef(4)),
+                            span(1, 10, 1, 12)
+                        )
                     ),
                     span(1, 1, 1, 12)
                 )),
@@ -860,8 +1827,14 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Pop(
-                        Node(StackRef(4), span(1, 5, 1, 7)),
-                        Node(RegisterRef::User(2), span(1, 8, 1, 11)),
+                        Node(
+                            StackOperand::Direct(StackRef(4)),
+                            span(1, 5, 1, 7)
+                        ),
+                        Node(
+                            RegisterOperand::Register(RegisterRef::User(2)),
+                            span(1, 8, 1, 11)
+                        ),
                     ),
                     span(1, 1, 1, 11)
                 )),
@@ -870,6 +1843,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_indirect() {
+        assert_eq!(
+            parse("PUSH [RX0] S[RX1]\nPOP S[[RLI]] [counter]")
+                .unwrap()
+                .body,
+            vec![
+                Node(
+                    Statement::Operator(Node(
+                        Operator::Push(
+                            Node(
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Indirect(Box::new(
+                                        RegisterOperand::Register(
+                                            RegisterRef::User(0)
+                                        )
+                                    )),
+                                    span(1, 6, 1, 11)
+                                )),
+                                span(1, 6, 1, 11)
+                            ),
+                            Node(
+                                StackOperand::Indirect(
+                                    RegisterOperand::Register(
+                                        RegisterRef::User(1)
+                                    )
+                                ),
+                                span(1, 12, 1, 18)
+                            )
+                        ),
+                        span(1, 1, 1, 18)
+                    )),
+                    span(1, 1, 1, 18)
+                ),
+                Node(
+                    Statement::Operator(Node(
+                        Operator::Pop(
+                            Node(
+                                StackOperand::Indirect(
+                                    RegisterOperand::Indirect(Box::new(
+                                        RegisterOperand::Register(
+                                            RegisterRef::InputLength
+                                        )
+                                    ))
+                                ),
+                                span(2, 5, 2, 13)
+                            ),
+                            Node(
+                                RegisterOperand::Indirect(Box::new(
+                                    RegisterOperand::Alias("counter".into())
+                                )),
+                                span(2, 14, 2, 23)
+                            ),
+                        ),
+                        span(2, 1, 2, 23)
+                    )),
+                    span(2, 1, 2, 23)
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_jumps() {
         assert_eq!(
@@ -896,8 +1931,10 @@ mod tests {
                     Statement::Jump(
                         Node(
                             Jump::Jez(Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::User(0),
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::User(0)
+                                    ),
                                     span(3, 21, 3, 24)
                                 )),
                                 span(3, 21, 3, 24)
@@ -912,8 +1949,10 @@ mod tests {
                     Statement::Jump(
                         Node(
                             Jump::Jnz(Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::User(0),
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::User(0)
+                                    ),
                                     span(4, 21, 4, 24)
                                 )),
                                 span(4, 21, 4, 24)
@@ -928,8 +1967,10 @@ mod tests {
                     Statement::Jump(
                         Node(
                             Jump::Jlz(Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::User(0),
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::User(0)
+                                    ),
                                     span(5, 21, 5, 24)
                                 )),
                                 span(5, 21, 5, 24)
@@ -944,8 +1985,10 @@ mod tests {
                     Statement::Jump(
                         Node(
                             Jump::Jgz(Node(
-                                ValueSource::Register(Node(
-                                    RegisterRef::User(0),
+                                ValueOperand::Register(Node(
+                                    RegisterOperand::Register(
+                                        RegisterRef::User(0)
+                                    ),
                                     span(6, 21, 6, 24)
                                 )),
                                 span(6, 21, 6, 24)
@@ -960,6 +2003,227 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_computed_jumps() {
+        assert_eq!(
+            parse(
+                "
+                JMP RPC
+                JMP 3
+                JMP RXA
+                "
+            )
+            .unwrap()
+            .body,
+            vec![
+                Node(
+                    Statement::ComputedJump(Node(
+                        ValueOperand::Register(Node(
+                            RegisterOperand::Register(
+                                RegisterRef::ProgramCounter
+                            ),
+                            span(2, 21, 2, 24)
+                        )),
+                        span(2, 21, 2, 24)
+                    )),
+                    span(2, 17, 2, 24)
+                ),
+                Node(
+                    Statement::ComputedJump(Node(
+                        ValueOperand::Const(Node(3, span(3, 21, 3, 22))),
+                        span(3, 21, 3, 22)
+                    )),
+                    span(3, 17, 3, 22)
+                ),
+                // Anything that isn't a register or constant is a label
+                Node(
+                    Statement::Jump(
+                        Node(Jump::Jmp, span(4, 17, 4, 20)),
+                        Node("RXA".into(), span(4, 21, 4, 24))
+                    ),
+                    span(4, 17, 4, 24)
+                ),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_call_ret_halt() {
+        assert_eq!(
+            parse(
+                "
+                CALL LBL
+                RET
+                HALT
+                "
+            )
+            .unwrap()
+            .body,
+            vec![
+                Node(
+                    Statement::Call(Node("LBL".into(), span(2, 22, 2, 25))),
+                    span(2, 17, 2, 25)
+                ),
+                Node(Statement::Return, span(3, 17, 3, 20)),
+                Node(Statement::Halt, span(4, 17, 4, 21)),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse("IF RX0 < 1\n  HALT\nELSE\n  RET\nEND\nwhile 1 == RX0\nend")
+                .unwrap()
+                .body,
+            vec![
+                Node(
+                    Statement::If {
+                        condition: Node(
+                            Jump::Jlt(
+                                Node(
+                                    ValueOperand::Register(Node(
+                                        RegisterOperand::Register(
+                                            RegisterRef::User(0)
+                                        ),
+                                        span(1, 4, 1, 7)
+                                    )),
+                                    span(1, 4, 1, 7)
+                                ),
+                                Node(
+                                    ValueOperand::Const(Node(
+                                        1,
+                                        span(1, 10, 1, 11)
+                                    )),
+                                    span(1, 10, 1, 11)
+                                )
+                            ),
+                            span(1, 4, 1, 11)
+                        ),
+                        body: vec![Node(Statement::Halt, span(2, 3, 2, 7))],
+                        else_body: Some(Node(
+                            vec![Node(Statement::Return, span(4, 3, 4, 6))],
+                            span(3, 1, 3, 5)
+                        )),
+                    },
+                    span(1, 1, 5, 4)
+                ),
+                Node(
+                    Statement::While {
+                        condition: Node(
+                            Jump::Jeq(
+                                Node(
+                                    ValueOperand::Const(Node(
+                                        1,
+                                        span(6, 7, 6, 8)
+                                    )),
+                                    span(6, 7, 6, 8)
+                                ),
+                                Node(
+                                    ValueOperand::Register(Node(
+                                        RegisterOperand::Register(
+                                            RegisterRef::User(0)
+                                        ),
+                                        span(6, 12, 6, 15)
+                                    )),
+                                    span(6, 12, 6, 15)
+                                )
+                            ),
+                            span(6, 7, 6, 15)
+                        ),
+                        body: vec![],
+                        end: span(7, 1, 7, 4),
+                    },
+                    span(6, 1, 7, 4)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(
+            parse(
+                "
+                .const LIMIT 10
+                .alias counter RX0
+                SET counter LIMIT
+                "
+            )
+            .unwrap()
+            .body,
+            vec![
+                Node(
+                    Statement::Directive(Node(
+                        Directive::Const(
+                            Node("LIMIT".into(), span(2, 24, 2, 29)),
+                            Node(10, span(2, 30, 2, 32))
+                        ),
+                        span(2, 17, 2, 32)
+                    )),
+                    span(2, 17, 2, 32)
+                ),
+                Node(
+                    Statement::Directive(Node(
+                        Directive::Alias(
+                            Node("counter".into(), span(3, 24, 3, 31)),
+                            Node(RegisterRef::User(0), span(3, 32, 3, 35))
+                        ),
+                        span(3, 17, 3, 35)
+                    )),
+                    span(3, 17, 3, 35)
+                ),
+                Node(
+                    Statement::Operator(Node(
+                        Operator::Set(
+                            Node(
+                                RegisterOperand::Alias("counter".into()),
+                                span(4, 21, 4, 28)
+                            ),
+                            Node(
+                                ValueOperand::Symbol(Node(
+                                    "LIMIT".into(),
+                                    span(4, 29, 4, 34)
+                                )),
+                                span(4, 29, 4, 34)
+                            )
+                        ),
+                        span(4, 17, 4, 34)
+                    )),
+                    span(4, 17, 4, 34)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include() {
+        assert_eq!(
+            parse(".include \"lib/util.gdlk\" ; helpers").unwrap().body,
+            vec![Node(
+                Statement::Include(Node(
+                    "lib/util.gdlk".into(),
+                    span(1, 11, 1, 24)
+                )),
+                span(1, 1, 1, 25)
+            )]
+        );
+
+        // Spans from an included file are tagged with its ID
+        let mut sources = SourceFiles::new("".into());
+        let file = sources.add_include("lib.gdlk".into(), "HALT".into());
+        assert_eq!(
+            super::parse(&sources, file, ValueWidth::default()).0.body,
+            vec![Node(
+                Statement::Halt,
+                Span {
+                    file: 1,
+                    ..span(1, 1, 1, 5)
+                }
+            )]
+        );
+    }
+
     #[test]
     fn test_comments() {
         assert_eq!(
@@ -974,10 +2238,13 @@ mod tests {
             vec![Node(
                 Statement::Operator(Node(
                     Operator::Add(
-                        Node(RegisterRef::User(1), span(3, 21, 3, 24)),
                         Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(4),
+                            RegisterOperand::Register(RegisterRef::User(1)),
+                            span(3, 21, 3, 24)
+                        ),
+                        Node(
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(4)),
                                 span(3, 25, 3, 28)
                             )),
                             span(3, 25, 3, 28)
@@ -1009,7 +2276,7 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Read(Node(
-                            RegisterRef::User(0),
+                            RegisterOperand::Register(RegisterRef::User(0)),
                             span(3, 22, 3, 25)
                         )),
                         span(3, 17, 3, 25)
@@ -1019,9 +2286,15 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Set(
-                            Node(RegisterRef::User(0), span(5, 21, 5, 24)),
                             Node(
-                                ValueSource::Const(Node(2, span(5, 25, 5, 26))),
+                                RegisterOperand::Register(RegisterRef::User(0)),
+                                span(5, 21, 5, 24)
+                            ),
+                            Node(
+                                ValueOperand::Const(Node(
+                                    2,
+                                    span(5, 25, 5, 26)
+                                )),
                                 span(5, 25, 5, 26)
                             )
                         ),
@@ -1032,8 +2305,8 @@ mod tests {
                 Node(
                     Statement::Operator(Node(
                         Operator::Write(Node(
-                            ValueSource::Register(Node(
-                                RegisterRef::User(0),
+                            ValueOperand::Register(Node(
+                                RegisterOperand::Register(RegisterRef::User(0)),
                                 span(6, 23, 6, 26)
                             )),
                             span(6, 23, 6, 26)
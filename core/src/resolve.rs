@@ -0,0 +1,498 @@
+use crate::{
+    ast::{
+        source::{
+            BinaryOp, Block, Directive, Expr, LabelDecl, RegisterOperand,
+            ResolvedBlock, ResolvedStatement, Statement, ValueOperand,
+        },
+        Jump, LangValue, Node, Operator, RegisterRef, SpanNode, StackOperand,
+        Symbol, ValueSource,
+    },
+    error::CompileError,
+    models::ValueWidth,
+    util::Span,
+};
+use std::collections::HashMap;
+
+/// The value that a symbol is bound to by its directive.
+#[derive(Clone, Debug)]
+enum Binding {
+    /// Bound by `.const`
+    Const(LangValue),
+    /// Bound by `.alias`
+    Alias(RegisterRef),
+}
+
+/// All symbols defined in a program, mapped to their bindings and the span of
+/// their definition.
+type SymbolTable = HashMap<Symbol, (Binding, Span)>;
+
+/// Everything needed to resolve the symbols in a program.
+struct Context {
+    symbols: SymbolTable,
+    /// Constant expressions have to fit in the hardware's value width
+    value_width: ValueWidth,
+}
+
+trait Resolve {
+    /// The type of this node once it's resolved
+    type Output;
+
+    /// Replace any symbols in this node with the value that they're bound to,
+    /// and fold any constant expressions into a single value. The span of the
+    /// node is left untouched, so it still points at the use site. An error
+    /// is added for each undefined symbol and invalid expression, and a
+    /// placeholder is put in its place, so that the rest of the program can
+    /// still be validated.
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output;
+}
+
+impl Resolve for SpanNode<RegisterOperand> {
+    type Output = SpanNode<RegisterRef>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(reg, span) = self;
+        Node(resolve_register(reg, span, context, errors), span)
+    }
+}
+
+/// Resolve a register operand. Indirect registers don't have their own span
+/// for the index register, so any errors point to the whole operand. The null
+/// register is used as the placeholder for invalid references, so that it
+/// doesn't cause any more errors.
+fn resolve_register(
+    reg: RegisterOperand,
+    span: Span,
+    context: &Context,
+    errors: &mut Vec<(CompileError, Span)>,
+) -> RegisterRef {
+    match reg {
+        RegisterOperand::Register(reg_ref) => reg_ref,
+        RegisterOperand::Alias(name) => match context.symbols.get(&name) {
+            Some((Binding::Alias(reg_ref), _)) => reg_ref.clone(),
+            // Constants can't be used where a register is required
+            Some((Binding::Const(_), _)) => {
+                errors.push((CompileError::InvalidRegisterRef, span));
+                RegisterRef::Null
+            }
+            None => {
+                errors.push((CompileError::UndefinedSymbol, span));
+                RegisterRef::Null
+            }
+        },
+        RegisterOperand::Indirect(index_reg) => RegisterRef::Indirect(
+            Box::new(resolve_register(*index_reg, span, context, errors)),
+        ),
+    }
+}
+
+impl Resolve for SpanNode<StackOperand<RegisterOperand>> {
+    type Output = SpanNode<StackOperand>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(stack, span) = self;
+        let stack = match stack {
+            StackOperand::Direct(stack_ref) => StackOperand::Direct(stack_ref),
+            StackOperand::Indirect(index_reg) => StackOperand::Indirect(
+                resolve_register(index_reg, span, context, errors),
+            ),
+        };
+        Node(stack, span)
+    }
+}
+
+impl Resolve for SpanNode<ValueOperand<Span>> {
+    type Output = SpanNode<ValueSource<Span>>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(value, span) = self;
+        let value = match value {
+            ValueOperand::Const(value) => ValueSource::Const(value),
+            ValueOperand::Register(reg) => {
+                ValueSource::Register(reg.resolve(context, errors))
+            }
+            ValueOperand::Expr(expr) => ValueSource::Const(Node(
+                eval_expr(&expr, context, errors).unwrap_or(0),
+                expr.1,
+            )),
+            ValueOperand::Symbol(Node(name, span)) => {
+                match context.symbols.get(&name) {
+                    Some((Binding::Const(value), _)) => {
+                        ValueSource::Const(Node(*value, span))
+                    }
+                    Some((Binding::Alias(reg_ref), _)) => {
+                        ValueSource::Register(Node(reg_ref.clone(), span))
+                    }
+                    None => {
+                        errors.push((CompileError::UndefinedSymbol, span));
+                        ValueSource::Const(Node(0, span))
+                    }
+                }
+            }
+        };
+        Node(value, span)
+    }
+}
+
+/// Evaluate a constant expression. Like arithmetic at runtime, every
+/// intermediate value has to fit in the value width. Returns `None` if there
+/// are any errors, in which case they're added to the list.
+fn eval_expr(
+    Node(expr, span): &SpanNode<Expr<Span>>,
+    context: &Context,
+    errors: &mut Vec<(CompileError, Span)>,
+) -> Option<LangValue> {
+    let result = match expr {
+        Expr::Literal(value) => return Some(*value),
+        Expr::Symbol(name) => {
+            let error = match context.symbols.get(name) {
+                Some((Binding::Const(value), _)) => return Some(*value),
+                Some((Binding::Alias(_), _)) => CompileError::NonConstantSymbol,
+                None => CompileError::UndefinedSymbol,
+            };
+            errors.push((error, *span));
+            return None;
+        }
+        Expr::Neg(operand) => {
+            i64::from(eval_expr(operand, context, errors)?).checked_neg()
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            // Evaluate both sides before bailing out, to get all the errors
+            let lhs = eval_expr(lhs, context, errors);
+            let rhs = eval_expr(rhs, context, errors);
+            let (lhs, rhs) = (i64::from(lhs?), i64::from(rhs?));
+            match op {
+                BinaryOp::Add => lhs.checked_add(rhs),
+                BinaryOp::Sub => lhs.checked_sub(rhs),
+                BinaryOp::Mul => lhs.checked_mul(rhs),
+                BinaryOp::Div | BinaryOp::Mod if rhs == 0 => {
+                    errors.push((CompileError::ConstantDivideByZero, *span));
+                    return None;
+                }
+                // These round towards zero, same as DIV and MOD
+                BinaryOp::Div => lhs.checked_div(rhs),
+                BinaryOp::Mod => lhs.checked_rem(rhs),
+            }
+        }
+    };
+
+    match result {
+        Some(value) if context.value_width.contains(value) => {
+            Some(value as LangValue)
+        }
+        _ => {
+            errors.push((CompileError::ConstantOverflow, *span));
+            None
+        }
+    }
+}
+
+impl Resolve for SpanNode<Operator<Span, RegisterOperand, ValueOperand<Span>>> {
+    type Output = SpanNode<Operator<Span>>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(op, span) = self;
+        let c = context;
+        let e = errors;
+        let op = match op {
+            Operator::Read(dst) => Operator::Read(dst.resolve(c, e)),
+            Operator::Write(src) => Operator::Write(src.resolve(c, e)),
+            Operator::Set(dst, src) => {
+                Operator::Set(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Add(dst, src) => {
+                Operator::Add(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Sub(dst, src) => {
+                Operator::Sub(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Mul(dst, src) => {
+                Operator::Mul(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Div(dst, src) => {
+                Operator::Div(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Mod(dst, src) => {
+                Operator::Mod(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Neg(dst) => Operator::Neg(dst.resolve(c, e)),
+            Operator::And(dst, src) => {
+                Operator::And(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Or(dst, src) => {
+                Operator::Or(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Xor(dst, src) => {
+                Operator::Xor(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Shl(dst, src) => {
+                Operator::Shl(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Shr(dst, src) => {
+                Operator::Shr(dst.resolve(c, e), src.resolve(c, e))
+            }
+            Operator::Cmp(dst, src_1, src_2) => Operator::Cmp(
+                dst.resolve(c, e),
+                src_1.resolve(c, e),
+                src_2.resolve(c, e),
+            ),
+            Operator::Rand(dst) => Operator::Rand(dst.resolve(c, e)),
+            Operator::Push(src, stack) => {
+                Operator::Push(src.resolve(c, e), stack.resolve(c, e))
+            }
+            Operator::Pop(stack, dst) => {
+                Operator::Pop(stack.resolve(c, e), dst.resolve(c, e))
+            }
+            Operator::Peek(stack, dst) => {
+                Operator::Peek(stack.resolve(c, e), dst.resolve(c, e))
+            }
+            Operator::Dup(stack) => Operator::Dup(stack.resolve(c, e)),
+            Operator::Swap(stack) => Operator::Swap(stack.resolve(c, e)),
+            Operator::Enq(src, queue) => {
+                Operator::Enq(src.resolve(c, e), queue)
+            }
+            Operator::Deq(queue, dst) => {
+                Operator::Deq(queue, dst.resolve(c, e))
+            }
+            Operator::Load(dst, addr) => {
+                Operator::Load(dst.resolve(c, e), addr.resolve(c, e))
+            }
+            Operator::Store(src, addr) => {
+                Operator::Store(src.resolve(c, e), addr.resolve(c, e))
+            }
+        };
+        Node(op, span)
+    }
+}
+
+impl Resolve for SpanNode<Jump<Span, ValueOperand<Span>>> {
+    type Output = SpanNode<Jump<Span>>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(jump, span) = self;
+        let c = context;
+        let e = errors;
+        let jump = match jump {
+            Jump::Jmp => Jump::Jmp,
+            Jump::Jez(src) => Jump::Jez(src.resolve(c, e)),
+            Jump::Jnz(src) => Jump::Jnz(src.resolve(c, e)),
+            Jump::Jlz(src) => Jump::Jlz(src.resolve(c, e)),
+            Jump::Jgz(src) => Jump::Jgz(src.resolve(c, e)),
+            Jump::Jeq(src_1, src_2) => {
+                Jump::Jeq(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+            Jump::Jne(src_1, src_2) => {
+                Jump::Jne(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+            Jump::Jlt(src_1, src_2) => {
+                Jump::Jlt(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+            Jump::Jgt(src_1, src_2) => {
+                Jump::Jgt(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+            Jump::Jle(src_1, src_2) => {
+                Jump::Jle(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+            Jump::Jge(src_1, src_2) => {
+                Jump::Jge(src_1.resolve(c, e), src_2.resolve(c, e))
+            }
+        };
+        Node(jump, span)
+    }
+}
+
+impl Resolve for Block<Span> {
+    type Output = Block<Span, RegisterRef, ValueSource<Span>>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        self.into_iter()
+            .map(|stmt| stmt.resolve(context, errors))
+            .collect()
+    }
+}
+
+impl Resolve for SpanNode<Statement<Span>> {
+    type Output = SpanNode<ResolvedStatement<Span>>;
+
+    fn resolve(
+        self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) -> Self::Output {
+        let Node(stmt, span) = self;
+        let c = context;
+        let e = errors;
+        let stmt = match stmt {
+            Statement::Label(label) => Statement::Label(label),
+            Statement::Directive(directive) => Statement::Directive(directive),
+            Statement::Include(path) => Statement::Include(path),
+            Statement::Operator(op) => Statement::Operator(op.resolve(c, e)),
+            // A plain jump to a name that's bound by `.const` or `.alias` is
+            // really a computed jump. Symbols can't share a name with a label,
+            // so this can't shadow one.
+            Statement::Jump(Node(Jump::Jmp, _), Node(name, name_span))
+                if c.symbols.contains_key(&name) =>
+            {
+                Statement::ComputedJump(
+                    Node(
+                        ValueOperand::Symbol(Node(name, name_span)),
+                        name_span,
+                    )
+                    .resolve(c, e),
+                )
+            }
+            Statement::Jump(jump, label) => {
+                Statement::Jump(jump.resolve(c, e), label)
+            }
+            Statement::ComputedJump(target) => {
+                Statement::ComputedJump(target.resolve(c, e))
+            }
+            Statement::Call(label) => Statement::Call(label),
+            Statement::Return => Statement::Return,
+            Statement::Halt => Statement::Halt,
+            Statement::If {
+                condition,
+                body,
+                else_body,
+            } => Statement::If {
+                condition: condition.resolve(c, e),
+                body: body.resolve(c, e),
+                else_body: else_body.map(|Node(else_body, else_span)| {
+                    Node(else_body.resolve(c, e), else_span)
+                }),
+            },
+            Statement::While {
+                condition,
+                body,
+                end,
+            } => Statement::While {
+                condition: condition.resolve(c, e),
+                body: body.resolve(c, e),
+                end,
+            },
+        };
+        Node(stmt, span)
+    }
+}
+
+/// Collect all symbols defined in the program into a table, including those
+/// defined inside blocks. Returns errors for any symbols that are defined more
+/// than once.
+fn collect_symbols(
+    errors: &mut Vec<(CompileError, Span)>,
+    body: &[SpanNode<Statement<Span>>],
+) -> SymbolTable {
+    let mut symbols = SymbolTable::new();
+    add_symbols(&mut symbols, errors, body);
+    symbols
+}
+
+/// Recursive helper for [collect_symbols]
+fn add_symbols(
+    symbols: &mut SymbolTable,
+    errors: &mut Vec<(CompileError, Span)>,
+    body: &[SpanNode<Statement<Span>>],
+) {
+    for stmt in body {
+        for nested_body in stmt.value().bodies() {
+            add_symbols(symbols, errors, nested_body);
+        }
+        if let Node(Statement::Directive(Node(directive, _)), _) = stmt {
+            let (Node(name, span), binding) = match directive {
+                Directive::Const(name, Node(value, _)) => {
+                    (name, Binding::Const(*value))
+                }
+                Directive::Alias(name, Node(reg_ref, _)) => {
+                    (name, Binding::Alias(reg_ref.clone()))
+                }
+            };
+
+            if let Some((_, original_span)) = symbols.get(name) {
+                errors.push((
+                    CompileError::DuplicateSymbol {
+                        original: *original_span,
+                    },
+                    *span,
+                ));
+            } else {
+                symbols.insert(name.clone(), (binding, *span));
+            }
+        }
+    }
+}
+
+/// Check that no label has the same name as a `.const` or `.alias` symbol.
+/// Otherwise `JMP NAME` would be ambiguous. Whichever definition comes later
+/// in the source gets the error. Only global labels can clash, since local
+/// and anonymous label names can't be symbols.
+fn check_label_clashes(
+    symbols: &SymbolTable,
+    errors: &mut Vec<(CompileError, Span)>,
+    body: &[SpanNode<Statement<Span>>],
+) {
+    for stmt in body {
+        for nested_body in stmt.value().bodies() {
+            check_label_clashes(symbols, errors, nested_body);
+        }
+        if let Node(Statement::Label(Node(LabelDecl(label), span)), _) = stmt {
+            if let Some((_, symbol_span)) = symbols.get(label) {
+                let (original, span) = if (symbol_span.file, symbol_span.offset)
+                    < (span.file, span.offset)
+                {
+                    (*symbol_span, *span)
+                } else {
+                    (*span, *symbol_span)
+                };
+                errors.push((CompileError::DuplicateSymbol { original }, span));
+            }
+        }
+    }
+}
+
+/// Replaces every use of a `.const` or `.alias` symbol in the body with the
+/// constant or register that it's bound to, and folds constant expressions.
+/// Returns the resolved body, along with errors for undefined and duplicate
+/// symbols, symbols that clash with labels, and for expressions that can't be
+/// evaluated. The directives
+/// themselves are left in the body.
+pub(crate) fn resolve_symbols(
+    body: Block<Span>,
+    value_width: ValueWidth,
+) -> (ResolvedBlock<Span>, Vec<(CompileError, Span)>) {
+    let mut errors = Vec::new();
+    let symbols = collect_symbols(&mut errors, &body);
+    check_label_clashes(&symbols, &mut errors, &body);
+    let context = Context {
+        symbols,
+        value_width,
+    };
+    let body = body.resolve(&context, &mut errors);
+    (body, errors)
+}
@@ -1,14 +1,85 @@
+use crate::{error::CompileError, models::ValueWidth};
 use nom::Slice;
 use nom_locate::LocatedSpan;
 use serde::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
     fmt::{self, Formatter},
     iter,
 };
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-pub type RawSpan<'a> = LocatedSpan<&'a str>;
+/// Source code being parsed. Carries a [ParseContext] along with it.
+pub type RawSpan<'a> = LocatedSpan<&'a str, ParseContext<'a>>;
+
+/// Extra data that is carried through the parser with the source code.
+#[derive(Copy, Clone, Debug)]
+pub struct ParseContext<'a> {
+    /// The file being parsed, which gets attached to every [Span]
+    pub file: FileId,
+    /// The value width of the target hardware, so that literals can be range
+    /// checked during parsing
+    pub value_width: ValueWidth,
+    /// Syntax errors that the parser has recovered from so far. The parser
+    /// skips past each bad line, so this is the only place they're kept.
+    pub errors: &'a RefCell<Vec<(CompileError, Span)>>,
+}
+
+/// Identifies one file within a [SourceFiles]. The main source is always `0`,
+/// and included files are numbered in the order that they were loaded.
+pub type FileId = usize;
+
+/// All of the source code that makes up a program: the main source, plus the
+/// source of each file that was pulled in with `.include`. Every [Span] refers
+/// to a file in here.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SourceFiles {
+    main: String,
+    /// (path, source) for each included file
+    includes: Vec<(String, String)>,
+}
+
+impl SourceFiles {
+    /// Create a new collection, holding just the main source.
+    pub fn new(main: String) -> Self {
+        Self {
+            main,
+            includes: Vec::new(),
+        }
+    }
+
+    /// Add an included file, and return the ID that was assigned to it.
+    pub fn add_include(&mut self, path: String, source: String) -> FileId {
+        self.includes.push((path, source));
+        self.includes.len()
+    }
+
+    /// Replace the source code of a file that's already in the collection.
+    pub(crate) fn set_source(&mut self, file: FileId, source: String) {
+        match file {
+            0 => self.main = source,
+            _ => self.includes[file - 1].1 = source,
+        }
+    }
+
+    /// Get the source code of a file.
+    pub fn source(&self, file: FileId) -> &str {
+        match file {
+            0 => &self.main,
+            _ => &self.includes[file - 1].1,
+        }
+    }
+
+    /// Get the path of an included file, as it was written in the `.include`
+    /// directive. Returns `None` for the main source, which has no path.
+    pub fn path(&self, file: FileId) -> Option<&str> {
+        match file {
+            0 => None,
+            _ => Some(&self.includes[file - 1].0),
+        }
+    }
+}
 
 /// A definition of a span of source code. This doesn't actually hold the code
 /// itself (or any reference to it), it just defines parameters that can be used
@@ -29,6 +100,8 @@ pub struct Span {
     pub end_line: usize,
     /// The column that this span ends at, starting at `1`.
     pub end_col: usize,
+    /// The source file that this span is in. See [FileId].
+    pub file: FileId,
 }
 
 impl Span {
@@ -46,6 +119,7 @@ impl Span {
             start_col: start.get_column(),
             end_line: end.location_line() as usize,
             end_col: end.get_column(),
+            file: start.extra.file,
         }
     }
 
@@ -65,6 +139,7 @@ impl Span {
             end_line: line,
             // +1 so the underlining shows 1 caret
             end_col: col + 1,
+            file: raw_span.extra.file,
         }
     }
 
@@ -184,5 +259,31 @@ impl PartialEq<Span> for Span {
             && self.start_col == other.start_col
             && self.end_line == other.end_line
             && self.end_col == other.end_col
+            && self.file == other.file
+    }
+}
+
+/// A small, deterministic pseudo-random number generator (SplitMix64). `RAND`
+/// needs to give the same results every time a program runs with the same
+/// seed, so this doesn't need to be anything fancy.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a new generator. Two generators with the same seed will always
+    /// produce the same sequence of values.
+    pub fn new(seed: u32) -> Self {
+        Self { state: seed.into() }
+    }
+
+    /// Get the next pseudo-random value in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
     }
 }
@@ -1,11 +1,16 @@
 use crate::{
     ast::{
-        source::{LabelDecl, Program, Statement},
-        Jump, Label, Node, Operator, RegisterRef, SpanNode, StackId, StackRef,
-        ValueSource,
+        compiled::Program as CompiledProgram,
+        source::{
+            Block, Directive, LabelDecl, Program, ResolvedBlock,
+            ResolvedProgram, ResolvedStatement, Statement,
+        },
+        Jump, Label, Node, Operator, QueueId, QueueRef, RegisterRef, SpanNode,
+        StackId, StackOperand, StackRef, ValueSource,
     },
     error::{CompileError, SourceErrorWrapper, WithSource},
-    models::HardwareSpec,
+    models::{HardwareSpec, InstructionKind},
+    resolve::resolve_symbols,
     util::Span,
     Compiler,
 };
@@ -51,11 +56,21 @@ impl Validate for SpanNode<RegisterRef> {
             {
                 errors.push((CompileError::InvalidRegisterRef, *span))
             }
+            Node(RegisterRef::QueueLength(queue_ref), span)
+                if !is_queue_id_valid(context.hardware_spec, *queue_ref) =>
+            {
+                errors.push((CompileError::InvalidRegisterRef, *span))
+            }
             Node(RegisterRef::User(reg_ref), span) => {
                 if *reg_ref >= context.hardware_spec.num_registers {
                     errors.push((CompileError::InvalidRegisterRef, *span))
                 }
             }
+            // The index itself can only be checked at runtime, but the
+            // register that holds it has to exist
+            Node(RegisterRef::Indirect(index_reg), span) => {
+                Node((**index_reg).clone(), *span).validate(context, errors)
+            }
             _ => {}
         }
     }
@@ -91,6 +106,39 @@ impl Validate for SpanNode<StackRef> {
     }
 }
 
+impl Validate for SpanNode<StackOperand> {
+    /// Ensures a direct stack reference is in bounds, or that the index
+    /// register of an indirect reference is valid.
+    fn validate(
+        &self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) {
+        match self {
+            Node(StackOperand::Direct(stack_ref), span) => {
+                Node(*stack_ref, *span).validate(context, errors)
+            }
+            Node(StackOperand::Indirect(index_reg), span) => {
+                Node(index_reg.clone(), *span).validate(context, errors)
+            }
+        }
+    }
+}
+
+impl Validate for SpanNode<QueueRef> {
+    /// Ensures the queue ID refers to a real queue in the hardware, i.e.
+    /// makes sure it's in bounds.
+    fn validate(
+        &self,
+        context: &Context,
+        errors: &mut Vec<(CompileError, Span)>,
+    ) {
+        if !is_queue_id_valid(context.hardware_spec, self.value().0) {
+            errors.push((CompileError::InvalidQueueRef, *self.metadata()))
+        }
+    }
+}
+
 impl Validate for SpanNode<Operator<Span>> {
     fn validate(
         &self,
@@ -98,7 +146,9 @@ impl Validate for SpanNode<Operator<Span>> {
         errors: &mut Vec<(CompileError, Span)>,
     ) {
         match self.value() {
-            Operator::Read(reg_ref) => {
+            Operator::Read(reg_ref)
+            | Operator::Neg(reg_ref)
+            | Operator::Rand(reg_ref) => {
                 reg_ref.validate(context, errors);
                 validate_writable(errors, reg_ref);
             }
@@ -107,7 +157,14 @@ impl Validate for SpanNode<Operator<Span>> {
             | Operator::Add(reg_ref, val_src)
             | Operator::Sub(reg_ref, val_src)
             | Operator::Mul(reg_ref, val_src)
-            | Operator::Div(reg_ref, val_src) => {
+            | Operator::Div(reg_ref, val_src)
+            | Operator::Mod(reg_ref, val_src)
+            | Operator::And(reg_ref, val_src)
+            | Operator::Or(reg_ref, val_src)
+            | Operator::Xor(reg_ref, val_src)
+            | Operator::Shl(reg_ref, val_src)
+            | Operator::Shr(reg_ref, val_src)
+            | Operator::Load(reg_ref, val_src) => {
                 // Make sure the first reg is valid and writable, and the
                 // second is a valid value source
                 reg_ref.validate(context, errors);
@@ -124,9 +181,27 @@ impl Validate for SpanNode<Operator<Span>> {
                 val_src.validate(context, errors);
                 stack_ref.validate(context, errors);
             }
-            Operator::Pop(stack_ref, reg_ref) => {
+            Operator::Pop(stack_ref, reg_ref)
+            | Operator::Peek(stack_ref, reg_ref) => {
                 stack_ref.validate(context, errors);
                 reg_ref.validate(context, errors);
+                validate_writable(errors, reg_ref);
+            }
+            Operator::Dup(stack_ref) | Operator::Swap(stack_ref) => {
+                stack_ref.validate(context, errors);
+            }
+            Operator::Enq(val_src, queue_ref) => {
+                val_src.validate(context, errors);
+                queue_ref.validate(context, errors);
+            }
+            Operator::Deq(queue_ref, reg_ref) => {
+                queue_ref.validate(context, errors);
+                reg_ref.validate(context, errors);
+                validate_writable(errors, reg_ref);
+            }
+            Operator::Store(val_src, addr) => {
+                val_src.validate(context, errors);
+                addr.validate(context, errors);
             }
         }
     }
@@ -144,55 +219,334 @@ impl Validate for SpanNode<Jump<Span>> {
             | Jump::Jnz(val_src)
             | Jump::Jlz(val_src)
             | Jump::Jgz(val_src) => val_src.validate(context, errors),
+            Jump::Jeq(val_src_1, val_src_2)
+            | Jump::Jne(val_src_1, val_src_2)
+            | Jump::Jlt(val_src_1, val_src_2)
+            | Jump::Jgt(val_src_1, val_src_2)
+            | Jump::Jle(val_src_1, val_src_2)
+            | Jump::Jge(val_src_1, val_src_2) => {
+                val_src_1.validate(context, errors);
+                val_src_2.validate(context, errors);
+            }
         }
     }
 }
 
-impl Validate for SpanNode<Statement<Span>> {
+impl Validate for SpanNode<ResolvedStatement<Span>> {
     fn validate(
         &self,
         context: &Context,
         errors: &mut Vec<(CompileError, Span)>,
     ) {
+        // Puzzles can restrict which instructions are available
+        if let Some(kind) = instruction_kind(self.value()) {
+            if !context.hardware_spec.is_instruction_allowed(kind) {
+                errors.push((
+                    CompileError::DisallowedInstruction { name: kind.name() },
+                    *self.metadata(),
+                ));
+            }
+        }
+
         match self.value() {
-            Statement::Label(_) => {}
+            Statement::Directive(Node(Directive::Alias(_, reg_ref), _)) => {
+                reg_ref.validate(context, errors)
+            }
+            Statement::Directive(Node(Directive::Const(..), _))
+            | Statement::Label(_) => {}
             Statement::Operator(op) => op.validate(context, errors),
             Statement::Jump(jump, label) => {
                 jump.validate(context, errors);
                 label.validate(context, errors);
             }
+            Statement::ComputedJump(target) => target.validate(context, errors),
+            Statement::Call(label) => label.validate(context, errors),
+            Statement::Return | Statement::Halt => {}
+            // Includes are expanded before validation
+            Statement::Include(_) => unreachable!(),
+            Statement::If { condition, .. }
+            | Statement::While { condition, .. } => {
+                // Purist puzzles can require all control flow to be jumps
+                if !context.hardware_spec.blocks_enabled {
+                    errors.push((
+                        CompileError::DisallowedInstruction {
+                            name: block_name(self.value()),
+                        },
+                        *self.metadata(),
+                    ));
+                } else {
+                    // Blocks are lowered into jumps, so they can only be used
+                    // if those jumps are allowed too
+                    for (kind, span) in block_jump_kinds(self.value()) {
+                        if !context.hardware_spec.is_instruction_allowed(kind) {
+                            errors.push((
+                                CompileError::DisallowedInstruction {
+                                    name: kind.name(),
+                                },
+                                span,
+                            ));
+                        }
+                    }
+                }
+                condition.validate(context, errors);
+                for body in self.value().bodies() {
+                    for stmt in body {
+                        stmt.validate(context, errors);
+                    }
+                }
+            }
         }
     }
 }
 
+/// Gets the kind of instruction that a statement represents. Returns `None`
+/// for statements that aren't instructions, e.g. labels and directives.
+fn instruction_kind(stmt: &ResolvedStatement<Span>) -> Option<InstructionKind> {
+    let kind = match stmt {
+        Statement::Directive(_)
+        | Statement::Include(_)
+        | Statement::Label(_)
+        | Statement::If { .. }
+        | Statement::While { .. } => return None,
+        Statement::Operator(Node(op, _)) => match op {
+            Operator::Read(..) => InstructionKind::Read,
+            Operator::Write(..) => InstructionKind::Write,
+            Operator::Set(..) => InstructionKind::Set,
+            Operator::Add(..) => InstructionKind::Add,
+            Operator::Sub(..) => InstructionKind::Sub,
+            Operator::Mul(..) => InstructionKind::Mul,
+            Operator::Div(..) => InstructionKind::Div,
+            Operator::Mod(..) => InstructionKind::Mod,
+            Operator::Neg(..) => InstructionKind::Neg,
+            Operator::And(..) => InstructionKind::And,
+            Operator::Or(..) => InstructionKind::Or,
+            Operator::Xor(..) => InstructionKind::Xor,
+            Operator::Shl(..) => InstructionKind::Shl,
+            Operator::Shr(..) => InstructionKind::Shr,
+            Operator::Cmp(..) => InstructionKind::Cmp,
+            Operator::Rand(..) => InstructionKind::Rand,
+            Operator::Push(..) => InstructionKind::Push,
+            Operator::Pop(..) => InstructionKind::Pop,
+            Operator::Peek(..) => InstructionKind::Peek,
+            Operator::Dup(..) => InstructionKind::Dup,
+            Operator::Swap(..) => InstructionKind::Swap,
+            Operator::Enq(..) => InstructionKind::Enq,
+            Operator::Deq(..) => InstructionKind::Deq,
+            Operator::Load(..) => InstructionKind::Load,
+            Operator::Store(..) => InstructionKind::Store,
+        },
+        Statement::Jump(Node(jump, _), _) => match jump {
+            Jump::Jmp => InstructionKind::Jmp,
+            Jump::Jez(..) => InstructionKind::Jez,
+            Jump::Jnz(..) => InstructionKind::Jnz,
+            Jump::Jlz(..) => InstructionKind::Jlz,
+            Jump::Jgz(..) => InstructionKind::Jgz,
+            Jump::Jeq(..) => InstructionKind::Jeq,
+            Jump::Jne(..) => InstructionKind::Jne,
+            Jump::Jlt(..) => InstructionKind::Jlt,
+            Jump::Jgt(..) => InstructionKind::Jgt,
+            Jump::Jle(..) => InstructionKind::Jle,
+            Jump::Jge(..) => InstructionKind::Jge,
+        },
+        Statement::ComputedJump(_) => InstructionKind::Jmp,
+        Statement::Call(_) => InstructionKind::Call,
+        Statement::Return => InstructionKind::Ret,
+        Statement::Halt => InstructionKind::Halt,
+    };
+    Some(kind)
+}
+
+/// Gets the kinds of the jumps that a block statement is lowered into, along
+/// with the span that each one is generated from. The condition becomes its
+/// negated compare jump, and the `ELSE` of an `IF` or the `END` of a `WHILE`
+/// becomes a `JMP`. Nested blocks aren't included.
+fn block_jump_kinds(
+    stmt: &ResolvedStatement<Span>,
+) -> Vec<(InstructionKind, Span)> {
+    let negated_condition = |Node(condition, span): &SpanNode<Jump<Span>>| {
+        let kind = match condition {
+            Jump::Jeq(..) => InstructionKind::Jne,
+            Jump::Jne(..) => InstructionKind::Jeq,
+            Jump::Jlt(..) => InstructionKind::Jge,
+            Jump::Jge(..) => InstructionKind::Jlt,
+            Jump::Jgt(..) => InstructionKind::Jle,
+            Jump::Jle(..) => InstructionKind::Jgt,
+            // The parser only generates compare jumps for conditions
+            _ => unreachable!("Invalid block condition: {:?}", condition),
+        };
+        (kind, *span)
+    };
+
+    match stmt {
+        Statement::If {
+            condition,
+            else_body,
+            ..
+        } => {
+            let mut kinds = vec![negated_condition(condition)];
+            if let Some(Node(_, else_span)) = else_body {
+                kinds.push((InstructionKind::Jmp, *else_span));
+            }
+            kinds
+        }
+        Statement::While { condition, end, .. } => {
+            vec![negated_condition(condition), (InstructionKind::Jmp, *end)]
+        }
+        _ => unreachable!("Not a block statement: {:?}", stmt),
+    }
+}
+
+/// Gets the keyword that opens a block statement, for error messages.
+fn block_name(stmt: &ResolvedStatement<Span>) -> &'static str {
+    match stmt {
+        Statement::If { .. } => "IF",
+        Statement::While { .. } => "WHILE",
+        _ => unreachable!("Not a block statement: {:?}", stmt),
+    }
+}
+
 /// Helper method to change if a stack reference is in range. This is used for
 /// mutliple error types so the comparison logic is pulled out here.
 fn is_stack_id_valid(hardware_spec: &HardwareSpec, stack_id: StackId) -> bool {
     stack_id < hardware_spec.num_stacks
 }
 
+fn is_queue_id_valid(hardware_spec: &HardwareSpec, queue_id: QueueId) -> bool {
+    queue_id < hardware_spec.num_queues
+}
+
 /// Ensures the register reference refers to a writable register.
 fn validate_writable(
     errors: &mut Vec<(CompileError, Span)>,
     reg_ref_node: &SpanNode<RegisterRef>,
 ) {
-    // Only User registers are writable, all others cause an error.
+    // Only User registers are writable, all others cause an error
     match reg_ref_node {
-        Node(RegisterRef::Null | RegisterRef::User(_), _) => {}
-        Node(RegisterRef::InputLength | RegisterRef::StackLength(_), span) => {
-            errors.push((CompileError::UnwritableRegister, *span))
+        Node(
+            RegisterRef::Null | RegisterRef::User(_) | RegisterRef::Indirect(_),
+            _,
+        ) => {}
+        Node(
+            RegisterRef::InputLength
+            | RegisterRef::ProgramCounter
+            | RegisterRef::CycleCount
+            | RegisterRef::StackLength(_)
+            | RegisterRef::QueueLength(_),
+            span,
+        ) => errors.push((CompileError::UnwritableRegister, *span)),
+    }
+}
+
+/// Tracks label scopes while walking a program, so that local and anonymous
+/// labels can be given unique names.
+#[derive(Default)]
+struct LabelScopes {
+    /// The most recent global label, which local labels are scoped to
+    global: Label,
+    /// The number of times each anonymous label has been declared so far
+    anonymous_counts: HashMap<Label, usize>,
+}
+
+impl LabelScopes {
+    /// Get the unique name for a label declaration. Global labels keep their
+    /// name, and start a new scope for local labels.
+    fn declare(&mut self, label: &Label) -> Label {
+        if label.starts_with('.') {
+            format!("{}{}", self.global, label)
+        } else if label.chars().all(|c| c.is_ascii_digit()) {
+            let count = self.anonymous_counts.entry(label.clone()).or_insert(0);
+            *count += 1;
+            anonymous_label(label, *count - 1)
+        } else {
+            self.global = label.clone();
+            label.clone()
+        }
+    }
+
+    /// Get the unique name that a label reference refers to, based on the
+    /// current scope. If an anonymous reference has no matching declaration,
+    /// the name is left as is, so that it fails validation.
+    fn reference(&self, label: &Label) -> Label {
+        if label.starts_with('.') {
+            return format!("{}{}", self.global, label);
+        }
+
+        // Anonymous references are a number followed by `b` or `f`
+        let (i, direction) = label.char_indices().last().unwrap();
+        let number = &label[..i];
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return label.clone();
+        }
+        let count = self.anonymous_counts.get(number).copied().unwrap_or(0);
+        match direction {
+            // The most recent declaration
+            'b' if count > 0 => anonymous_label(number, count - 1),
+            // The next declaration, which may not exist
+            'f' => anonymous_label(number, count),
+            _ => label.clone(),
+        }
+    }
+
+    /// Rename all label declarations and references in the body, in source
+    /// order. This recurses into blocks.
+    fn qualify_body(&mut self, body: &mut [SpanNode<ResolvedStatement<Span>>]) {
+        for stmt in body.iter_mut() {
+            match &mut stmt.0 {
+                Statement::Label(Node(LabelDecl(label), _)) => {
+                    *label = self.declare(label);
+                }
+                Statement::Jump(_, Node(label, _))
+                | Statement::Call(Node(label, _)) => {
+                    *label = self.reference(label);
+                }
+                _ => {}
+            }
+            for nested_body in stmt.0.bodies_mut() {
+                self.qualify_body(nested_body);
+            }
         }
     }
 }
 
-/// Collect all labels in the program into a set. Returns errors for any
-/// duplicate labels.
+/// Get the unique name for the nth declaration of an anonymous label. This
+/// starts with a `#`, which can't appear in labels in the source.
+fn anonymous_label(number: &str, n: usize) -> Label {
+    format!("#{}.{}", number, n)
+}
+
+/// Give every local and anonymous label a unique name, so they can be treated
+/// like any other label from here on. A local label (e.g. `.loop`) is scoped
+/// to the most recent global label, so it's renamed to e.g. `OUTER.loop`. An
+/// anonymous label (e.g. `1`) can be declared any number of times, and is
+/// referenced with `1b` (the closest declaration before) or `1f` (the closest
+/// declaration after). The spans aren't changed, so errors still show the
+/// original source.
+fn qualify_labels(body: &mut [SpanNode<ResolvedStatement<Span>>]) {
+    LabelScopes::default().qualify_body(body);
+}
+
+/// Collect all labels in the program into a set, including those inside
+/// blocks. Returns errors for any duplicate labels. Labels must be qualified
+/// with [qualify_labels] first.
 fn collect_labels<'a>(
     errors: &mut Vec<(CompileError, Span)>,
-    body: &'a [SpanNode<Statement<Span>>],
+    body: &'a [SpanNode<ResolvedStatement<Span>>],
 ) -> HashMap<&'a Label, Span> {
     let mut labels: HashMap<&'a Label, Span> = HashMap::new();
+    add_labels(&mut labels, errors, body);
+    labels
+}
+
+/// Recursive helper for [collect_labels]
+fn add_labels<'a>(
+    labels: &mut HashMap<&'a Label, Span>,
+    errors: &mut Vec<(CompileError, Span)>,
+    body: &'a [SpanNode<ResolvedStatement<Span>>],
+) {
     for stmt in body {
+        for nested_body in stmt.value().bodies() {
+            add_labels(labels, errors, nested_body);
+        }
         if let Node(Statement::Label(Node(LabelDecl(label), span)), _) = stmt {
             // insert returns false if the value was already present
             if let Some(original_span) = labels.get(&label) {
@@ -207,16 +561,31 @@ fn collect_labels<'a>(
             }
         }
     }
-    labels
 }
 
-/// Collects all the validation errors in all the instructions in the body.
+/// Removes all directives from the body, including those inside blocks.
+fn remove_directives(body: &mut Vec<SpanNode<ResolvedStatement<Span>>>) {
+    body.retain(|stmt| match stmt.value() {
+        Statement::Directive(_) => false,
+        _ => true,
+    });
+    for stmt in body.iter_mut() {
+        for nested_body in stmt.0.bodies_mut() {
+            remove_directives(nested_body);
+        }
+    }
+}
+
+/// Resolves all symbols in the body, then collects all the validation errors
+/// in all the instructions. Returns the resolved body, along with the errors.
 fn validate_body(
     hardware_spec: &HardwareSpec,
-    body: &[SpanNode<Statement<Span>>],
-) -> Vec<(CompileError, Span)> {
-    let mut errors = Vec::new();
-    let labels = collect_labels(&mut errors, body);
+    body: Block<Span>,
+) -> (ResolvedBlock<Span>, Vec<(CompileError, Span)>) {
+    let (mut body, mut errors) =
+        resolve_symbols(body, hardware_spec.value_width);
+    qualify_labels(&mut body);
+    let labels = collect_labels(&mut errors, &body);
     let context = Context {
         hardware_spec,
         labels: &labels,
@@ -227,28 +596,65 @@ fn validate_body(
         stmt.validate(&context, &mut errors);
     }
 
-    errors
+    (body, errors)
 }
 
 impl Compiler<Program<Span>> {
     /// Performs all possible static validation on the program. The
     /// hardware is needed to determine what values and references
     /// are valid. If any errors occur, `Err` will be returned with all the
-    /// errors in a collection.
+    /// errors in a collection. Any `.const`/`.alias` symbols are resolved
+    /// here, so the returned program no longer contains any symbols or
+    /// directives.
+    ///
+    /// `errors` holds errors from earlier stages that were recovered from,
+    /// e.g. syntax errors. If there are any, validation still runs on what's
+    /// left of the program, but it will fail and report those errors first.
     pub(crate) fn validate(
         self,
-    ) -> Result<Compiler<Program<Span>>, WithSource<CompileError>> {
-        let errors = validate_body(&self.hardware_spec, &self.ast.body);
+        mut errors: Vec<SourceErrorWrapper<CompileError>>,
+    ) -> Result<Compiler<ResolvedProgram<Span>>, WithSource<CompileError>> {
+        let (mut body, validation_errors) =
+            validate_body(&self.hardware_spec, self.ast.body);
+        let sources = &self.sources;
+        errors.extend(validation_errors.into_iter().map(|(error, span)| {
+            SourceErrorWrapper::new(error, span, sources)
+        }));
+
         if errors.is_empty() {
-            Ok(self)
+            // All symbols have been resolved, so the directives are useless now
+            remove_directives(&mut body);
+            Ok(Compiler {
+                sources: self.sources,
+                hardware_spec: self.hardware_spec,
+                ast: ResolvedProgram { body },
+                warnings: self.warnings,
+            })
         } else {
-            let errors: Vec<_> = errors
-                .into_iter()
-                .map(|(error, span)| {
-                    SourceErrorWrapper::new(error, span, &self.source)
-                })
-                .collect();
-            Err(WithSource::new(errors, self.source))
+            Err(WithSource::new(errors, self.sources))
+        }
+    }
+}
+
+impl Compiler<CompiledProgram<Span>> {
+    /// Checks the compiled program against the hardware's instruction limit.
+    /// This has to run after delabelling, because labels and directives don't
+    /// count as instructions. The error points to the first instruction that
+    /// exceeds the limit.
+    pub(crate) fn check_instruction_count(
+        self,
+    ) -> Result<Self, WithSource<CompileError>> {
+        let count = self.ast.instructions.len();
+        match self.hardware_spec.max_instructions {
+            Some(max) if count > max => {
+                let error = SourceErrorWrapper::new(
+                    CompileError::TooManyInstructions { count, max },
+                    *self.ast.instructions[max].metadata(),
+                    &self.sources,
+                );
+                Err(WithSource::new(vec![error], self.sources))
+            }
+            _ => Ok(self),
         }
     }
 }
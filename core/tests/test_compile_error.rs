@@ -1,7 +1,8 @@
 //! Integration tests for GDLK that expect compile errors. The programs in
 //! these tests should all fail during compilation.
 
-use gdlk::{ast::LangValue, Compiler, HardwareSpec};
+use gdlk::{Compiler, HardwareSpec, InstructionKind, ValueWidth};
+use std::collections::HashMap;
 
 /// Compiles the program for the given hardware, expecting compile error(s).
 /// Panics if the program compiles successfully, or if the wrong set of
@@ -38,11 +39,21 @@ fn test_parse_errors() {
     assert_parse_error!(
         "
         READ RX0
-        READ RW0
+        READ 0RW
         READ RX0
         ",
         "Syntax error at 3:14: Expected register reference",
     );
+    // Anything that looks like a name is parsed as a symbol, so a typo in a
+    // register name is reported as an undefined name
+    assert_parse_error!(
+        "
+        READ RX0
+        READ RW0
+        READ RX0
+        ",
+        "Validation error at 3:14: Undefined name `RW0`",
+    );
     assert_parse_error!(
         "READ",
         "Syntax error at 1:5: Expected register reference",
@@ -50,6 +61,10 @@ fn test_parse_errors() {
     assert_parse_error!("WRITE", "Syntax error at 1:6: Expected value");
     assert_parse_error!(
         "READ RW0",
+        "Validation error at 1:6: Undefined name `RW0`"
+    );
+    assert_parse_error!(
+        "READ $RX0",
         "Syntax error at 1:6: Expected register reference"
     );
     assert_parse_error!(
@@ -67,10 +82,28 @@ fn test_parse_errors() {
         "Syntax error at 1:10: Expected stack reference",
     );
 
+    // Queue references
+    assert_parse_error!(
+        "ENQ RX0 S0",
+        "Syntax error at 1:9: Expected queue reference",
+    );
+    assert_parse_error!(
+        "DEQ RX0 Q0",
+        "Syntax error at 1:5: Expected queue reference",
+    );
+
     // Operators
     assert_parse_error!("RAD RX0", "Syntax error at 1:1: Expected statement",);
     assert_parse_error!("READE RX0", "Syntax error at 1:1: Expected statement",);
-    assert_parse_error!("PUSH STEVE S0", "Syntax error at 1:6: Expected value");
+    assert_parse_error!(
+        "PUSH STEVE S0",
+        "Validation error at 1:6: Undefined name `STEVE`\n\
+            Validation error at 1:12: Invalid reference to stack `S0`"
+    );
+    assert_parse_error!(
+        "PUSH $STEVE S0",
+        "Syntax error at 1:6: Expected value"
+    );
     assert_parse_error!(
         "READ RX1 WRITE RX2",
         "Syntax error at 1:10: Expected end of statement",
@@ -80,26 +113,231 @@ fn test_parse_errors() {
     assert_parse_error!("JMP", "Syntax error at 1:4: Expected label");
     assert_parse_error!("JEZ", "Syntax error at 1:4: Expected value");
     assert_parse_error!("JEZ RX0", "Syntax error at 1:8: Expected label");
-    assert_parse_error!("JEZ RW0 LABEL", "Syntax error at 1:5: Expected value");
+    assert_parse_error!("JEQ RX0", "Syntax error at 1:8: Expected value");
+    assert_parse_error!("JEQ RX0 1", "Syntax error at 1:10: Expected label");
+    assert_parse_error!(
+        "JEZ RW0 LABEL",
+        "Validation error at 1:5: Undefined name `RW0`\n\
+            Validation error at 1:9: Invalid reference to label `LABEL`"
+    );
+    assert_parse_error!(
+        "JEZ $RX0 LABEL",
+        "Syntax error at 1:5: Expected value"
+    );
     assert_parse_error!(
         "LABEL:JMP LABEL",
         "Syntax error at 1:7: Expected end of statement"
     );
-    // These errors aren't the best, but eh close enough
-    assert_parse_error!("JMP BAD-LABEL", "Syntax error at 1:4: Expected label");
+    // These errors aren't the best, but eh close enough. `BAD-LABEL` is a
+    // valid expression, so it's treated as a computed jump target.
+    assert_parse_error!(
+        "JMP BAD-LABEL",
+        "Validation error at 1:5: Undefined name `BAD`\n\
+            Validation error at 1:9: Undefined name `LABEL`"
+    );
     assert_parse_error!(
         "BAD-LABEL:",
         "Syntax error at 1:1: Expected statement"
     );
 
+    // Directives
+    assert_parse_error!(".const", "Syntax error at 1:7: Expected name");
+    assert_parse_error!(".const RX0 1", "Syntax error at 1:8: Expected name");
+    assert_parse_error!(".const LIMIT", "Syntax error at 1:13: Expected value");
+    assert_parse_error!(
+        ".const LIMIT RX0",
+        "Syntax error at 1:14: Expected value"
+    );
+    assert_parse_error!(
+        ".alias counter 3",
+        "Syntax error at 1:16: Expected register reference"
+    );
+    assert_parse_error!(
+        ".alias counter other",
+        "Syntax error at 1:16: Expected register reference"
+    );
+
+    // Constant expressions
+    assert_parse_error!("SET RX0 3*", "Syntax error at 1:11: Expected value");
+    assert_parse_error!(
+        "SET RX0 (3+4",
+        "Syntax error at 1:13: Expected closing parenthesis"
+    );
+    assert_parse_error!(
+        "SET RX0 3 + 4",
+        "Syntax error at 1:11: Expected end of statement"
+    );
+
     // Invalid constants (out of range)
     assert_parse_error!(
-        &format!("SET RX0 {}", LangValue::max_value() as isize + 1),
-        "Syntax error at 1:9: Expected value"
+        &format!("SET RX0 {}", i64::from(i16::max_value()) + 1),
+        "Syntax error at 1:9: Expected value between -32768 and 32767"
     );
     assert_parse_error!(
-        &format!("SET RX0 {}", LangValue::min_value() as isize - 1),
-        "Syntax error at 1:9: Expected value"
+        &format!("SET RX0 {}", i64::from(i16::min_value()) - 1),
+        "Syntax error at 1:9: Expected value between -32768 and 32767"
+    );
+    assert_parse_error!(
+        "SET RX0 99999999999999999999",
+        "Syntax error at 1:9: Expected value between -32768 and 32767"
+    );
+
+    // Malformed literals
+    assert_parse_error!("SET RX0 0x", "Syntax error at 1:9: Expected value");
+    assert_parse_error!("SET RX0 0xG", "Syntax error at 1:9: Expected value");
+    assert_parse_error!("SET RX0 0b12", "Syntax error at 1:9: Expected value");
+    assert_parse_error!(
+        "SET RX0 ''",
+        "Syntax error at 1:10: Expected character"
+    );
+    assert_parse_error!(
+        "SET RX0 'AB'",
+        "Syntax error at 1:11: Expected closing quote"
+    );
+    assert_parse_error!(
+        "SET RX0 'A",
+        "Syntax error at 1:11: Expected closing quote"
+    );
+    assert_parse_error!(
+        "SET RX0 '\\q'",
+        "Syntax error at 1:11: Expected escape sequence"
+    );
+
+    // Blocks
+    assert_parse_error!(
+        "IF RX0 == 1\nWRITE RX0",
+        "Syntax error at 2:10: Expected END"
+    );
+    assert_parse_error!(
+        "WHILE RX0 = 1\nEND",
+        "Syntax error at 1:11: Expected comparison operator"
+    );
+    assert_parse_error!(
+        "IF RX0\nEND",
+        "Syntax error at 1:7: Expected comparison operator"
+    );
+    assert_parse_error!(
+        "IF RX0 < \nEND",
+        "Syntax error at 1:10: Expected value"
+    );
+    assert_parse_error!("ELSE", "Syntax error at 1:1: Expected statement");
+}
+
+#[test]
+fn test_multiple_syntax_errors() {
+    // The parser should skip each bad line and keep going, then validation
+    // should still run on the lines that did parse
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        READ RX0
+        READ 0RW
+        WRITE RX9
+        ADD RX0
+        IF RX0 ~ 1
+            WRITE RX0
+            SET RX0
+        END
+        WHILE RX0 > 0
+            SUB RX0
+        END
+        JMP NOWHERE
+        FOO BAR
+        ",
+        &[
+            "Syntax error at 3:14: Expected register reference",
+            "Syntax error at 5:16: Expected value",
+            "Syntax error at 6:16: Expected comparison operator",
+            "Syntax error at 8:20: Expected value",
+            "Syntax error at 11:20: Expected value",
+            "Syntax error at 14:9: Expected statement",
+            "Validation error at 4:15: Invalid reference to register `RX9`",
+            "Validation error at 13:13: Invalid reference to label `NOWHERE`",
+        ],
+    );
+}
+
+#[test]
+fn test_value_width_literals() {
+    let hw_8 = HardwareSpec {
+        value_width: ValueWidth::Bits8,
+        ..Default::default()
+    };
+    let hw_32 = HardwareSpec {
+        value_width: ValueWidth::Bits32,
+        ..Default::default()
+    };
+    assert_compile_errors!(
+        hw_8.clone(),
+        "SET RX0 128",
+        &["Syntax error at 1:9: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_8.clone(),
+        "SET RX0 -129",
+        &["Syntax error at 1:9: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_8.clone(),
+        ".const BIG 200",
+        &["Syntax error at 1:12: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_8.clone(),
+        "SET RX0 0x80",
+        &["Syntax error at 1:9: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_8.clone(),
+        "SET RX0 0b100000000",
+        &["Syntax error at 1:9: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_8,
+        "SET RX0 '\u{e9}'",
+        &["Syntax error at 1:9: Expected value between -128 and 127"]
+    );
+    assert_compile_errors!(
+        hw_32.clone(),
+        &format!("SET RX0 {}", i64::from(i32::max_value()) + 1),
+        &["Syntax error at 1:9: \
+            Expected value between -2147483648 and 2147483647"]
+    );
+    assert_compile_errors!(
+        hw_32,
+        &format!("SET RX0 {}", i64::from(i32::min_value()) - 1),
+        &["Syntax error at 1:9: \
+            Expected value between -2147483648 and 2147483647"]
+    );
+}
+
+#[test]
+fn test_invalid_const_expressions() {
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        .const BIG 20000
+        .alias counter RX0
+        SET RX0 BIG*2
+        SET RX0 1+BIG*2
+        SET RX0 10/(5-5)
+        SET RX0 counter+1
+        SET RX0 FOO+1
+        .const MIN -32768
+        SET RX0 -MIN
+        ",
+        &[
+            "Validation error at 4:17: \
+                Arithmetic overflow in constant expression `BIG*2`",
+            "Validation error at 5:19: \
+                Arithmetic overflow in constant expression `BIG*2`",
+            "Validation error at 6:17: \
+                Divide by zero in constant expression `10/(5-5)`",
+            "Validation error at 7:17: Name `counter` is not a constant",
+            "Validation error at 8:17: Undefined name `FOO`",
+            "Validation error at 10:17: \
+                Arithmetic overflow in constant expression `-MIN`",
+        ],
     );
 }
 
@@ -124,6 +362,7 @@ fn test_invalid_user_reg_ref() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 5,
+            ..Default::default()
         },
         "
         READ RX1
@@ -155,6 +394,7 @@ fn test_invalid_stack_reg_ref() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 5,
+            ..Default::default()
         },
         "
         SET RX0 RS1
@@ -170,14 +410,68 @@ fn test_invalid_stack_ref() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 5,
+            ..Default::default()
         },
         "
         PUSH 5 S1
         POP S2 RX0
+        PEEK S1 RX0
+        DUP S1
+        SWAP S1
         ",
         &[
             "Validation error at 2:16: Invalid reference to stack `S1`",
             "Validation error at 3:13: Invalid reference to stack `S2`",
+            "Validation error at 4:14: Invalid reference to stack `S1`",
+            "Validation error at 5:13: Invalid reference to stack `S1`",
+            "Validation error at 6:14: Invalid reference to stack `S1`",
+        ],
+    );
+}
+
+#[test]
+fn test_invalid_indirect_ref() {
+    assert_compile_errors!(
+        HardwareSpec {
+            num_registers: 1,
+            num_stacks: 1,
+            max_stack_length: 5,
+            ..Default::default()
+        },
+        "
+        SET [RX1] 0
+        PUSH 1 S[RS1]
+        WRITE [foo]
+        ",
+        &[
+            "Validation error at 4:15: Undefined name `[foo]`",
+            "Validation error at 2:13: Invalid reference to register `[RX1]`",
+            "Validation error at 3:16: \
+                Invalid reference to register `S[RS1]`",
+        ],
+    );
+}
+
+#[test]
+fn test_invalid_queue_ref() {
+    assert_compile_errors!(
+        HardwareSpec {
+            num_queues: 1,
+            max_queue_length: 5,
+            ..Default::default()
+        },
+        "
+        ENQ 5 Q1
+        DEQ Q2 RX0
+        SET RX0 RQ3
+        SET RQ0 1
+        ",
+        &[
+            "Validation error at 2:15: Invalid reference to queue `Q1`",
+            "Validation error at 3:13: Invalid reference to queue `Q2`",
+            "Validation error at 4:17: Invalid reference to register `RQ3`",
+            "Validation error at 5:13: \
+                Cannot write to read-only register `RQ0`",
         ],
     );
 }
@@ -189,16 +483,438 @@ fn test_unwritable_reg() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 5,
+            ..Default::default()
         },
         "
         SET RLI 5
         SET RS0 5
+        NEG RLI
+        SHL RS0 1
+        LOAD RLI 0
+        SET RPC 0
+        RAND RCY
         ",
         &[
             "Validation error at 2:13: \
                 Cannot write to read-only register `RLI`",
             "Validation error at 3:13: \
                 Cannot write to read-only register `RS0`",
+            "Validation error at 4:13: \
+                Cannot write to read-only register `RLI`",
+            "Validation error at 5:13: \
+                Cannot write to read-only register `RS0`",
+            "Validation error at 6:14: \
+                Cannot write to read-only register `RLI`",
+            "Validation error at 7:13: \
+                Cannot write to read-only register `RPC`",
+            "Validation error at 8:14: \
+                Cannot write to read-only register `RCY`",
+        ],
+    );
+}
+
+#[test]
+fn test_unwritable_deq_reg() {
+    assert_compile_errors!(
+        HardwareSpec {
+            num_queues: 1,
+            max_queue_length: 5,
+            ..Default::default()
+        },
+        "
+        ENQ 1 Q0
+        DEQ Q0 RLI
+        ",
+        &["Validation error at 3:16: \
+            Cannot write to read-only register `RLI`"],
+    );
+}
+
+#[test]
+fn test_unwritable_pop_reg() {
+    assert_compile_errors!(
+        HardwareSpec {
+            num_stacks: 1,
+            max_stack_length: 5,
+            ..Default::default()
+        },
+        "
+        PUSH 1 S0
+        PEEK S0 RS0
+        POP S0 RLI
+        ",
+        &[
+            "Validation error at 3:17: \
+                Cannot write to read-only register `RS0`",
+            "Validation error at 4:16: \
+                Cannot write to read-only register `RLI`",
+        ],
+    );
+}
+
+#[test]
+fn test_disallowed_compare_jumps() {
+    assert_compile_errors!(
+        HardwareSpec {
+            compare_jumps_enabled: false,
+            ..Default::default()
+        },
+        "
+        LOOP:
+        JEZ RX0 LOOP
+        JEQ RX0 1 LOOP
+        JGE RX1 RX0 LOOP
+        ",
+        &[
+            "Validation error at 4:9: \
+                Instruction `JEQ` is not allowed on this hardware",
+            "Validation error at 5:9: \
+                Instruction `JGE` is not allowed on this hardware",
+            "Validation error at 5:13: Invalid reference to register `RX1`",
+        ],
+    );
+}
+
+#[test]
+fn test_disallowed_blocks() {
+    assert_compile_errors!(
+        HardwareSpec {
+            blocks_enabled: false,
+            ..Default::default()
+        },
+        "
+        WHILE RLI > 0
+            READ RX0
+            IF RX0 == 0
+                WRITE RX1
+            END
+        END
+        ",
+        &[
+            "Validation error at 2:9: \
+                Instruction `WHILE` is not allowed on this hardware",
+            "Validation error at 4:13: \
+                Instruction `IF` is not allowed on this hardware",
+            "Validation error at 5:23: Invalid reference to register `RX1`",
+        ],
+    );
+}
+
+#[test]
+fn test_disallowed_block_jumps() {
+    // Blocks are lowered into jumps, which have to be allowed too
+    assert_compile_errors!(
+        HardwareSpec {
+            compare_jumps_enabled: false,
+            ..Default::default()
+        },
+        "
+        WHILE RX0 > 0
+            SUB RX0 1
+        END
+        ",
+        &["Validation error at 2:15: \
+            Instruction `JLE` is not allowed on this hardware"],
+    );
+    assert_compile_errors!(
+        HardwareSpec {
+            disallowed_instructions: vec![
+                InstructionKind::Jmp,
+                InstructionKind::Jle,
+                InstructionKind::Jne,
+            ],
+            ..Default::default()
+        },
+        "
+        WHILE RX0 > 0
+            SUB RX0 1
+        END
+        IF RX0 == 0
+            WRITE 1
+        ELSE
+            WRITE 2
+        END
+        ",
+        &[
+            "Validation error at 2:15: \
+                Instruction `JLE` is not allowed on this hardware",
+            "Validation error at 4:9: \
+                Instruction `JMP` is not allowed on this hardware",
+            "Validation error at 5:12: \
+                Instruction `JNE` is not allowed on this hardware",
+            "Validation error at 7:9: \
+                Instruction `JMP` is not allowed on this hardware",
+        ],
+    );
+}
+
+#[test]
+fn test_allowed_instructions() {
+    assert_compile_errors!(
+        HardwareSpec {
+            allowed_instructions: Some(vec![
+                InstructionKind::Read,
+                InstructionKind::Write,
+                InstructionKind::Add,
+                InstructionKind::Jmp,
+            ]),
+            ..Default::default()
+        },
+        "
+        LOOP:
+        READ RX0
+        MUL RX0 2
+        ADD RX0 1
+        WRITE RX0
+        JEZ RLI END
+        JMP LOOP
+        END:
+        ",
+        &[
+            "Validation error at 4:9: \
+                Instruction `MUL` is not allowed on this hardware",
+            "Validation error at 7:9: \
+                Instruction `JEZ` is not allowed on this hardware",
+        ],
+    );
+}
+
+#[test]
+fn test_disallowed_instructions() {
+    assert_compile_errors!(
+        HardwareSpec {
+            num_stacks: 1,
+            max_stack_length: 1,
+            max_call_depth: 1,
+            disallowed_instructions: vec![
+                InstructionKind::Mul,
+                InstructionKind::Pop,
+                InstructionKind::Call,
+            ],
+            ..Default::default()
+        },
+        "
+        SUB RX0 1
+        MUL RX0 2
+        PUSH RX0 S0
+        POP S0 RX0
+        CALL SUB
+        SUB:
+        ",
+        &[
+            "Validation error at 3:9: \
+                Instruction `MUL` is not allowed on this hardware",
+            "Validation error at 5:9: \
+                Instruction `POP` is not allowed on this hardware",
+            "Validation error at 6:9: \
+                Instruction `CALL` is not allowed on this hardware",
+        ],
+    );
+}
+
+#[test]
+fn test_invalid_label() {
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        JMP FOO
+        CALL BAR
+        ",
+        &[
+            "Validation error at 2:13: Invalid reference to label `FOO`",
+            "Validation error at 3:14: Invalid reference to label `BAR`",
         ],
     );
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        .loop:
+        FOO:
+        .loop:
+        .loop:
+        JMP .nope
+        JMP 1b
+        1:
+        JMP 1f
+        BAR:
+        JMP .loop
+        JEZ RX0 1
+        ",
+        &[
+            "Validation error at 5:9: Duplicate decalaration of label \
+                `.loop:`, originally defined on line 4",
+            "Validation error at 6:13: Invalid reference to label `.nope`",
+            "Validation error at 7:13: Invalid reference to label `1b`",
+            "Validation error at 9:13: Invalid reference to label `1f`",
+            "Validation error at 11:13: Invalid reference to label `.loop`",
+            "Validation error at 12:17: Invalid reference to label `1`",
+        ],
+    );
+}
+
+#[test]
+fn test_undefined_symbol() {
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        READ RW0
+        WRITE STEVE
+        JEZ LIMIT END
+        END:
+        ",
+        &[
+            "Validation error at 2:14: Undefined name `RW0`",
+            "Validation error at 3:15: Undefined name `STEVE`",
+            "Validation error at 4:13: Undefined name `LIMIT`",
+        ],
+    );
+}
+
+#[test]
+fn test_duplicate_symbol() {
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        .const LIMIT 10
+        .alias counter RX0
+        .const counter 3
+        .alias LIMIT RX0
+        ",
+        &[
+            "Validation error at 4:16: Duplicate definition of name \
+                `counter`, originally defined on line 3",
+            "Validation error at 5:16: Duplicate definition of name \
+                `LIMIT`, originally defined on line 2",
+        ],
+    );
+
+    // Labels and symbols share a namespace, otherwise `JMP TARGET` would be
+    // ambiguous
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        .const TARGET 3
+        JMP TARGET
+        WRITE 1
+        TARGET:
+        WRITE 2
+        START:
+        .alias START RX0
+        ",
+        &[
+            "Validation error at 5:9: Duplicate definition of name \
+                `TARGET:`, originally defined on line 2",
+            "Validation error at 8:16: Duplicate definition of name \
+                `START`, originally defined on line 7",
+        ],
+    );
+}
+
+#[test]
+fn test_invalid_symbol_usage() {
+    assert_compile_errors!(
+        HardwareSpec::default(),
+        "
+        .const LIMIT 10
+        .alias counter RX1
+        .alias length RLI
+        SET LIMIT 3
+        READ counter
+        SET length 3
+        ",
+        &[
+            "Validation error at 5:13: Invalid reference to register `LIMIT`",
+            "Validation error at 3:24: Invalid reference to register `RX1`",
+            "Validation error at 6:14: Invalid reference to register `counter`",
+            "Validation error at 7:13: \
+                Cannot write to read-only register `length`",
+        ],
+    );
+}
+
+#[test]
+fn test_include_errors() {
+    let includes: HashMap<String, String> = vec![
+        ("a.gdlk".into(), ".include \"b.gdlk\"".into()),
+        ("b.gdlk".into(), "READ RX0\n.include \"a.gdlk\"".into()),
+        ("bad_syntax.gdlk".into(), "READ RX0\nREAD 0".into()),
+        ("bad_ref.gdlk".into(), "READ RX0\nWRITE RX9".into()),
+        ("lib/c.gdlk".into(), ".include \"d.gdlk\"".into()),
+        (
+            "lib/d.gdlk".into(),
+            "READ RX0\n.include \"./c.gdlk\"".into(),
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let compile = |src: &str| {
+        Compiler::compile_with_loader(
+            src.into(),
+            HardwareSpec::default(),
+            &includes,
+        )
+        .unwrap_err()
+        .to_string()
+    };
+
+    assert_eq!(
+        compile(".include \"missing.gdlk\""),
+        "Include error at 1:11: Could not load file `missing.gdlk`"
+    );
+    assert_eq!(
+        compile(".include \"a.gdlk\""),
+        "Include error at b.gdlk:2:11: Circular include of file `a.gdlk`"
+    );
+    assert_eq!(
+        compile(".include \"lib/c.gdlk\""),
+        "Include error at lib/d.gdlk:2:11: \
+            Circular include of file `./c.gdlk`"
+    );
+    assert_eq!(
+        compile(".include \"lib/missing.gdlk\""),
+        "Include error at 1:11: Could not load file `lib/missing.gdlk`"
+    );
+    assert_eq!(
+        compile(".include \"bad_syntax.gdlk\""),
+        "Syntax error at bad_syntax.gdlk:2:6: Expected register reference"
+    );
+    assert_eq!(
+        compile("WRITE RX8\n.include \"bad_ref.gdlk\""),
+        "Validation error at 1:7: Invalid reference to register `RX8`\n\
+        Validation error at bad_ref.gdlk:2:7: \
+            Invalid reference to register `RX9`"
+    );
+
+    // Includes aren't available without a loader
+    assert_parse_error!(
+        ".include \"a.gdlk\"",
+        "Include error at 1:11: Could not load file `a.gdlk`"
+    );
+    assert_parse_error!(
+        ".include a.gdlk",
+        "Syntax error at 1:10: Expected file path"
+    );
+    assert_parse_error!(
+        ".include \"a.gdlk",
+        "Syntax error at 1:17: Expected closing quote"
+    );
+}
+
+#[test]
+fn test_too_many_instructions() {
+    assert_compile_errors!(
+        HardwareSpec {
+            max_instructions: Some(2),
+            ..Default::default()
+        },
+        "
+        LOOP:
+        READ RX0
+        WRITE RX0
+        JMP LOOP
+        WRITE RX0
+        ",
+        &["Validation error at 5:9: \
+            Program has 4 instructions, but the hardware allows at most 2"],
+    );
 }
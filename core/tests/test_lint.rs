@@ -0,0 +1,245 @@
+//! Integration tests for GDLK lint warnings. The programs in these tests should
+//! all compile successfully, but with some warnings.
+
+use gdlk::{Compiler, HardwareSpec};
+
+/// Compiles the program for the given hardware, expecting it to succeed with
+/// the given warnings.
+macro_rules! assert_warnings {
+    ($hw_spec:expr, $src:expr, $expected_warnings:expr $(,)?) => {
+        let actual_warnings: Vec<String> =
+            Compiler::compile($src.into(), $hw_spec)
+                .unwrap()
+                .warnings()
+                .errors()
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect();
+        let strs: Vec<&str> =
+            actual_warnings.iter().map(String::as_str).collect();
+        assert_eq!(strs.as_slice(), $expected_warnings);
+    };
+}
+
+#[test]
+fn test_no_warnings() {
+    assert_warnings!(
+        HardwareSpec {
+            num_registers: 2,
+            num_stacks: 1,
+            ..Default::default()
+        },
+        "
+        LOOP:
+            JEZ RLI END
+            READ RX0
+            PUSH RX0 S0
+            POP S0 RX1
+            WRITE RX1
+            JMP LOOP
+        END:
+        ",
+        &[] as &[&str],
+    );
+}
+
+#[test]
+fn test_unreachable_code() {
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        LOOP:
+        JEZ RLI 1f
+        READ RX0
+        WRITE RX0
+        JMP LOOP
+        WRITE RX0
+        WRITE RX0
+        1:
+        IF RX0 == 0
+            HALT
+            WRITE 1
+        END
+        RET
+        JMP LOOP
+        ",
+        &[
+            "Lint warning at 7:9: Unreachable code",
+            "Lint warning at 12:13: Unreachable code",
+            "Lint warning at 15:9: Unreachable code",
+        ],
+    );
+
+    // A computed jump to a register could land on the next statement
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        SET RX0 2
+        JMP RX0
+        HALT
+        ",
+        &[] as &[&str],
+    );
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        JMP 2
+        HALT
+        ",
+        &["Lint warning at 3:9: Unreachable code"],
+    );
+}
+
+#[test]
+fn test_unused_labels() {
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        START:
+        SUB:
+        .loop:
+            CALL SUB
+            JMP .loop
+        .unused:
+        1:
+        2:
+            JMP 2b
+        ",
+        &[
+            "Lint warning at 2:9: Label `START` is never used",
+            "Lint warning at 7:9: Label `.unused` is never used",
+            "Lint warning at 8:9: Label `1` is never used",
+        ],
+    );
+
+    // A global label is used if one of its local labels is
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        MAIN:
+        .loop:
+            READ RX0
+            WRITE RX0
+            JGT RLI 0 .loop
+        ",
+        &[] as &[&str],
+    );
+}
+
+#[test]
+fn test_dead_stores() {
+    assert_warnings!(
+        HardwareSpec {
+            num_registers: 4,
+            ..Default::default()
+        },
+        "
+        READ RX0
+        SET RX1 RX0
+        ADD RX2 1
+        SET RX3 5
+        CMP RX3 RX0 RX2
+        ",
+        &[
+            "Lint warning at 3:13: Value written to `RX1` is never read",
+            "Lint warning at 5:13: Value written to `RX3` is never read",
+            "Lint warning at 6:13: Value written to `RX3` is never read",
+        ],
+    );
+
+    // An indirect read could read any register
+    assert_warnings!(
+        HardwareSpec {
+            num_registers: 3,
+            ..Default::default()
+        },
+        "
+        SET RX1 1
+        SET RX2 2
+        WRITE [RX0]
+        ",
+        &[] as &[&str],
+    );
+}
+
+#[test]
+fn test_read_into_null() {
+    assert_warnings!(
+        HardwareSpec::default(),
+        "
+        READ RZR
+        READ RX0
+        WRITE RX0
+        ",
+        &["Lint warning at 2:14: Input read into `RZR` is thrown away"],
+    );
+}
+
+#[test]
+fn test_unpopped_pushes() {
+    assert_warnings!(
+        HardwareSpec {
+            num_stacks: 2,
+            ..Default::default()
+        },
+        "
+        PUSH 1 S0
+        PUSH 2 S1
+        PUSH 3 S[RX0]
+        POP S1 RZR
+        ",
+        &["Lint warning at 2:16: Value pushed onto `S0` is never popped"],
+    );
+
+    // With no pops at all, indirect pushes can't be popped either
+    assert_warnings!(
+        HardwareSpec {
+            num_stacks: 1,
+            ..Default::default()
+        },
+        "
+        PUSH 1 S[RX0]
+        ",
+        &["Lint warning at 2:16: Value pushed onto `S[RX0]` is never popped"],
+    );
+
+    // An indirect pop could pop any stack
+    assert_warnings!(
+        HardwareSpec {
+            num_stacks: 2,
+            ..Default::default()
+        },
+        "
+        PUSH 1 S0
+        POP S[RX0] RZR
+        ",
+        &[] as &[&str],
+    );
+}
+
+#[test]
+fn test_warning_source_highlight() {
+    let compiler = Compiler::compile(
+        "READ RZR\nHALT\nWRITE 1".into(),
+        HardwareSpec::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        format!("{:#}", compiler.warnings()),
+        concat!(
+            "Lint warning at 1:6: Input read into `RZR` is thrown away\n",
+            "    | \n",
+            "  1 | READ RZR\n",
+            "    |      ^^^\n",
+            "  2 | HALT\n",
+            "    | \n",
+            "\n",
+            "Lint warning at 3:1: Unreachable code\n",
+            "    | \n",
+            "  2 | HALT\n",
+            "  3 | WRITE 1\n",
+            "    | ^^^^^^^\n",
+            "    | \n",
+        )
+    );
+}
@@ -1,7 +1,8 @@
 //! Integration tests for GDLK that expect compile errors. The programs in
 //! these tests should all fail during execution.
 
-use gdlk::{Compiler, HardwareSpec, ProgramSpec};
+use gdlk::{Compiler, HardwareSpec, OverflowMode, ProgramSpec, ValueWidth};
+use std::collections::HashMap;
 
 /// Compiles the program for the given hardware, executes it under the given
 /// program spec, and expects a runtime error. Panics if the program executes
@@ -11,7 +12,8 @@ macro_rules! assert_runtime_error {
         // Compile from hardware+src
         let mut machine = Compiler::compile($src.into(), $hw_spec)
             .unwrap()
-            .allocate(&($program_spec));
+            .allocate(&($program_spec))
+            .unwrap();
 
         // Execute to completion
         let actual_error = machine.execute_all().unwrap_err();
@@ -33,6 +35,64 @@ fn test_divide_by_zero() {
     );
 }
 
+#[test]
+fn test_mod_by_zero() {
+    assert_runtime_error!(
+        HardwareSpec::default(),
+        ProgramSpec::default(),
+        "
+        SET RX0 1
+        MOD RX0 RZR
+        ",
+        "Runtime error at 3:9: Divide by zero",
+    );
+}
+
+#[test]
+fn test_overflow_trapping() {
+    let hardware_spec = HardwareSpec {
+        value_width: ValueWidth::Bits8,
+        overflow_mode: OverflowMode::Trapping,
+        ..Default::default()
+    };
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "
+        SET RX0 127
+        ADD RX0 1
+        ",
+        "Runtime error at 3:9: Arithmetic overflow in `ADD RX0 1`",
+    );
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "
+        SET RX0 -128
+        SUB RX0 1
+        ",
+        "Runtime error at 3:9: Arithmetic overflow in `SUB RX0 1`",
+    );
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "
+        SET RX0 -128
+        DIV RX0 -1
+        ",
+        "Runtime error at 3:9: Arithmetic overflow in `DIV RX0 -1`",
+    );
+    assert_runtime_error!(
+        hardware_spec,
+        ProgramSpec::default(),
+        "
+        SET RX0 -128
+        NEG RX0
+        ",
+        "Runtime error at 3:9: Arithmetic overflow in `NEG RX0`",
+    );
+}
+
 #[test]
 fn test_stack_overflow() {
     assert_runtime_error!(
@@ -40,6 +100,7 @@ fn test_stack_overflow() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 3,
+            ..Default::default()
         },
         ProgramSpec::default(),
         "
@@ -53,6 +114,87 @@ fn test_stack_overflow() {
     );
 }
 
+#[test]
+fn test_stack_lengths_overflow() {
+    assert_runtime_error!(
+        HardwareSpec {
+            num_stacks: 2,
+            max_stack_length: 3,
+            stack_lengths: vec![1],
+            ..Default::default()
+        },
+        ProgramSpec::default(),
+        "
+        PUSH 1 S1
+        PUSH 2 S1
+        PUSH 1 S0
+        DUP S0
+        ",
+        "Runtime error at 5:13: Overflow on stack `S0`",
+    );
+}
+
+#[test]
+fn test_stack_underflow() {
+    let hardware_spec = HardwareSpec {
+        num_stacks: 1,
+        max_stack_length: 3,
+        ..Default::default()
+    };
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "PEEK S0 RX0",
+        "Runtime error at 1:6: Not enough values on stack `S0`",
+    );
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "DUP S0",
+        "Runtime error at 1:5: Not enough values on stack `S0`",
+    );
+    assert_runtime_error!(
+        hardware_spec,
+        ProgramSpec::default(),
+        "
+        PUSH 1 S0
+        SWAP S0
+        ",
+        "Runtime error at 3:14: Not enough values on stack `S0`",
+    );
+}
+
+#[test]
+fn test_queue_overflow() {
+    assert_runtime_error!(
+        HardwareSpec {
+            num_queues: 1,
+            max_queue_length: 1,
+            ..Default::default()
+        },
+        ProgramSpec::default(),
+        "
+        ENQ 1 Q0
+        ENQ 2 Q0
+        ",
+        "Runtime error at 3:15: Overflow on queue `Q0`",
+    );
+}
+
+#[test]
+fn test_empty_queue() {
+    assert_runtime_error!(
+        HardwareSpec {
+            num_queues: 1,
+            max_queue_length: 1,
+            ..Default::default()
+        },
+        ProgramSpec::default(),
+        "DEQ Q0 RX0",
+        "Runtime error at 1:5: Cannot dequeue from empty queue `Q0`",
+    );
+}
+
 #[test]
 fn test_empty_input() {
     assert_runtime_error!(
@@ -70,6 +212,7 @@ fn test_empty_stack() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 3,
+            ..Default::default()
         },
         ProgramSpec::default(),
         "POP S0 RX0",
@@ -91,6 +234,189 @@ fn test_exceed_max_cycle_count() {
     );
 }
 
+#[test]
+fn test_exceed_hardware_max_cycles() {
+    assert_runtime_error!(
+        HardwareSpec {
+            max_cycles: 3,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1], vec![1]),
+        "
+        READ RX0
+        ADD RX0 0
+        ADD RX0 0
+        WRITE RX0
+        ",
+        "Runtime error at 5:9: Maximum number of cycles reached, \
+            cannot execute instruction `WRITE RX0`",
+    );
+}
+
+#[test]
+fn test_call_stack_overflow() {
+    assert_runtime_error!(
+        HardwareSpec {
+            max_call_depth: 2,
+            ..Default::default()
+        },
+        ProgramSpec::default(),
+        "
+        RECURSE:
+        CALL RECURSE
+        ",
+        "Runtime error at 3:9: Maximum call depth reached, \
+            cannot execute `CALL RECURSE`",
+    );
+}
+
+#[test]
+fn test_empty_call_stack() {
+    assert_runtime_error!(
+        HardwareSpec::default(),
+        ProgramSpec::default(),
+        "RET",
+        "Runtime error at 1:1: Cannot return from outside of a subroutine",
+    );
+}
+
+#[test]
+fn test_invalid_indirect_index() {
+    let hardware_spec = HardwareSpec {
+        num_registers: 2,
+        num_stacks: 1,
+        max_stack_length: 1,
+        ..Default::default()
+    };
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "
+        SET RX0 2
+        SET [RX0] 1
+        ",
+        "Runtime error at 3:13: Register index 2 is out of range in `[RX0]`",
+    );
+    assert_runtime_error!(
+        hardware_spec,
+        ProgramSpec::default(),
+        "
+        SET RX0 -1
+        PUSH 1 S[RX0]
+        ",
+        "Runtime error at 3:16: Stack index -1 is out of range in `S[RX0]`",
+    );
+}
+
+#[test]
+fn test_invalid_jump_target() {
+    assert_runtime_error!(
+        HardwareSpec::default(),
+        ProgramSpec::default(),
+        "JMP -1",
+        "Runtime error at 1:1: Jump target -1 is outside of the program, \
+            cannot execute `JMP -1`",
+    );
+    assert_runtime_error!(
+        HardwareSpec::default(),
+        ProgramSpec::default(),
+        "
+        SET RX0 RPC
+        ADD RX0 3
+        JMP RX0
+        ",
+        "Runtime error at 4:9: Jump target 3 is outside of the program, \
+            cannot execute `JMP RX0`",
+    );
+}
+
+#[test]
+fn test_memory_out_of_bounds() {
+    let hardware_spec = HardwareSpec {
+        memory_size: 4,
+        ..Default::default()
+    };
+    assert_runtime_error!(
+        hardware_spec.clone(),
+        ProgramSpec::default(),
+        "STORE 1 4",
+        "Runtime error at 1:1: Memory address 4 is out of bounds, \
+            cannot execute `STORE 1 4`",
+    );
+    assert_runtime_error!(
+        hardware_spec,
+        ProgramSpec::default(),
+        "
+        SET RX0 -1
+        LOAD RX0 RX0
+        ",
+        "Runtime error at 3:9: Memory address -1 is out of bounds, \
+            cannot execute `LOAD RX0 RX0`",
+    );
+    assert_runtime_error!(
+        HardwareSpec::default(),
+        ProgramSpec::default(),
+        "LOAD RX0 0",
+        "Runtime error at 1:1: Memory address 0 is out of bounds, \
+            cannot execute `LOAD RX0 0`",
+    );
+}
+
+#[test]
+fn test_program_spec_out_of_range() {
+    // The program spec has to fit in the hardware's value width before the
+    // program can run at all
+    let error = Compiler::compile(
+        "READ RX0".into(),
+        HardwareSpec {
+            value_width: ValueWidth::Bits8,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .allocate(&ProgramSpec::new(vec![200], vec![]))
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Value 200 in program spec is out of range for 8-bit values"
+    );
+}
+
+#[test]
+fn test_error_in_include() {
+    // The error should point to, and render, the included file
+    let includes: HashMap<String, String> =
+        vec![("lib.gdlk".into(), "WRITE 1\nREAD RX0\n".into())]
+            .into_iter()
+            .collect();
+    let mut machine = Compiler::compile_with_loader(
+        "SET RX0 1\n.include \"lib.gdlk\"".into(),
+        HardwareSpec::default(),
+        &includes,
+    )
+    .unwrap()
+    .allocate(&ProgramSpec::default())
+    .unwrap();
+
+    let actual_error = machine.execute_all().unwrap_err();
+    assert_eq!(
+        actual_error.to_string(),
+        "Runtime error at lib.gdlk:2:1: Read attempted while input is empty"
+    );
+    assert_eq!(
+        format!("{:#}", actual_error),
+        concat!(
+            "Runtime error at lib.gdlk:2:1: \
+                Read attempted while input is empty\n",
+            "    | \n",
+            "  1 | WRITE 1\n",
+            "  2 | READ RX0\n",
+            "    | ^^^^^^^^\n",
+            "    | \n",
+        )
+    );
+}
+
 #[test]
 fn test_execute_after_error() {
     // Excuting after an error returns false
@@ -2,7 +2,11 @@
 //! these tests should compile successfully, and execute with a successful
 //! outcome.
 
-use gdlk::{ast::LangValue, Compiler, HardwareSpec, ProgramSpec};
+use gdlk::{
+    ast::{LangValue, QueueRef, RegisterRef, StackRef},
+    Compiler, HardwareSpec, OverflowMode, ProgramSpec, ValueWidth,
+};
+use std::collections::HashMap;
 
 /// Compiles the program for the given hardware, and executes it against the
 /// program spec. Panics if the compile fails or the execution isn't
@@ -13,7 +17,8 @@ macro_rules! assert_success {
         // Compile from hardware+src
         let mut machine = Compiler::compile($src.into(), $hardware_spec)
             .unwrap()
-            .allocate(program_spec_val);
+            .allocate(program_spec_val)
+            .unwrap();
 
         // Execute to completion
         let success = machine.execute_all().unwrap();
@@ -50,6 +55,7 @@ fn test_read_write() {
             num_registers: 1,
             num_stacks: 0,
             max_stack_length: 0,
+            ..Default::default()
         },
         ProgramSpec::new(vec![1, 2], vec![1, 2]),
         "
@@ -68,6 +74,7 @@ fn test_set_push_pop() {
             num_registers: 2,
             num_stacks: 1,
             max_stack_length: 5,
+            ..Default::default()
         },
         ProgramSpec::new(vec![], vec![10, 5]),
         "
@@ -90,6 +97,7 @@ fn test_add_sub_mul() {
             num_registers: 2,
             num_stacks: 0,
             max_stack_length: 0,
+            ..Default::default()
         },
         ProgramSpec::new(vec![], vec![-3, 140]),
         "
@@ -143,6 +151,162 @@ fn test_div() {
     );
 }
 
+#[test]
+fn test_mod_neg() {
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![2, -2, 2, 0, -5, 5, -32768]),
+        "
+        SET RX0 11
+        MOD RX0 3
+        WRITE RX0 ; 2
+
+        SET RX0 -11
+        MOD RX0 3
+        WRITE RX0 ; -2 (same sign as the dividend)
+
+        SET RX0 11
+        MOD RX0 -3
+        WRITE RX0 ; 2
+
+        SET RX0 -32768
+        MOD RX0 -1
+        WRITE RX0 ; 0
+
+        SET RX0 5
+        NEG RX0
+        WRITE RX0 ; -5
+        NEG RX0
+        WRITE RX0 ; 5
+
+        SET RX0 -32768
+        NEG RX0
+        WRITE RX0 ; -32768 (wraps)
+
+        ; mod by zero test lives with the runtime error tests
+        ",
+    );
+}
+
+#[test]
+fn test_bitwise() {
+    assert_success!(
+        HardwareSpec {
+            num_registers: 2,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![8, 14, 6, -1, 40, 1, -3, 0, 4]),
+        "
+        SET RX1 12
+        SET RX0 10
+        AND RX0 RX1
+        WRITE RX0 ; 8
+        SET RX0 10
+        OR RX0 RX1
+        WRITE RX0 ; 14
+        SET RX0 10
+        XOR RX0 RX1
+        WRITE RX0 ; 6
+        SET RX0 0
+        XOR RX0 -1
+        WRITE RX0 ; -1
+
+        SET RX0 5
+        SHL RX0 3
+        WRITE RX0 ; 40
+        SHR RX0 5
+        WRITE RX0 ; 1
+        SET RX0 -6
+        SHR RX0 1
+        WRITE RX0 ; -3 (sign is preserved)
+        SET RX0 1
+        SHL RX0 15
+        SHL RX0 1
+        WRITE RX0 ; 0 (bits shifted off the end)
+        SET RX0 1
+        SHL RX0 18
+        WRITE RX0 ; 4 (shift amount wraps)
+        ",
+    );
+}
+
+#[test]
+fn test_value_widths() {
+    assert_success!(
+        HardwareSpec {
+            value_width: ValueWidth::Bits8,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![-128, 127, 0, -128, 64]),
+        "
+        SET RX0 127
+        ADD RX0 1
+        WRITE RX0 ; -128 (wraps)
+        SUB RX0 1
+        WRITE RX0 ; 127 (wraps)
+        MUL RX0 2
+        ADD RX0 2
+        WRITE RX0 ; 0
+        SET RX0 -128
+        NEG RX0
+        WRITE RX0 ; -128 (wraps)
+        SET RX0 1
+        SHL RX0 6
+        WRITE RX0 ; 64
+        ",
+    );
+    assert_success!(
+        HardwareSpec {
+            value_width: ValueWidth::Bits32,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![40000, -2147483648, 65536]),
+        "
+        SET RX0 20000
+        MUL RX0 2
+        WRITE RX0 ; 40000
+        SET RX0 2147483647
+        ADD RX0 1
+        WRITE RX0 ; -2147483648 (wraps)
+        SET RX0 1
+        SHL RX0 16
+        WRITE RX0 ; 65536
+        ",
+    );
+}
+
+#[test]
+fn test_overflow_saturating() {
+    assert_success!(
+        HardwareSpec {
+            value_width: ValueWidth::Bits8,
+            overflow_mode: OverflowMode::Saturating,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![127, -128, 127, 127, 127, 0]),
+        "
+        SET RX0 100
+        ADD RX0 100
+        WRITE RX0 ; 127
+        SET RX0 -100
+        SUB RX0 100
+        WRITE RX0 ; -128
+        MUL RX0 -1
+        WRITE RX0 ; 127
+        SET RX0 -128
+        NEG RX0
+        WRITE RX0 ; 127
+        SET RX0 -128
+        DIV RX0 -1
+        WRITE RX0 ; 127
+        SET RX0 1
+        SHL RX0 7
+        SHL RX0 1
+        WRITE RX0 ; 0 (shifts always discard bits)
+        ",
+    );
+}
+
 #[test]
 fn test_cmp() {
     assert_success!(
@@ -150,6 +314,7 @@ fn test_cmp() {
             num_registers: 2,
             num_stacks: 0,
             max_stack_length: 0,
+            ..Default::default()
         },
         ProgramSpec::new(vec![], vec![-1, 0, 1, 1]),
         "
@@ -232,6 +397,573 @@ fn test_jumps() {
     );
 }
 
+#[test]
+fn test_local_and_anonymous_labels() {
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![2, 3], vec![2, 1, 3, 2]),
+        "
+        FIRST:
+            READ RX0
+        .loop:
+            JEZ RX0 .done
+            WRITE RX0
+            SUB RX0 1
+            JMP .loop
+        .done:
+
+        SECOND:
+            READ RX0
+        1:
+            JEQ RX0 1 1f
+            WRITE RX0
+            SUB RX0 1
+            JMP 1b
+        1:
+            JMP .done
+            WRITE -1
+        .done:
+        ",
+    );
+}
+
+#[test]
+fn test_compare_jumps() {
+    let program_spec = ProgramSpec::new(vec![], vec![1]);
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JEQ 2 2 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JEQ 2 3 BAD
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JNE 2 3 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JNE 3 3 BAD
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JLT -1 0 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JLT 0 0 BAD
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JGT 1 0 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JGT 0 0 BAD
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JLE 0 0 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JLE 1 0 BAD
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        program_spec,
+        "
+        JGE 0 0 GOOD
+        BAD:
+        WRITE -1
+        GOOD:
+        WRITE 1
+        JGE 0 1 BAD
+        ",
+    );
+    // Compare against registers
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![0, 1, 2]),
+        "
+        LOOP:
+        WRITE RX0
+        ADD RX0 1
+        JLT RX0 3 LOOP
+        ",
+    );
+}
+
+#[test]
+fn test_computed_jump() {
+    // Use a jump table to map 0/1/2 to 10/20/30
+    for (input, output) in &[(0, 10), (1, 20), (2, 30)] {
+        assert_success!(
+            HardwareSpec::default(),
+            ProgramSpec::new(vec![*input], vec![*output]),
+            "
+            READ RX0    ; 0
+            MUL RX0 2   ; 1
+            ADD RX0 RPC ; 2
+            ADD RX0 3   ; 3
+            JMP RX0     ; 4
+            WRITE 10    ; 5
+            HALT        ; 6
+            WRITE 20    ; 7
+            HALT        ; 8
+            WRITE 30    ; 9
+            ",
+        );
+    }
+}
+
+#[test]
+fn test_computed_jump_symbols() {
+    // A jump to a constant, alias or expression is a computed jump
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![1]),
+        "
+        .const T 2
+        JMP T       ; 0
+        WRITE -1    ; 1
+        WRITE 1     ; 2
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![1]),
+        "
+        .alias A RX0
+        SET A 3     ; 0
+        JMP A       ; 1
+        WRITE -1    ; 2
+        WRITE 1     ; 3
+        ",
+    );
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![1]),
+        "
+        JMP 1+1     ; 0
+        WRITE -1    ; 1
+        WRITE 1     ; 2
+        ",
+    );
+}
+
+#[test]
+fn test_call_ret() {
+    assert_success!(
+        HardwareSpec {
+            num_registers: 1,
+            num_stacks: 0,
+            max_stack_length: 0,
+            max_call_depth: 2,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1, 2], vec![4, 8]),
+        "
+        READ RX0
+        CALL QUADRUPLE
+        WRITE RX0
+        READ RX0
+        CALL QUADRUPLE
+        WRITE RX0
+        JMP END
+
+        QUADRUPLE:
+            CALL DOUBLE
+            CALL DOUBLE
+            RET
+        DOUBLE:
+            ADD RX0 RX0
+            RET
+        END:
+        ",
+    );
+}
+
+#[test]
+fn test_halt() {
+    // HALT ends the program right away, just like running off the end
+    let mut machine = assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![1, 2, 3], vec![1, 2, 3]),
+        "
+        LOOP:
+        JEZ RLI DONE
+        READ RX0
+        WRITE RX0
+        JMP LOOP
+        DONE:
+        HALT
+        WRITE RX0 ; never executed
+        ",
+    );
+    assert!(machine.terminated());
+    assert_eq!(machine.program_counter(), machine.instruction_count());
+    assert_eq!(machine.cycle_count(), 14);
+    assert_eq!(machine.execute_next().unwrap(), false);
+}
+
+#[test]
+fn test_indirect() {
+    let machine = assert_success!(
+        HardwareSpec {
+            num_registers: 3,
+            num_stacks: 2,
+            max_stack_length: 2,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1, 0], vec![10, 10, 0, 0]),
+        "
+        READ RX0            ; RX0 = 1
+        SET [RX0] 5         ; RX1 = 5
+        ADD [RX0] [RX0]     ; RX1 = 10
+        PUSH [RX0] S[RX0]   ; S1 = [10]
+        READ RX2            ; RX2 = 0
+        PUSH 3 S[RX2]       ; S0 = [3]
+        POP S[RX0] RX2      ; RX2 = 10
+        WRITE RX1
+        WRITE RX2
+        WRITE RS1
+        SET RX0 0
+        POP S[[RZR]] [RZR]  ; RX0 = 3
+        WRITE RS0
+        ",
+    );
+    assert_eq!(machine.registers()[&RegisterRef::User(0)], 3);
+}
+
+#[test]
+fn test_peek_dup_swap() {
+    let machine = assert_success!(
+        HardwareSpec {
+            num_registers: 1,
+            num_stacks: 1,
+            max_stack_length: 3,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1, 2], vec![2, 2, 1]),
+        "
+        READ RX0
+        PUSH RX0 S0
+        READ RX0
+        PUSH RX0 S0
+        PEEK S0 RX0
+        WRITE RX0
+        DUP S0
+        POP S0 RX0
+        WRITE RX0
+        SWAP S0
+        PEEK S0 RX0
+        WRITE RX0
+        ",
+    );
+    assert_eq!(machine.stacks()[&StackRef(0)], &[2, 1]);
+}
+
+#[test]
+fn test_stack_lengths() {
+    // Each stack can have its own capacity
+    assert_success!(
+        HardwareSpec {
+            num_registers: 1,
+            num_stacks: 2,
+            max_stack_length: 1,
+            stack_lengths: vec![3],
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![]),
+        "
+        PUSH 1 S0
+        PUSH 2 S0
+        PUSH 3 S0
+        PUSH 1 S1
+        ",
+    );
+}
+
+#[test]
+fn test_queues() {
+    // Rotate the input: move the first value to the end
+    let machine = assert_success!(
+        HardwareSpec {
+            num_registers: 1,
+            num_queues: 1,
+            max_queue_length: 3,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1, 2, 3], vec![2, 3, 1]),
+        "
+        READ_LOOP:
+        JEZ RLI ROTATE
+        READ RX0
+        ENQ RX0 Q0
+        JMP READ_LOOP
+
+        ROTATE:
+        DEQ Q0 RX0
+        ENQ RX0 Q0
+
+        WRITE_LOOP:
+        JEZ RQ0 END
+        DEQ Q0 RX0
+        WRITE RX0
+        JMP WRITE_LOOP
+        END:
+        ",
+    );
+    assert_eq!(machine.queues()[&QueueRef(0)], &[] as &[LangValue]);
+}
+
+#[test]
+fn test_load_store() {
+    // Reverse the input by storing it in memory, then reading it back out
+    let machine = assert_success!(
+        HardwareSpec {
+            num_registers: 2,
+            memory_size: 4,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1, 2, 3], vec![3, 2, 1]),
+        "
+        READ_LOOP:
+        JEZ RLI WRITE_LOOP
+        READ RX0
+        STORE RX0 RX1
+        ADD RX1 1
+        JMP READ_LOOP
+
+        WRITE_LOOP:
+        JEZ RX1 END
+        SUB RX1 1
+        LOAD RX0 RX1
+        WRITE RX0
+        JMP WRITE_LOOP
+        END:
+        ",
+    );
+    assert_eq!(machine.memory(), &[1, 2, 3, 0]);
+}
+
+#[test]
+fn test_literals() {
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![], vec![65, 65, 65, -16, 10, 39, 32, 92]),
+        "
+        .const NEWLINE '\\n'
+        WRITE 'A'
+        WRITE 0x41
+        WRITE 0b1000001
+        WRITE -0x10
+        WRITE NEWLINE
+        WRITE '\\''
+        WRITE ' '   ; spaces and semicolons are fine in quotes
+        JMP 0_SKIP  ; labels can still start with a digit
+        WRITE ';'
+        0_SKIP:
+        WRITE '\\\\'
+        ",
+    );
+}
+
+#[test]
+fn test_const_alias() {
+    assert_success!(
+        HardwareSpec {
+            num_registers: 2,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![6]),
+        "
+        .const LIMIT 3
+        .alias counter RX0
+        .alias total RX1
+
+        SET counter LIMIT
+        LOOP:
+            ADD total counter
+            SUB counter 1
+            JGZ counter LOOP
+        WRITE total
+        ",
+    );
+}
+
+#[test]
+fn test_const_expressions() {
+    assert_success!(
+        HardwareSpec {
+            num_registers: 1,
+            num_stacks: 1,
+            max_stack_length: 1,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![], vec![22, -36, 3, -1, 17, 9, -10, -3, 2]),
+        "
+        .const LIMIT 10
+        .alias counter RX0
+
+        SET counter LIMIT-1
+        ADD counter 3*4+1
+        WRITE counter
+        WRITE (LIMIT+2)*-3
+        WRITE 7/2
+        WRITE -7%3
+        WRITE 0x10-'A'+'B'
+        PUSH LIMIT-1 S0
+        POP S0 counter
+        WRITE counter
+        SET RX0 -LIMIT
+        WRITE RX0
+        SET RX0 -(3)
+        WRITE RX0
+        WRITE -(LIMIT-12)
+        ",
+    );
+}
+
+#[test]
+fn test_blocks() {
+    let machine = assert_success!(
+        HardwareSpec {
+            num_registers: 2,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![3, -2, 0], vec![3, 2, 1, 0, -1, 0, 0]),
+        "
+        WHILE RLI > 0
+            READ RX0
+            IF RX0 >= 0
+                WHILE RX0 != 0
+                    WRITE RX0
+                    SUB RX0 1
+                END
+                WRITE 0
+            ELSE
+                IF RX0 == -2
+                    WRITE -1
+                END
+            END
+        END
+        WRITE 0
+        ",
+    );
+    assert_eq!(machine.cycle_count(), 34);
+}
+
+#[test]
+fn test_include() {
+    let includes: HashMap<String, String> = vec![
+        (
+            "math.gdlk".into(),
+            "
+            .include \"consts.gdlk\"
+            TRIPLE:
+                MUL RX0 FACTOR
+                RET
+            "
+            .into(),
+        ),
+        ("consts.gdlk".into(), ".const FACTOR 3".into()),
+    ]
+    .into_iter()
+    .collect();
+    let program_spec = ProgramSpec::new(vec![1, 2], vec![3, 6]);
+    let mut machine = Compiler::compile_with_loader(
+        "
+        WHILE RLI > 0
+            READ RX0
+            CALL TRIPLE
+            WRITE RX0
+        END
+        HALT
+        .include \"math.gdlk\"
+        "
+        .into(),
+        HardwareSpec {
+            max_call_depth: 1,
+            ..Default::default()
+        },
+        &includes,
+    )
+    .unwrap()
+    .allocate(&program_spec)
+    .unwrap();
+
+    assert!(machine.execute_all().unwrap());
+    assert_eq!(machine.output(), program_spec.expected_output());
+}
+
+#[test]
+fn test_nested_include() {
+    // Nested includes are relative to the including file, and a file that's
+    // included twice is only expanded once
+    let includes: HashMap<String, String> = vec![
+        (
+            "lib/a.gdlk".into(),
+            ".include \"b.gdlk\"\n.include \"../common.gdlk\"".into(),
+        ),
+        (
+            "lib/b.gdlk".into(),
+            ".include \"./../common.gdlk\"\nDOUBLE:\nADD RX0 RX0\nRET".into(),
+        ),
+        ("common.gdlk".into(), "INC:\nADD RX0 1\nRET".into()),
+    ]
+    .into_iter()
+    .collect();
+    let program_spec = ProgramSpec::new(vec![1, 2], vec![4, 6]);
+    let mut machine = Compiler::compile_with_loader(
+        "
+        WHILE RLI > 0
+            READ RX0
+            CALL INC
+            CALL DOUBLE
+            WRITE RX0
+        END
+        HALT
+        .include \"lib/a.gdlk\"
+        .include \"common.gdlk\"
+        "
+        .into(),
+        HardwareSpec {
+            max_call_depth: 1,
+            ..Default::default()
+        },
+        &includes,
+    )
+    .unwrap()
+    .allocate(&program_spec)
+    .unwrap();
+
+    assert!(machine.execute_all().unwrap());
+    assert_eq!(machine.output(), program_spec.expected_output());
+}
+
 #[test]
 fn test_square_all() {
     assert_success!(
@@ -239,6 +971,7 @@ fn test_square_all() {
             num_registers: 1,
             num_stacks: 0,
             max_stack_length: 0,
+            ..Default::default()
         },
         ProgramSpec::new(
             vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
@@ -263,6 +996,7 @@ fn test_fibonacci() {
             num_registers: 4,
             num_stacks: 0,
             max_stack_length: 0,
+            ..Default::default()
         },
         ProgramSpec::new(vec![10], vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34]),
         "
@@ -289,6 +1023,7 @@ fn test_insertion_sort() {
             num_registers: 3,
             num_stacks: 2,
             max_stack_length: 16,
+            ..Default::default()
         },
         ProgramSpec::new(
             vec![9, 3, 8, 4, 5, 1, 3, 8, 9, 5, 2, 10, 4, 1, 8],
@@ -380,6 +1115,99 @@ fn test_cycle_count_jump() {
     assert_eq!(m1.cycle_count(), 13);
 }
 
+#[test]
+fn test_cycle_count_at_limit() {
+    // Using exactly the maximum number of cycles is allowed
+    let machine = assert_success!(
+        HardwareSpec {
+            max_cycles: 3,
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1], vec![1]),
+        "
+        READ RX0
+        ADD RX0 0
+        WRITE RX0
+        ",
+    );
+    assert_eq!(machine.cycle_count(), 3);
+}
+
+#[test]
+fn test_instruction_count_at_limit() {
+    // Labels, directives, and comments don't count as instructions
+    let machine = assert_success!(
+        HardwareSpec {
+            max_instructions: Some(3),
+            ..Default::default()
+        },
+        ProgramSpec::new(vec![1], vec![1]),
+        "
+        .const ZERO 0
+        START:
+        READ RX0
+        ADD RX0 ZERO ; not really necessary
+        WRITE RX0
+        END:
+        ",
+    );
+    assert_eq!(machine.instruction_count(), 3);
+    assert_eq!(machine.cycle_count(), 3);
+}
+
+#[test]
+fn test_cycle_count_register() {
+    // RCY includes the instruction that's currently executing
+    assert_success!(
+        HardwareSpec::default(),
+        ProgramSpec::new(vec![5], vec![2, 4]),
+        "
+        READ RX0    ; 1
+        WRITE RCY   ; 2
+        SET RX0 RCY ; 3
+        ADD RX0 1
+        WRITE RX0   ; 5
+        ",
+    );
+}
+
+#[test]
+fn test_rand() {
+    let hardware_spec = HardwareSpec {
+        value_width: ValueWidth::Bits8,
+        ..Default::default()
+    };
+    let source = "
+    RAND RX0
+    WRITE RX0
+    RAND RX0
+    WRITE RX0
+    RAND RX0
+    WRITE RX0
+    ";
+    let run = |seed: u32| -> Vec<LangValue> {
+        let mut program_spec = ProgramSpec::default();
+        program_spec.set_seed(seed);
+        let mut machine =
+            Compiler::compile(source.into(), hardware_spec.clone())
+                .unwrap()
+                .allocate(&program_spec)
+                .unwrap();
+        machine.execute_all().unwrap();
+        machine.output().to_vec()
+    };
+
+    // The same seed always gives the same values, which makes it possible
+    // for a program that uses RAND to succeed consistently
+    let output = run(1234);
+    assert_eq!(output.len(), 3);
+    assert_eq!(run(1234), output);
+    assert_ne!(run(4321), output);
+    let mut program_spec = ProgramSpec::new(vec![], output);
+    program_spec.set_seed(1234);
+    assert_success!(hardware_spec, program_spec, source);
+}
+
 #[test]
 fn test_execute_after_termination() {
     // Excuting after a normal termination returns false
@@ -80,20 +80,48 @@ const DocsPage: React.FC = () => {
       <DocsSection level={2} title="Language Reference">
         <DocsSection level={3} title="Values">
           <Typography>
-            All GDLK values are integers in the range{' '}
-            <code>[-32768, 32767]</code>. Encoding systems can be built on top
-            of these values, but all hardware operations are performed on these
+            All GDLK values are integers. By default, values are 16 bits,
+            meaning they fall in the range <code>[-32768, 32767]</code>, but
+            some hardware uses 8-bit (<code>[-128, 127]</code>) or 32-bit
+            values instead. Encoding systems can be built on top of these
+            values, but all hardware operations are performed on these
             integers.
           </Typography>
 
+          <Typography id="values--literals" variant="h4">
+            Literals
+          </Typography>
+          <Typography>
+            Values can be written in decimal (<code>65</code>), hexadecimal (
+            <code>0x41</code>), or binary (<code>0b1000001</code>), with an
+            optional leading <code>-</code>. Character literals like{' '}
+            <code>{"'A'"}</code> hold the Unicode code point of the character,
+            and support the escapes <code>\n</code>, <code>\r</code>,{' '}
+            <code>\t</code>, <code>\0</code>, <code>\\</code>,{' '}
+            <code>{"\\'"}</code>, and <code>{'\\"'}</code>. A literal that
+            does not fit in the value size of the hardware is a compile error.
+          </Typography>
+
+          <Typography>
+            Anywhere a constant value is accepted, you can also use an integer
+            expression, such as <code>3*4+1</code> or{' '}
+            <code>(LIMIT-1)%2</code>. Expressions support <code>+</code>,{' '}
+            <code>-</code>, <code>*</code>, <code>/</code>, <code>%</code>,
+            parentheses, and names defined with <code>.const</code>. They
+            cannot contain spaces. Expressions are evaluated when the program
+            is compiled, so overflow or division by zero is a compile error.
+          </Typography>
+
           <Typography id="values--overflow-and-underflow" variant="h4">
             Overflow & Underflow
           </Typography>
           <Typography>
-            When an arithmetic instruction causes a value to go above the max to
-            below the min, the value wraps around. For example,{' '}
-            <code>32767 + 1 = -32768</code>, and <code>-32768 - 1 = 32767</code>
-            .
+            By default, when an arithmetic instruction causes a value to go
+            above the max or below the min, the value wraps around. For
+            example, <code>32767 + 1 = -32768</code>, and{' '}
+            <code>-32768 - 1 = 32767</code>. Some hardware instead saturates
+            the value, i.e. clamps it to the max or min, or causes a runtime
+            error on overflow.
           </Typography>
         </DocsSection>
 
@@ -104,13 +132,16 @@ const DocsPage: React.FC = () => {
             Stacks are a high capacity form of value storage. They trade the
             easy accessibility and operability of registers for much higher
             capacity. Operations cannot be performed directly on any values in a
-            stack. The only instructions that operate on stacks are{' '}
+            stack. The main instructions that operate on stacks are{' '}
             <Link to="#instructions--push">PUSH</Link> and{' '}
             <Link to="#instructions--pop">POP</Link>. <code>PUSH</code> puts a
             new value on top of a stack, and <code>POP</code> removes the top
             value into a register. Only the top value of a stack is accessible;
             all others cannot be read until the values above them are popped
-            off.
+            off. The top values can also be inspected and rearranged with{' '}
+            <Link to="#instructions--peek">PEEK</Link>,{' '}
+            <Link to="#instructions--dup">DUP</Link>, and{' '}
+            <Link to="#instructions--swap">SWAP</Link>.
           </Typography>
 
           <Typography>
@@ -126,15 +157,45 @@ const DocsPage: React.FC = () => {
             access the current length of the corresponding stack.
           </Typography>
 
+          <Typography>
+            A stack can also be chosen at runtime, with the pattern{' '}
+            <code>S[RXx]</code>. This refers to the stack whose index is held
+            in the given register. For example, if <code>RX0</code> holds{' '}
+            <code>1</code>, then <code>S[RX0]</code> refers to{' '}
+            <code>S1</code>. If the index is not a valid stack, it will
+            trigger an error.
+          </Typography>
+
           <DocsSection level={4} title="Capacity">
             <Typography>
               Each stack has a fixed capacity, determined by the hardware spec.
+              Different stacks on the same machine may have different
+              capacities.
               Once a stack is at capacity, it can no longer be pushed onto. If a
               push is attempted, it will trigger an error.
             </Typography>
           </DocsSection>
         </DocsSection>
 
+        <DocsSection id="queues" level={3} title="Queues">
+          <Typography>
+            Queues are similar to stacks, except values are removed in the same
+            order that they were added. The only instructions that operate on
+            queues are <Link to="#instructions--enq">ENQ</Link> and{' '}
+            <Link to="#instructions--deq">DEQ</Link>. <code>ENQ</code> puts a
+            new value at the back of a queue, and <code>DEQ</code> removes the
+            front value into a register.
+          </Typography>
+
+          <Typography>
+            All queues are referenced by the naming pattern <code>Qx</code>,
+            where <code>x</code> starts at <code>0</code>. Each queue also has
+            a corresponding <Link to="#registers--rqx">RQx</Link> register,
+            which holds the current length of the queue. Like stacks, each
+            queue has a fixed capacity, determined by the hardware spec.
+          </Typography>
+        </DocsSection>
+
         <DocsSection id="input-and-output" level={3} title="Input & Output">
           <Typography>
             Programs interact with outside values via input and output. The
@@ -193,6 +254,56 @@ const DocsPage: React.FC = () => {
           </DocsSection>
         </DocsSection>
 
+        <DocsSection id="labels" level={3} title="Labels">
+          <Typography>
+            A label marks a position in the program, so that it can be the
+            target of a jump. Labels are declared with a name followed by a
+            colon, e.g. <code>LOOP:</code>. Label names must be unique.
+          </Typography>
+
+          <Typography>
+            A label that starts with a <code>.</code>, e.g.{' '}
+            <code>.loop:</code>, is local. Local labels belong to the closest
+            regular label above them, so the same local name can be reused
+            under different labels. A jump to <code>.loop</code> goes to the
+            local label in the current section.
+          </Typography>
+
+          <Typography>
+            A label made of only digits, e.g. <code>1:</code>, is anonymous,
+            and can be declared any number of times. Jump to{' '}
+            <code>1b</code> to go to the closest <code>1:</code> before the
+            jump, or <code>1f</code> to go to the closest one after it.
+          </Typography>
+        </DocsSection>
+
+        <DocsSection id="blocks" level={3} title="Blocks">
+          <Typography>
+            Most control flow is written with labels and jumps, but simple
+            conditions and loops can also be written as blocks. A block starts
+            with <code>IF</code> or <code>WHILE</code>, followed by a
+            comparison between two values, using one of <code>==</code>,{' '}
+            <code>!=</code>, <code>&lt;</code>, <code>&gt;</code>,{' '}
+            <code>&lt;=</code>, or <code>&gt;=</code>. The block ends with{' '}
+            <code>END</code>. An <code>IF</code> block can also have an{' '}
+            <code>ELSE</code> branch. Blocks can be nested.
+          </Typography>
+
+          <pre>
+            <code>
+              {
+                'WHILE RLI > 0\n  READ RX0\n  IF RX0 < 0\n    NEG RX0\n  END\n  WRITE RX0\nEND'
+              }
+            </code>
+          </pre>
+
+          <Typography>
+            Blocks are compiled into the equivalent jumps, so they cost the
+            same number of cycles as the jumps would. Some hardware does not
+            allow blocks.
+          </Typography>
+        </DocsSection>
+
         <InstructionDocs />
       </DocsSection>
     </Paper>
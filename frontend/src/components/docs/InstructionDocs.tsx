@@ -3,7 +3,7 @@ import { Typography } from '@material-ui/core';
 import Link from 'components/common/Link';
 import DocsSection from './DocsSection';
 
-type ArgType = 'VAL' | 'REG' | 'STACK' | 'LABEL';
+type ArgType = 'VAL' | 'REG' | 'STACK' | 'QUEUE' | 'LABEL';
 
 interface InstructionReference {
   name: string;
@@ -134,6 +134,86 @@ const INSTRUCTIONS: InstructionReference[] = [
       'DIV RX0 RX1 ; Divide the value in RX0 by the value in RX1',
     ],
   },
+  {
+    name: 'MOD',
+    summary: 'Divide a register by a value, and keep the remainder.',
+    moreInfo: (
+      <>
+        The result has the same sign as the original value of the register.
+        The result is stored in the register.
+      </>
+    ),
+    args: ['REG', 'VAL'],
+    errorCases: ['Dividing by zero causes a runtime error.'],
+    examples: [
+      'MOD RX0 3   ; RX0 now holds the remainder of RX0 / 3',
+      'MOD RX0 RX1 ; RX0 now holds the remainder of RX0 / RX1',
+    ],
+  },
+  {
+    name: 'NEG',
+    summary: 'Negate the value in a register.',
+    moreInfo: (
+      <>
+        The result is stored in the register. See{' '}
+        <Link to="#values--overflow-and-underflow">
+          arithmetic overflow and underflow
+        </Link>
+        .
+      </>
+    ),
+    args: ['REG'],
+    examples: ['SET RX0 3\nNEG RX0 ; RX0 now holds -3'],
+  },
+  {
+    name: 'AND',
+    summary: 'Bitwise AND a register with a value.',
+    moreInfo: 'The result is stored in the register.',
+    args: ['REG', 'VAL'],
+    examples: ['SET RX0 10\nAND RX0 12 ; RX0 now holds 8'],
+  },
+  {
+    name: 'OR',
+    summary: 'Bitwise OR a register with a value.',
+    moreInfo: 'The result is stored in the register.',
+    args: ['REG', 'VAL'],
+    examples: ['SET RX0 10\nOR RX0 12 ; RX0 now holds 14'],
+  },
+  {
+    name: 'XOR',
+    summary: 'Bitwise XOR a register with a value.',
+    moreInfo: 'The result is stored in the register.',
+    args: ['REG', 'VAL'],
+    examples: ['SET RX0 10\nXOR RX0 12 ; RX0 now holds 6'],
+  },
+  {
+    name: 'SHL',
+    summary: 'Shift the bits of a register left by a number of places.',
+    moreInfo: (
+      <>
+        Bits shifted off the end are thrown away. The shift amount wraps
+        around the number of bits in a value. The result is stored in the
+        register.
+      </>
+    ),
+    args: ['REG', 'VAL'],
+    examples: ['SET RX0 5\nSHL RX0 3 ; RX0 now holds 40'],
+  },
+  {
+    name: 'SHR',
+    summary: 'Shift the bits of a register right by a number of places.',
+    moreInfo: (
+      <>
+        The sign of the value is preserved. The shift amount wraps around the
+        number of bits in a value. The result is stored in the register.
+      </>
+    ),
+    args: ['REG', 'VAL'],
+    examples: [
+      'SET RX0 40\nSHR RX0 3 ; RX0 now holds 5',
+      'SET RX0 -6\nSHR RX0 1 ; RX0 now holds -3',
+    ],
+  },
   {
     name: 'CMP',
     summary: 'Compare two values, and put the output into a register.',
@@ -151,6 +231,19 @@ const INSTRUCTIONS: InstructionReference[] = [
       'CMP RX0 11 10 ; RX0 now holds 1',
     ],
   },
+  {
+    name: 'RAND',
+    summary: 'Set a register to a random value.',
+    moreInfo: (
+      <>
+        The value can be anywhere in the range of values supported by the
+        hardware. The random values are seeded by the program spec, so every run of the
+        same program gets the same sequence of values.
+      </>
+    ),
+    args: ['REG'],
+    examples: ['RAND RX0 ; RX0 now holds a random value'],
+  },
   {
     name: 'PUSH',
     summary: 'Push a value onto the top of a stack.',
@@ -174,13 +267,109 @@ const INSTRUCTIONS: InstructionReference[] = [
     errorCases: [<>Popping from an empty stack causes a runtime error.</>],
     examples: ['POP S0 RX0 ; Move the top value of S0 into RX0'],
   },
+  {
+    name: 'PEEK',
+    summary:
+      'Copy the top value of a stack into a register, without removing it.',
+    args: ['STACK', 'REG'],
+    errorCases: [<>Peeking at an empty stack causes a runtime error.</>],
+    examples: ['PEEK S0 RX0 ; Copy the top value of S0 into RX0'],
+  },
+  {
+    name: 'DUP',
+    summary: 'Push a copy of the top value of a stack onto the same stack.',
+    args: ['STACK'],
+    errorCases: [
+      <>Duplicating on an empty stack causes a runtime error.</>,
+      <>Duplicating on a stack that is at capacity causes a runtime error.</>,
+    ],
+    examples: ['DUP S0 ; The top two values of S0 are now the same'],
+  },
+  {
+    name: 'SWAP',
+    summary: 'Swap the top two values of a stack.',
+    args: ['STACK'],
+    errorCases: [
+      <>
+        Swapping on a stack with fewer than two values causes a runtime error.
+      </>,
+    ],
+    examples: ['PUSH 1 S0\nPUSH 2 S0\nSWAP S0 ; 1 is now on top of S0'],
+  },
+  {
+    name: 'ENQ',
+    summary: 'Add a value to the back of a queue.',
+    args: ['VAL', 'QUEUE'],
+    errorCases: [
+      <>Adding to a queue that is at capacity causes a runtime error.</>,
+    ],
+    examples: [
+      'ENQ RX0 Q0 ; Add the value in RX0 to the back of Q0',
+      'ENQ 10 Q0 ; Add 10 to the back of Q0',
+    ],
+  },
+  {
+    name: 'DEQ',
+    summary: 'Remove the value at the front of a queue into a register.',
+    args: ['QUEUE', 'REG'],
+    errorCases: [<>Removing from an empty queue causes a runtime error.</>],
+    examples: ['DEQ Q0 RX0 ; Move the front value of Q0 into RX0'],
+  },
+  {
+    name: 'LOAD',
+    summary: 'Load the value at a memory address into a register.',
+    args: ['REG', 'VAL'],
+    errorCases: [
+      <>
+        Loading from an address that is negative or past the end of memory
+        causes a runtime error.
+      </>,
+    ],
+    examples: [
+      'LOAD RX0 3 ; Move the value at address 3 into RX0',
+      'LOAD RX0 RX1 ; Move the value at the address in RX1 into RX0',
+    ],
+  },
+  {
+    name: 'STORE',
+    summary: 'Store a value at a memory address.',
+    args: ['VAL', 'VAL'],
+    errorCases: [
+      <>
+        Storing to an address that is negative or past the end of memory
+        causes a runtime error.
+      </>,
+    ],
+    examples: [
+      'STORE RX0 3 ; Copy the value in RX0 to address 3',
+      'STORE 10 RX1 ; Put 10 at the address in RX1',
+    ],
+  },
   {
     name: 'JMP',
     summary: 'Jump to a label, unconditionally.',
+    moreInfo: (
+      <>
+        Instead of a label, the target can also be a register, a constant, a
+        constant expression, or a name defined with <code>.const</code> or{' '}
+        <code>.alias</code>. In that case, the value is the index of the
+        instruction to jump to,
+        starting at <code>0</code>. Combined with the{' '}
+        <Link to="#registers--rpc">RPC</Link> register, this can be used to
+        build jump tables.
+      </>
+    ),
     args: ['LABEL'],
+    errorCases: [
+      <>
+        Jumping to a value that is not the index of an instruction causes a
+        runtime error.
+      </>,
+    ],
     examples: [
       'JMP END\nREAD RX0 ; This instruction will be skipped\nEND:',
       'LOOP:\nADD RX0 1\nJMP LOOP ; Infinite loop',
+      'SET RX0 RPC\nADD RX0 3\nJMP RX0 ; Jumps to the WRITE\nWRITE 1',
     ],
   },
   {
@@ -235,6 +424,108 @@ const INSTRUCTIONS: InstructionReference[] = [
       'SET RX0 1\nJLZ END\nREAD RX0 ; This instruction will NOT be skipped\nEND:',
     ],
   },
+  {
+    name: 'JEQ',
+    summary: 'Jump to a label if the first value is equal to the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JEQ RX0 3 END ; Jump if RX0 is 3'],
+  },
+  {
+    name: 'JNE',
+    summary: 'Jump to a label if the first value is NOT equal to the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JNE RX0 RX1 END ; Jump if RX0 and RX1 differ'],
+  },
+  {
+    name: 'JLT',
+    summary: 'Jump to a label if the first value is less than the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JLT RX0 10 LOOP ; Jump while RX0 is below 10'],
+  },
+  {
+    name: 'JGT',
+    summary: 'Jump to a label if the first value is greater than the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JGT RX0 RX1 END ; Jump if RX0 is bigger than RX1'],
+  },
+  {
+    name: 'JLE',
+    summary: 'Jump to a label if the first value is less than or equal to the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JLE RX0 0 END ; Jump if RX0 is not positive'],
+  },
+  {
+    name: 'JGE',
+    summary: 'Jump to a label if the first value is greater than or equal to the second.',
+    args: ['VAL', 'VAL', 'LABEL'],
+    errorCases: [
+      'Using this instruction on hardware that disables two-operand jumps causes a compile error.',
+    ],
+    examples: ['JGE RS0 5 END ; Jump if S0 has at least 5 values'],
+  },
+  {
+    name: 'CALL',
+    summary: 'Call the subroutine that starts at a label.',
+    moreInfo: (
+      <>
+        Execution jumps to the label. The next <code>RET</code> will resume
+        execution at the instruction after the <code>CALL</code>.
+      </>
+    ),
+    args: ['LABEL'],
+    errorCases: [
+      <>
+        Calling when the maximum call depth of the hardware has been reached
+        causes a runtime error.
+      </>,
+    ],
+    examples: [
+      'CALL DOUBLE ; RX0 is now doubled\nJMP END\nDOUBLE:\nADD RX0 RX0\nRET\nEND:',
+    ],
+  },
+  {
+    name: 'RET',
+    summary: 'Return from the current subroutine.',
+    moreInfo: (
+      <>
+        Execution resumes at the instruction after the most recent{' '}
+        <code>CALL</code>.
+      </>
+    ),
+    args: [],
+    errorCases: [
+      <>Returning while not inside a subroutine causes a runtime error.</>,
+    ],
+    examples: ['DOUBLE:\nADD RX0 RX0\nRET ; Go back to the caller'],
+  },
+  {
+    name: 'HALT',
+    summary: 'Stop the program immediately.',
+    moreInfo: (
+      <>
+        This is treated exactly the same as reaching the end of the program,
+        so the program is successful if the input is empty and the output
+        matches the expected output.
+      </>
+    ),
+    args: [],
+    examples: ['JEZ RLI DONE\nREAD RX0\nDONE:\nHALT'],
+  },
 ];
 
 /**
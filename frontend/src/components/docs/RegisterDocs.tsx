@@ -29,6 +29,26 @@ const REGISTERS: Register[] = [
       </>
     ),
   },
+  {
+    name: 'RPC',
+    writable: false,
+    summary: (
+      <>
+        Always available. Holds the index of the instruction that is currently
+        executing, starting at <code>0</code>.
+      </>
+    ),
+  },
+  {
+    name: 'RCY',
+    writable: false,
+    summary: (
+      <>
+        Always available. Holds the number of cycles that have been executed so
+        far, including the instruction that is currently executing.
+      </>
+    ),
+  },
   {
     name: 'RSx',
     writable: false,
@@ -40,6 +60,17 @@ const REGISTERS: Register[] = [
       </>
     ),
   },
+  {
+    name: 'RQx',
+    writable: false,
+    summary: (
+      <>
+        Holds the current number of values in the corresponding queue. There is
+        one of these for each queue in the machine. Queues start at{' '}
+        <code>Q0</code>, so these start at <code>RQ0</code>.
+      </>
+    ),
+  },
   {
     name: 'RXx',
     writable: true,
@@ -73,6 +104,15 @@ const RegisterDocs: React.FC = () => {
         some registers can be written to. Write behavior varies by register.
       </Typography>
 
+      <Typography>
+        Registers can also be referenced indirectly, by wrapping another
+        register in brackets. <code>[RX0]</code> refers to the{' '}
+        <code>RXx</code> register whose index is held in <code>RX0</code>, so
+        if <code>RX0</code> holds <code>2</code>, then <code>[RX0]</code>{' '}
+        refers to <code>RX2</code>. Indirect registers can always be written
+        to. If the index is not a valid register, it will trigger an error.
+      </Typography>
+
       <table>
         <thead>
           <tr>
@@ -14,6 +14,27 @@ const HardwareSpecSummary: React.FC<{
         { label: 'Registers', value: hardwareSpec.numRegisters },
         { label: 'Stacks', value: hardwareSpec.numStacks },
         { label: 'Stack Size', value: hardwareSpec.maxStackLength },
+        { label: 'Max Cycles', value: hardwareSpec.maxCycles },
+        { label: 'Memory Size', value: hardwareSpec.memorySize },
+        {
+          label: 'Stack Sizes',
+          value: hardwareSpec.stackLengths.join(', ') || 'Default',
+        },
+        { label: 'Max Call Depth', value: hardwareSpec.maxCallDepth },
+        { label: 'Queues', value: hardwareSpec.numQueues },
+        { label: 'Queue Size', value: hardwareSpec.maxQueueLength },
+        {
+          label: 'Max Instructions',
+          value: hardwareSpec.maxInstructions ?? 'None',
+        },
+        {
+          label: 'Allowed Instructions',
+          value: hardwareSpec.allowedInstructions?.join(', ') ?? 'All',
+        },
+        {
+          label: 'Disallowed Instructions',
+          value: hardwareSpec.disallowedInstructions.join(', ') || 'None',
+        },
       ]}
     />
   );
@@ -25,6 +46,15 @@ export default createFragmentContainer(HardwareSpecSummary, {
       numRegisters
       numStacks
       maxStackLength
+      maxCycles
+      memorySize
+      stackLengths
+      maxCallDepth
+      numQueues
+      maxQueueLength
+      maxInstructions
+      allowedInstructions
+      disallowedInstructions
     }
   `,
 });
@@ -0,0 +1,47 @@
+import React, { useContext } from 'react';
+import { IdeContext } from 'state/ide';
+import { makeStyles, Typography } from '@material-ui/core';
+import BufferDisplay from './BufferDisplay';
+import clsx from 'clsx';
+
+const useLocalStyles = makeStyles(({ palette, spacing }) => ({
+  memoryInfo: {
+    display: 'flex',
+    flexDirection: 'column',
+    padding: spacing(1),
+    backgroundColor: palette.background.default,
+    height: '100%',
+  },
+  memory: {
+    flexGrow: 1,
+    maxHeight: '100%',
+  },
+}));
+
+const MemoryInfo: React.FC<{
+  className?: string;
+}> = ({ className }) => {
+  const localClasses = useLocalStyles();
+  const { wasmHardwareSpec, compiledState } = useContext(IdeContext);
+  const machineState =
+    compiledState?.type === 'compiled' ? compiledState.machineState : undefined;
+
+  if (wasmHardwareSpec.memory_size === 0) {
+    return null;
+  }
+
+  return (
+    <div className={clsx(localClasses.memoryInfo, className)}>
+      <Typography variant="h3">Memory</Typography>
+
+      <BufferDisplay
+        className={localClasses.memory}
+        label="MEM"
+        values={machineState?.memory ?? []}
+        maxLength={wasmHardwareSpec.memory_size}
+      />
+    </div>
+  );
+};
+
+export default MemoryInfo;
@@ -8,6 +8,8 @@ import RegistersInfo from './RegistersInfo';
 import { IdeContextType, IdeContext, CompiledState } from 'state/ide';
 import IoInfo from './IoInfo';
 import StackInfo from './StackInfo';
+import QueueInfo from './QueueInfo';
+import MemoryInfo from './MemoryInfo';
 import IdeControls from './IdeControls';
 import ProgramStatus from './ProgramStatus';
 import { CompileResult, CompilerWrapper } from 'util/compile';
@@ -27,12 +29,12 @@ const useLocalStyles = makeStyles(({ palette, spacing }) => {
       height: '100%',
       display: 'grid',
       gridTemplateRows: 'auto auto 1fr 1fr',
-      gridTemplateColumns: 'auto 1fr auto auto',
+      gridTemplateColumns: 'auto 1fr auto auto auto auto',
       gridTemplateAreas: `
-      'io rg rg sk'
-      'io st ct sk'
-      'io ed ed sk'
-      'io ed ed sk'
+      'io rg rg sk qu mm'
+      'io st ct sk qu mm'
+      'io ed ed sk qu mm'
+      'io ed ed sk qu mm'
       `,
       border,
     },
@@ -63,6 +65,14 @@ const useLocalStyles = makeStyles(({ palette, spacing }) => {
       gridArea: 'sk',
       padding: spacing(1),
     },
+    queueInfo: {
+      gridArea: 'qu',
+      padding: spacing(1),
+    },
+    memoryInfo: {
+      gridArea: 'mm',
+      padding: spacing(1),
+    },
   };
 });
 
@@ -87,21 +97,31 @@ const ProgramIde: React.FC<{
   // with the whole component tree. They are pointers and therefore updates
   // won't trigger re-renders, but these values shouldn't be changing while
   // this component tree is mounted anyway.
-  const wasmHardwareSpec = useStaticValue(
-    () =>
-      new HardwareSpec(
-        hardwareSpec.numRegisters,
-        hardwareSpec.numStacks,
-        hardwareSpec.maxStackLength
-      )
-  );
-  const wasmProgramSpec = useStaticValue(
-    () =>
-      new ProgramSpec(
-        Int16Array.from(programSpec.input),
-        Int16Array.from(programSpec.expectedOutput)
-      )
-  );
+  const wasmHardwareSpec = useStaticValue(() => {
+    const spec = new HardwareSpec(
+      hardwareSpec.numRegisters,
+      hardwareSpec.numStacks,
+      hardwareSpec.maxStackLength
+    );
+    spec.max_cycles = hardwareSpec.maxCycles;
+    spec.memory_size = hardwareSpec.memorySize;
+    spec.max_call_depth = hardwareSpec.maxCallDepth;
+    spec.num_queues = hardwareSpec.numQueues;
+    spec.max_queue_length = hardwareSpec.maxQueueLength;
+    spec.max_instructions = hardwareSpec.maxInstructions ?? undefined;
+    spec.setStackLengths(Uint32Array.from(hardwareSpec.stackLengths));
+    spec.setAllowedInstructions(hardwareSpec.allowedInstructions);
+    spec.setDisallowedInstructions(hardwareSpec.disallowedInstructions);
+    return spec;
+  });
+  const wasmProgramSpec = useStaticValue(() => {
+    const spec = new ProgramSpec(
+      Int32Array.from(programSpec.input),
+      Int32Array.from(programSpec.expectedOutput)
+    );
+    spec.seed = programSpec.seed;
+    return spec;
+  });
 
   // This wasm value is NOT safe to share outside this component. It's stored
   // in a ref because it contains pointers, which often don't reflect changed
@@ -238,6 +258,8 @@ const ProgramIde: React.FC<{
           userProgram={userProgram}
         />
         <StackInfo className={localClasses.stackInfo} />
+        <QueueInfo className={localClasses.queueInfo} />
+        <MemoryInfo className={localClasses.memoryInfo} />
         <CodeEditor className={localClasses.editor} />
 
         {/* Prompt on exit for unsaved changes */}
@@ -277,10 +299,20 @@ export default createFragmentContainer(ProgramIdeWrapper, {
       numRegisters
       numStacks
       maxStackLength
+      maxCycles
+      memorySize
+      stackLengths
+      maxCallDepth
+      numQueues
+      maxQueueLength
+      maxInstructions
+      allowedInstructions
+      disallowedInstructions
       programSpec(slug: $programSlug) {
         id
         input
         expectedOutput
+        seed
         userProgram(fileName: $fileName) {
           id
           sourceCode
@@ -18,6 +18,7 @@ const ProgramStatus: React.FC<{ className?: string }> = ({ className }) => {
 
   return (
     <div className={clsx(className, localClasses.programStatus)}>
+      <div>Instructions: {machineState?.instructionCount ?? '-'}</div>
       <div>CPU Cycles: {machineState?.cycleCount ?? '-'}</div>
       {machineState?.terminated && (
         <div>{machineState?.successful ? 'SUCCESS' : 'FAILURE'}</div>
@@ -0,0 +1,56 @@
+import React, { useContext } from 'react';
+import { IdeContext } from 'state/ide';
+import { makeStyles, Typography } from '@material-ui/core';
+import BufferDisplay from './BufferDisplay';
+import clsx from 'clsx';
+
+const useLocalStyles = makeStyles(({ palette, spacing }) => ({
+  queueInfo: {
+    display: 'flex',
+    flexDirection: 'column',
+    padding: spacing(1),
+    backgroundColor: palette.background.default,
+    height: '100%',
+  },
+  queues: {
+    display: 'flex',
+    flexGrow: 1,
+    maxHeight: '100%',
+  },
+  queue: {
+    maxHeight: '100%',
+  },
+}));
+
+const QueueInfo: React.FC<{
+  className?: string;
+}> = ({ className }) => {
+  const localClasses = useLocalStyles();
+  const { wasmHardwareSpec, compiledState } = useContext(IdeContext);
+  const machineState =
+    compiledState?.type === 'compiled' ? compiledState.machineState : undefined;
+
+  if (wasmHardwareSpec.num_queues === 0) {
+    return null;
+  }
+
+  return (
+    <div className={clsx(localClasses.queueInfo, className)}>
+      <Typography variant="h3">Queues</Typography>
+
+      <div className={localClasses.queues}>
+        {wasmHardwareSpec.queues.map((name) => (
+          <BufferDisplay
+            className={localClasses.queue}
+            key={name}
+            label={name}
+            values={machineState?.queues[name] ?? []}
+            maxLength={wasmHardwareSpec.max_queue_length}
+          />
+        ))}
+      </div>
+    </div>
+  );
+};
+
+export default QueueInfo;
@@ -39,14 +39,14 @@ const StackInfo: React.FC<{
       <Typography variant="h3">Stacks</Typography>
 
       <div className={localClasses.stacks}>
-        {wasmHardwareSpec.stacks.map((name) => (
+        {wasmHardwareSpec.stacks.map((name, i) => (
           <BufferDisplay
             className={localClasses.stack}
             key={name}
             invert
             label={name}
             values={machineState?.stacks[name] ?? []}
-            maxLength={wasmHardwareSpec.max_stack_length}
+            maxLength={wasmHardwareSpec.stackCapacity(i)}
           />
         ))}
       </div>
@@ -31,6 +31,9 @@ export interface MachineState {
   output: LangValue[];
   registers: Record<string, LangValue>;
   stacks: Record<string, LangValue[]>;
+  queues: Record<string, LangValue[]>;
+  memory: LangValue[];
+  instructionCount: number;
   cycleCount: number;
   terminated: boolean;
   successful: boolean;
@@ -63,6 +66,9 @@ export class MachineWrapper {
       output: Array.from(machine.output),
       registers: machine.registers,
       stacks: machine.stacks,
+      queues: machine.queues,
+      memory: Array.from(machine.memory),
+      instructionCount: machine.instructionCount,
       cycleCount: machine.cycleCount,
       terminated: machine.terminated,
       successful: machine.successful,
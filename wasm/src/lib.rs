@@ -5,6 +5,7 @@ pub use gdlk::{
     ast::{compiled::Program, wasm::SourceElement, LangValue},
     Compiler, HardwareSpec, Machine, ProgramSpec, Span,
 };
+use std::collections::HashMap;
 use wasm_bindgen::{prelude::*, JsCast};
 
 #[wasm_bindgen]
@@ -12,6 +13,7 @@ use wasm_bindgen::{prelude::*, JsCast};
 pub struct CompileSuccess {
     program: Program<Span>,
     machine: Machine,
+    warnings: Vec<SourceElement>,
 }
 
 #[wasm_bindgen]
@@ -44,6 +46,15 @@ impl CompileSuccess {
     pub fn machine(&self) -> Machine {
         self.machine.clone()
     }
+
+    /// Get the lint warnings for the program. These don't stop the program
+    /// from running, but they probably point to mistakes.
+    #[wasm_bindgen(getter)]
+    pub fn warnings(&self) -> SourceElementArray {
+        JsValue::from_serde(&self.warnings)
+            .unwrap()
+            .unchecked_into()
+    }
 }
 
 /// Compile a program under the given specifications. This takes in references
@@ -54,11 +65,49 @@ pub fn compile(
     program_spec: &ProgramSpec,
     source: &str,
 ) -> Result<CompileSuccess, JsValue> {
-    match Compiler::compile(source.to_string(), *hardware_spec) {
+    compile_with_includes(hardware_spec, program_spec, source, &JsValue::NULL)
+}
+
+/// Compile a program, like [compile], but also provide the files that can be
+/// pulled in with `.include`. `includes` should be an object that maps each
+/// path to its source code, or `null`/`undefined` if there are no included
+/// files.
+#[wasm_bindgen(js_name = "compileWithIncludes")]
+pub fn compile_with_includes(
+    hardware_spec: &HardwareSpec,
+    program_spec: &ProgramSpec,
+    source: &str,
+    includes: &JsValue,
+) -> Result<CompileSuccess, JsValue> {
+    let includes: HashMap<String, String> =
+        if includes.is_null() || includes.is_undefined() {
+            HashMap::new()
+        } else {
+            includes
+                .into_serde()
+                .map_err(|err| JsValue::from_str(&err.to_string()))?
+        };
+    match Compiler::compile_with_loader(
+        source.to_string(),
+        hardware_spec.clone(),
+        &includes,
+    ) {
         Ok(compiler) => {
             let program = compiler.program().clone();
-            let machine = compiler.allocate(program_spec);
-            Ok(CompileSuccess { program, machine })
+            let warnings = compiler
+                .warnings()
+                .errors()
+                .iter()
+                .map(SourceElement::from)
+                .collect();
+            let machine = compiler
+                .allocate(program_spec)
+                .map_err(|err| JsValue::from_str(&err.to_string()))?;
+            Ok(CompileSuccess {
+                program,
+                machine,
+                warnings,
+            })
         }
         Err(err) => {
             let errors: Vec<SourceElement> =
@@ -67,3 +116,10 @@ pub fn compile(
         }
     }
 }
+
+/// Format source code into the canonical style. This never fails, even if the
+/// source has syntax errors.
+#[wasm_bindgen(js_name = "formatSource")]
+pub fn format_source(source: &str) -> String {
+    gdlk::format_source(source)
+}
@@ -2,11 +2,14 @@
 
 #![deny(clippy::all)]
 
+use gdlk::InstructionKind;
 use gdlk_wasm::{
-    compile, HardwareSpec, LangValue, ProgramSpec, SourceElement, Span,
+    compile, format_source, HardwareSpec, LangValue, ProgramSpec,
+    SourceElement, Span,
 };
 use maplit::hashmap;
 use std::collections::HashMap;
+use wasm_bindgen::JsValue;
 use wasm_bindgen_test::wasm_bindgen_test;
 
 /// Checks each portion of the given machine's state, and compares each field
@@ -57,6 +60,7 @@ fn test_compile_success() {
             num_registers: 1,
             num_stacks: 2,
             max_stack_length: 10,
+            ..Default::default()
         },
         &ProgramSpec::new(vec![1], vec![1]),
         "
@@ -78,7 +82,8 @@ fn test_compile_success() {
                     start_line: 2,
                     start_col: 9,
                     end_line: 2,
-                    end_col: 17
+                    end_col: 17,
+                    file: 0,
                 }
             },
             SourceElement {
@@ -89,7 +94,8 @@ fn test_compile_success() {
                     start_line: 3,
                     start_col: 9,
                     end_line: 3,
-                    end_col: 18
+                    end_col: 18,
+                    file: 0,
                 }
             }
         ]
@@ -104,7 +110,7 @@ fn test_compile_success() {
         cycle_count = 0,
         terminated = false,
         successful = false,
-        input = &[1i16],
+        input = &[1],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 1,
@@ -146,6 +152,7 @@ fn test_compile_errors() {
                     start_col: 14,
                     end_line: 2,
                     end_col: 17,
+                    file: 0,
                 }
             },
             SourceElement {
@@ -158,12 +165,48 @@ fn test_compile_errors() {
                     start_col: 16,
                     end_line: 3,
                     end_col: 18,
+                    file: 0,
                 }
             }
         ]
     );
 }
 
+#[wasm_bindgen_test]
+fn test_compile_warnings() {
+    let result = compile(
+        &HardwareSpec::default(),
+        &ProgramSpec::new(vec![1], vec![]),
+        "READ RZR",
+    );
+
+    let warnings = result.unwrap().warnings();
+    assert_eq!(
+        warnings.into_serde::<Vec<SourceElement>>().unwrap(),
+        vec![SourceElement {
+            text: "Lint warning at 1:6: Input read into `RZR` is thrown away"
+                .into(),
+            span: Span {
+                offset: 5,
+                length: 3,
+                start_line: 1,
+                start_col: 6,
+                end_line: 1,
+                end_col: 9,
+                file: 0,
+            }
+        }]
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_format_source() {
+    assert_eq!(
+        format_source("loop:\nread rx0 ; in\nwrite rx0 ; out\njmp loop\n"),
+        "loop:\n    READ RX0  ; in\n    WRITE RX0 ; out\n    JMP loop\n"
+    );
+}
+
 #[allow(clippy::cognitive_complexity)]
 #[wasm_bindgen_test]
 fn test_execute() {
@@ -172,6 +215,7 @@ fn test_execute() {
             num_registers: 1,
             num_stacks: 1,
             max_stack_length: 10,
+            ..Default::default()
         },
         &ProgramSpec::new(vec![1, 2, 3], vec![1, 2, 3]),
         "
@@ -195,7 +239,7 @@ fn test_execute() {
         cycle_count = 0,
         terminated = false,
         successful = false,
-        input = &[1i16, 2i16, 3i16],
+        input = &[1, 2, 3],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 3,
@@ -216,7 +260,7 @@ fn test_execute() {
         cycle_count = 1,
         terminated = false,
         successful = false,
-        input = &[1i16, 2i16, 3i16],
+        input = &[1, 2, 3],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 3,
@@ -237,7 +281,7 @@ fn test_execute() {
         cycle_count = 2,
         terminated = false,
         successful = false,
-        input = &[2i16, 3i16],
+        input = &[2, 3],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 2,
@@ -258,7 +302,7 @@ fn test_execute() {
         cycle_count = 3,
         terminated = false,
         successful = false,
-        input = &[2i16, 3i16],
+        input = &[2, 3],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 2,
@@ -279,7 +323,7 @@ fn test_execute() {
         cycle_count = 4,
         terminated = false,
         successful = false,
-        input = &[2i16, 3i16],
+        input = &[2, 3],
         output = &[],
         registers = hashmap! {
             "RLI".into() => 2,
@@ -300,8 +344,8 @@ fn test_execute() {
         cycle_count = 5,
         terminated = false,
         successful = false,
-        input = &[2i16, 3i16],
-        output = &[1i16],
+        input = &[2, 3],
+        output = &[1],
         registers = hashmap! {
             "RLI".into() => 2,
             "RS0".into() => 0,
@@ -321,8 +365,8 @@ fn test_execute() {
         cycle_count = 6,
         terminated = false,
         successful = false,
-        input = &[2i16, 3i16],
-        output = &[1i16],
+        input = &[2, 3],
+        output = &[1],
         registers = hashmap! {
             "RLI".into() => 2,
             "RS0".into() => 0,
@@ -347,7 +391,7 @@ fn test_execute() {
         terminated = true,
         successful = true,
         input = &[],
-        output = &[1i16, 2i16, 3i16],
+        output = &[1, 2, 3],
         registers = hashmap! {
             "RLI".into() => 0,
             "RS0".into() => 0,
@@ -392,8 +436,69 @@ fn test_runtime_error() {
                 start_line: 1,
                 start_col: 1,
                 end_line: 1,
-                end_col: 9
+                end_col: 9,
+                file: 0,
             }
         })
     );
 }
+
+#[wasm_bindgen_test]
+fn test_memory() {
+    let result = compile(
+        &HardwareSpec {
+            num_registers: 2,
+            memory_size: 3,
+            ..Default::default()
+        },
+        &ProgramSpec::default(),
+        "
+        SET RX0 5
+        SET RX1 2
+        STORE RX0 RX1
+        ",
+    );
+
+    let mut machine = result.unwrap().machine();
+    assert_eq!(machine.wasm_memory(), vec![0, 0, 0]);
+    assert!(machine.wasm_execute_all());
+    assert_eq!(machine.wasm_memory(), vec![0, 0, 5]);
+}
+
+#[wasm_bindgen_test]
+fn test_set_instructions() {
+    // Names are case-insensitive, just like in the API
+    let mut hardware_spec = HardwareSpec::default();
+    hardware_spec
+        .wasm_set_allowed_instructions(
+            JsValue::from_serde(&["read", "Write", "ADD"]).unwrap(),
+        )
+        .unwrap();
+    hardware_spec
+        .wasm_set_disallowed_instructions(
+            JsValue::from_serde(&["add"]).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        hardware_spec.allowed_instructions,
+        Some(vec![
+            InstructionKind::Read,
+            InstructionKind::Write,
+            InstructionKind::Add
+        ])
+    );
+    assert_eq!(
+        hardware_spec.disallowed_instructions,
+        vec![InstructionKind::Add]
+    );
+
+    assert!(hardware_spec
+        .wasm_set_disallowed_instructions(
+            JsValue::from_serde(&["nope"]).unwrap(),
+        )
+        .is_err());
+    assert!(hardware_spec
+        .wasm_set_allowed_instructions(JsValue::NULL)
+        .is_ok());
+    assert_eq!(hardware_spec.allowed_instructions, None);
+}